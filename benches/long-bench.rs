@@ -1,9 +1,12 @@
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+#[cfg(feature = "gui")]
 use femtovg::Color;
+#[cfg(feature = "gui")]
 use grav::{
     helpers::{init_canvas, wgpu::create_canvas},
-    App, Orbital,
+    App, OrbitalApp,
 };
+use grav::{Orbital, Simulation};
 
 // bench a call of the "run" function with many ticks already calculated
 fn long_run_bench(c: &mut Criterion) {
@@ -25,6 +28,8 @@ fn long_run_bench(c: &mut Criterion) {
     });
 }
 
+// drawing needs the window, so it only runs with the gui feature
+#[cfg(feature = "gui")]
 fn long_draw_bench(c: &mut Criterion) {
     let (mut canvas, _, _, _) = spin_on::spin_on(create_canvas(1600, 1000, "benching"));
 
@@ -33,11 +38,12 @@ fn long_draw_bench(c: &mut Criterion) {
     c.bench_function("long_draw_bench", |b| {
         b.iter_batched_ref(
             || {
-                let mut app = Orbital::new();
-                app.load_preset(1);
-                app.start();
+                let mut app = OrbitalApp::new();
+                app.sim.load_preset(1);
+                app.sim.start();
+                // on this thread, the app would run it in the background
                 for _ in 0..10 {
-                    app.run();
+                    app.sim.run();
                 }
                 app
             },
@@ -50,5 +56,8 @@ fn long_draw_bench(c: &mut Criterion) {
     });
 }
 
+#[cfg(feature = "gui")]
 criterion_group!(benches, long_run_bench, long_draw_bench);
+#[cfg(not(feature = "gui"))]
+criterion_group!(benches, long_run_bench);
 criterion_main!(benches);
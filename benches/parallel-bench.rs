@@ -0,0 +1,50 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use grav::{app::orbital::generators::ClusterSettings, Orbital, Simulation};
+
+// the same run on one thread and on several, only faster with the parallel feature
+fn compare(c: &mut Criterion, name: &str, num_ticks: i32, setup: impl Fn(&mut Orbital)) {
+    let mut group = c.benchmark_group(name);
+    for (label, parallel) in [("serial", false), ("parallel", true)] {
+        group.bench_function(label, |b| {
+            b.iter_batched_ref(
+                || {
+                    let mut app = Orbital::new();
+                    setup(&mut app);
+                    app.num_ticks = num_ticks;
+                    app.parallel = parallel;
+                    app.prepare();
+                    app.start();
+                    app
+                },
+                |app| app.run(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+// one sibling group of many bodies that all attract each other
+fn cluster_bench(c: &mut Criterion) {
+    compare(c, "star_cluster_1000", 10, |app| {
+        app.load_cluster(&ClusterSettings {
+            count: 1000,
+            ..Default::default()
+        })
+    });
+}
+
+// thousands of test particles attracted by two galaxy cores
+fn galaxy_bench(c: &mut Criterion) {
+    compare(c, "galaxy_collision", 100, |app| {
+        let preset = app
+            .presets()
+            .iter()
+            .position(|p| p.name == "Galaxy collision")
+            .expect("no galaxy collision preset");
+        app.load_preset(preset);
+    });
+}
+
+criterion_group!(benches, cluster_bench, galaxy_bench);
+criterion_main!(benches);
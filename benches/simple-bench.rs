@@ -1,9 +1,12 @@
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+#[cfg(feature = "gui")]
 use femtovg::Color;
+#[cfg(feature = "gui")]
 use grav::{
     helpers::{init_canvas, wgpu::create_canvas},
-    App, Orbital,
+    App, OrbitalApp,
 };
+use grav::{Orbital, Simulation};
 
 // bench the first call of the "run" function
 fn run_bench(c: &mut Criterion) {
@@ -21,6 +24,8 @@ fn run_bench(c: &mut Criterion) {
     });
 }
 
+// drawing needs the window, so it only runs with the gui feature
+#[cfg(feature = "gui")]
 fn draw_bench(c: &mut Criterion) {
     let (mut canvas, _, _, _) = spin_on::spin_on(create_canvas(1600, 1000, "benching"));
 
@@ -29,9 +34,9 @@ fn draw_bench(c: &mut Criterion) {
     c.bench_function("draw_bench", |b| {
         b.iter_batched_ref(
             || {
-                let mut app = Orbital::new();
-                app.load_preset(1);
-                app.start();
+                let mut app = OrbitalApp::new();
+                app.sim.load_preset(1);
+                app.sim.start();
                 app.run();
                 app.run();
                 app
@@ -45,5 +50,8 @@ fn draw_bench(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, run_bench, draw_bench,);
+#[cfg(feature = "gui")]
+criterion_group!(benches, run_bench, draw_bench);
+#[cfg(not(feature = "gui"))]
+criterion_group!(benches, run_bench);
 criterion_main!(benches);
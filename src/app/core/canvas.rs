@@ -0,0 +1,94 @@
+// Drawing to femtovg's canvas in the window. The canvas's transform is the zoom and pan.
+
+use femtovg::{Canvas, Color, Paint, Renderer};
+
+use super::{
+    draw::{DrawTarget, Path, PathCommand, TextAlign, TextBaseline, TextStyle},
+    physics::Position,
+};
+
+pub fn get_scale<T: Renderer>(canvas: &Canvas<T>) -> f32 {
+    let transform_matrix = canvas.transform().0;
+    let scale_opt = transform_matrix.first();
+
+    match scale_opt {
+        Some(scale) => *scale,
+        None => 1.,
+    }
+}
+
+fn femtovg_path(path: &Path) -> femtovg::Path {
+    let mut femtovg_path = femtovg::Path::new();
+    for command in path.commands.iter() {
+        match *command {
+            PathCommand::MoveTo(x, y) => femtovg_path.move_to(x, y),
+            PathCommand::LineTo(x, y) => femtovg_path.line_to(x, y),
+            PathCommand::Circle(x, y, r) => femtovg_path.circle(x, y, r),
+            PathCommand::Rect(x, y, width, height) => femtovg_path.rect(x, y, width, height),
+        }
+    }
+    femtovg_path
+}
+
+fn color((r, g, b): (u8, u8, u8)) -> Color {
+    Color::rgb(r, g, b)
+}
+
+fn canvas_pos_to_screen<T: Renderer>(canvas: &Canvas<T>, px: &Position) -> Position {
+    let transform = canvas.transform();
+    let screen_x = transform.0[0] * px.x + transform.0[4];
+    let screen_y = transform.0[3] * px.y + transform.0[5];
+    Position::new(screen_x, screen_y)
+}
+
+impl<T: Renderer> DrawTarget for Canvas<T> {
+    fn scale(&self) -> f32 {
+        get_scale(self)
+    }
+
+    fn visible_area(&self) -> (Position, Position) {
+        let transform = self.transform().0;
+        let (offset_x, offset_y) = (transform[4], transform[5]);
+        let scale = get_scale(self);
+        (
+            Position::new(-offset_x / scale, -offset_y / scale),
+            Position::new(
+                (self.width() as f32 - offset_x) / scale,
+                (self.height() as f32 - offset_y) / scale,
+            ),
+        )
+    }
+
+    fn stroke(&mut self, path: &Path, rgb: (u8, u8, u8), width: f32) {
+        let paint = Paint::color(color(rgb)).with_line_width(width);
+        self.stroke_path(&femtovg_path(path), &paint);
+    }
+
+    fn fill(&mut self, path: &Path, rgb: (u8, u8, u8)) {
+        self.fill_path(&femtovg_path(path), &Paint::color(color(rgb)));
+    }
+
+    fn text(&mut self, canvas_pos: Position, text: &str, style: &TextStyle) {
+        let mut paint = Paint::color(color(style.color)).with_font_size(style.size);
+        if style.align == TextAlign::Right {
+            paint = paint.with_text_align(femtovg::Align::Right);
+        }
+        if style.baseline == TextBaseline::Top {
+            paint = paint.with_text_baseline(femtovg::Baseline::Top);
+        }
+
+        // Convert the given canvas position to the actual screen position.
+        // Revert canvas transformation, then write text based on screen position, then restore canvas transformation.
+        // This prevents blurry text due to scaling and anti-aliasing.
+        self.save();
+
+        let screen_pos = canvas_pos_to_screen(self, &canvas_pos);
+
+        self.reset_transform();
+
+        self.fill_text(screen_pos.x, screen_pos.y, text, &paint)
+            .expect("failed to write text");
+
+        self.restore();
+    }
+}
@@ -0,0 +1,75 @@
+use super::physics::Position;
+
+// Marching squares: finds the line segments where f(x, y) = 0 by sampling f on a grid.
+// Each cell of the grid that has corners with different signs contains part of the contour,
+// and the crossing points on the cell edges are interpolated linearly.
+pub fn zero_contour(
+    x_range: (f32, f32),
+    y_range: (f32, f32),
+    cells_x: usize,
+    cells_y: usize,
+    f: impl Fn(f32, f32) -> f64,
+) -> Vec<(Position, Position)> {
+    let dx = (x_range.1 - x_range.0) / cells_x as f32;
+    let dy = (y_range.1 - y_range.0) / cells_y as f32;
+    let x_at = |i: usize| x_range.0 + i as f32 * dx;
+    let y_at = |j: usize| y_range.0 + j as f32 * dy;
+
+    let values: Vec<Vec<f64>> = (0..=cells_x)
+        .map(|i| (0..=cells_y).map(|j| f(x_at(i), y_at(j))).collect())
+        .collect();
+
+    let mut segments = vec![];
+
+    for i in 0..cells_x {
+        for j in 0..cells_y {
+            // corners in order around the cell
+            let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+
+            let mut crossings = vec![];
+            for k in 0..4 {
+                let (a, b) = (corners[k], corners[(k + 1) % 4]);
+                let (value_a, value_b) = (values[a.0][a.1], values[b.0][b.1]);
+
+                if (value_a < 0.) != (value_b < 0.) {
+                    let fraction = (value_a / (value_a - value_b)) as f32;
+                    let pos_a = Position::new(x_at(a.0), y_at(a.1));
+                    let pos_b = Position::new(x_at(b.0), y_at(b.1));
+                    crossings.push(pos_a.add(pos_b.minus(pos_a).scale(fraction)));
+                }
+            }
+
+            // 2 crossings is one segment, 4 crossings is a saddle with two segments
+            for pair in crossings.chunks_exact(2) {
+                segments.push((pair[0], pair[1]));
+            }
+        }
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_circle() {
+        let segments = zero_contour((-2., 2.), (-2., 2.), 40, 40, |x, y| {
+            (x.powi(2) + y.powi(2) - 1.) as f64
+        });
+
+        assert!(segments.len() > 20);
+        for (start, end) in segments {
+            assert!((start.mag() - 1.).abs() < 0.02);
+            assert!((end.mag() - 1.).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn no_contour() {
+        let segments = zero_contour((-2., 2.), (-2., 2.), 10, 10, |_, _| 1.);
+
+        assert!(segments.is_empty());
+    }
+}
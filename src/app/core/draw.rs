@@ -1,28 +1,119 @@
-use std::ops::Neg;
+// Drawing the scene without depending on what it's drawn to.
+// The functions here work in canvas units: a distance in km divided by distance_per_px, with y pointing down.
+// A DrawTarget maps canvas units to the screen with its own zoom and pan (scale), like femtovg's canvas does,
+// and the sizes that should stay the same on screen are divided by the scale (scaled_width).
+// The femtovg canvas is a DrawTarget in the windowed app, and the Svg writer and Raster draw figures without a GPU.
 
-use egui::emath::round_to_decimals;
-use femtovg::{Canvas, Color, Paint, Path, Renderer};
+use std::ops::Neg;
 
 use crate::app::orbital::body::Body;
 
 use super::physics::{Axis, Position};
 
-pub fn get_scale<T: Renderer>(canvas: &Canvas<T>) -> f32 {
-    let transform_matrix = canvas.transform().0;
-    let scale_opt = transform_matrix.get(0);
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathCommand {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    // center and radius
+    Circle(f32, f32, f32),
+    // top left corner, width and height
+    Rect(f32, f32, f32, f32),
+}
 
-    match scale_opt {
-        Some(scale) => *scale,
-        None => 1.,
+// shapes in canvas units, stroked or filled in one call
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    pub commands: Vec<PathCommand>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        self.commands.push(PathCommand::MoveTo(x, y));
+    }
+    pub fn line_to(&mut self, x: f32, y: f32) {
+        self.commands.push(PathCommand::LineTo(x, y));
     }
+    pub fn circle(&mut self, x: f32, y: f32, r: f32) {
+        self.commands.push(PathCommand::Circle(x, y, r));
+    }
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.commands.push(PathCommand::Rect(x, y, width, height));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TextBaseline {
+    // the bottom of letters without descenders is at the position
+    #[default]
+    Alphabetic,
+    Top,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub color: (u8, u8, u8),
+    // screen px
+    pub size: f32,
+    pub align: TextAlign,
+    pub baseline: TextBaseline,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            color: (255, 255, 255),
+            size: 16.,
+            align: TextAlign::Left,
+            baseline: TextBaseline::Alphabetic,
+        }
+    }
+}
+
+pub trait DrawTarget {
+    // screen px per canvas unit
+    fn scale(&self) -> f32;
+    // the area that's on screen, in canvas units: top left and bottom right
+    fn visible_area(&self) -> (Position, Position);
+    // width in canvas units
+    fn stroke(&mut self, path: &Path, color: (u8, u8, u8), width: f32);
+    fn fill(&mut self, path: &Path, color: (u8, u8, u8));
+    // text stays the same size on screen and isn't scaled with the canvas
+    fn text(&mut self, canvas_pos: Position, text: &str, style: &TextStyle);
 }
 
 // Provide a width number and then use the result as the canvas length.
 // It will stay a constant screen size as scale changes.
-pub fn scaled_width<T: Renderer>(canvas: &Canvas<T>, width_factor: f32) -> f32 {
-    let canvas_scale = get_scale(canvas);
+pub fn scaled_width<D: DrawTarget>(canvas: &D, width_factor: f32) -> f32 {
+    width_factor / canvas.scale()
+}
 
-    width_factor / canvas_scale
+// Scale and offset (screen px of the canvas origin) that fit an area in canvas units (top left, bottom right)
+// into a width and height in px, centered, leaving a margin in px on every side.
+pub fn fit_area(
+    width: f32,
+    height: f32,
+    area: (Position, Position),
+    margin: f32,
+) -> (f32, Position) {
+    let (min, max) = area;
+    let (area_width, area_height) = ((max.x - min.x).max(1e-6), (max.y - min.y).max(1e-6));
+    let scale = ((width - 2. * margin) / area_width).min((height - 2. * margin) / area_height);
+    let center = Position::new((min.x + max.x) / 2., (min.y + max.y) / 2.);
+    let offset = Position::new(
+        width / 2. - center.x * scale,
+        height / 2. - center.y * scale,
+    );
+    (scale, offset)
 }
 
 fn pos_to_canvas(position: &Position, distance_per_px: f32) -> Position {
@@ -32,27 +123,20 @@ fn convert_length(length: f32, distance_per_px: f32) -> f32 {
     length / distance_per_px
 }
 
-fn white<T: Renderer>(canvas: &Canvas<T>) -> Paint {
-    Paint::color(Color::white()).with_line_width(scaled_width(canvas, 1.))
-}
+const WHITE: (u8, u8, u8) = (255, 255, 255);
 
-pub fn draw_line_px<T: Renderer>(canvas: &mut Canvas<T>, start_px: &Position, end_px: &Position) {
+pub fn draw_line_px<D: DrawTarget>(canvas: &mut D, start_px: &Position, end_px: &Position) {
     let mut path = Path::new();
 
     path.move_to(start_px.x, start_px.y);
     path.line_to(end_px.x, end_px.y);
 
-    let paint = white(canvas);
-    canvas.stroke_path(&path, &paint);
+    let width = scaled_width(canvas, 1.);
+    canvas.stroke(&path, WHITE, width);
 }
 
 // one dimension is distance, the other is a fixed px length
-fn draw_tick<T: Renderer>(
-    canvas: &mut Canvas<T>,
-    axis: &Axis,
-    axis_distance: f32,
-    distance_per_px: f32,
-) {
+fn draw_tick<D: DrawTarget>(canvas: &mut D, axis: &Axis, axis_distance: f32, distance_per_px: f32) {
     let tick_length = scaled_width(canvas, 20.);
     let tick_dist_from_axis = tick_length / 2.;
     let (tick_start, tick_end) = (-tick_dist_from_axis, tick_dist_from_axis);
@@ -85,8 +169,8 @@ fn axis_distance_to_position(axis: &Axis, axis_distance: f32) -> Position {
     };
     position
 }
-fn draw_ticks_for_axis<T: Renderer>(
-    canvas: &mut Canvas<T>,
+fn draw_ticks_for_axis<D: DrawTarget>(
+    canvas: &mut D,
     axis: &Axis,
     distance_range: (f32, f32),
     interval: i32,
@@ -100,24 +184,23 @@ fn draw_ticks_for_axis<T: Renderer>(
         let axis_distance = (interval * i) as f32;
 
         if i == first_tick || i == last_tick {
-            let mut paint = Paint::default();
+            let mut style = TextStyle::default();
             if let Axis::X = axis {
                 if i == last_tick {
-                    paint = paint.with_text_align(femtovg::Align::Right)
+                    style.align = TextAlign::Right;
                 }
             };
             if let Axis::Y = axis {
                 if i == last_tick {
-                    paint = paint.with_text_baseline(femtovg::Baseline::Top)
+                    style.baseline = TextBaseline::Top;
                 }
             }
-            paint = paint.with_font_size(16.0);
             let distance_text = format!("{} km", large_number_formatter(axis_distance.into()));
             draw_text_custom(
                 canvas,
                 distance_text,
                 &axis_distance_to_position(axis, axis_distance),
-                paint,
+                style,
                 distance_per_px,
             );
         }
@@ -125,8 +208,8 @@ fn draw_ticks_for_axis<T: Renderer>(
     }
 }
 
-pub fn draw_tick_marks<T: Renderer>(
-    canvas: &mut Canvas<T>,
+pub fn draw_tick_marks<D: DrawTarget>(
+    canvas: &mut D,
     x_distance_range: (f32, f32),
     y_distance_range: (f32, f32),
     distance_per_px: f32,
@@ -142,37 +225,35 @@ pub fn draw_tick_marks<T: Renderer>(
     }
 }
 
-fn draw_circle_paint<T: Renderer>(
-    canvas: &mut Canvas<T>,
+fn draw_circle_color<D: DrawTarget>(
+    canvas: &mut D,
     position: &Position,
     r: f32,
     distance_per_px: f32,
-    paint: Paint,
+    color: (u8, u8, u8),
 ) {
     let mut path = Path::new();
     let px = pos_to_canvas(position, distance_per_px);
 
     path.circle(px.x, px.y, r);
 
-    canvas.fill_path(&path, &paint);
+    canvas.fill(&path, color);
 }
 
-fn _draw_circle_green<T: Renderer>(
-    canvas: &mut Canvas<T>,
+fn _draw_circle_green<D: DrawTarget>(
+    canvas: &mut D,
     position: &Position,
     r: f32,
     distance_per_px: f32,
 ) {
-    let paint = Paint::color(Color::rgbf(0., 1., 0.));
-
-    draw_circle_paint(canvas, position, r, distance_per_px, paint);
+    draw_circle_color(canvas, position, r, distance_per_px, (0, 255, 0));
 }
 
 // Draws a circle based on its radius.
 // At large scale, the size will be mostly determined by the radius,
 // at small scale, the size will be mostly determined by the width_factor so it stays visible.
-pub fn _draw_circle_by_radius<T: Renderer>(
-    canvas: &mut Canvas<T>,
+pub fn _draw_circle_by_radius<D: DrawTarget>(
+    canvas: &mut D,
     position: &Position,
     r: f32,
     distance_per_px: f32,
@@ -185,8 +266,8 @@ pub fn _draw_circle_by_radius<T: Renderer>(
 }
 
 // draws a circle with a radius that scales up and down as you zoom in and out
-pub fn _draw_circle_scaled<T: Renderer>(
-    canvas: &mut Canvas<T>,
+pub fn _draw_circle_scaled<D: DrawTarget>(
+    canvas: &mut D,
     position: &Position,
     width_factor: f32,
     distance_per_px: f32,
@@ -196,7 +277,27 @@ pub fn _draw_circle_scaled<T: Renderer>(
     _draw_circle_green(canvas, position, r, distance_per_px);
 }
 
-pub fn draw_body<T: Renderer>(canvas: &mut Canvas<T>, body: &Body, distance_per_px: f32) {
+// draws an x that stays the same size on screen as scale changes
+pub fn draw_marker<D: DrawTarget>(
+    canvas: &mut D,
+    position: &Position,
+    distance_per_px: f32,
+    color: (u8, u8, u8),
+) {
+    let size = scaled_width(canvas, 6.);
+    let px = pos_to_canvas(position, distance_per_px);
+
+    let mut path = Path::new();
+    path.move_to(px.x - size, px.y - size);
+    path.line_to(px.x + size, px.y + size);
+    path.move_to(px.x - size, px.y + size);
+    path.line_to(px.x + size, px.y - size);
+
+    let width = scaled_width(canvas, 1.5);
+    canvas.stroke(&path, color, width);
+}
+
+pub fn draw_body<D: DrawTarget>(canvas: &mut D, body: &Body, distance_per_px: f32) {
     let radius = if body.is_barycenter {
         scaled_width(canvas, 4.)
     } else if body.radius == 0. {
@@ -204,7 +305,7 @@ pub fn draw_body<T: Renderer>(canvas: &mut Canvas<T>, body: &Body, distance_per_
     } else {
         // use actual radius if it's big enough, else use a constant so it stays visible
         let canvas_radius = convert_length(body.radius, distance_per_px);
-        let scale = get_scale(canvas);
+        let scale = canvas.scale();
 
         if canvas_radius * scale > 20. {
             canvas_radius
@@ -213,15 +314,18 @@ pub fn draw_body<T: Renderer>(canvas: &mut Canvas<T>, body: &Body, distance_per_
         }
     };
 
-    let (r, g, b) = body.color;
-    let paint = Paint::color(Color::rgb(r, g, b));
-
-    draw_circle_paint(canvas, &body.absolute_pos, radius, distance_per_px, paint);
+    draw_circle_color(
+        canvas,
+        &body.absolute_pos,
+        radius,
+        distance_per_px,
+        body.color,
+    );
 }
 
-pub fn draw_line_thru_points<T: Renderer>(
-    canvas: &mut Canvas<T>,
-    trajectory: &Vec<Body>,
+pub fn draw_line_thru_points<D: DrawTarget>(
+    canvas: &mut D,
+    trajectory: &[Body],
     ticks_per_graph_point: usize, // number of array elements per graphed point
     distance_per_px: f32,
     color: (u8, u8, u8),
@@ -243,74 +347,145 @@ pub fn draw_line_thru_points<T: Renderer>(
         trajectory_path.line_to(canvas_pos.x, canvas_pos.y);
     }
 
-    let (r, g, b) = color;
-    let paint = Paint::color(Color::rgb(r, g, b)).with_line_width(width);
-
-    canvas.stroke_path(&trajectory_path, &paint);
+    canvas.stroke(&trajectory_path, color, width);
 }
 
-pub fn draw_text<T: Renderer>(
-    canvas: &mut Canvas<T>,
-    text: String,
-    pos: &Position,
+pub fn draw_line_thru_positions<D: DrawTarget>(
+    canvas: &mut D,
+    positions: &[Position],
     distance_per_px: f32,
+    color: (u8, u8, u8),
 ) {
-    draw_text_font(canvas, text, pos, 16.0, distance_per_px);
+    let width = scaled_width(canvas, 1.);
+    let mut path = Path::new();
+
+    for (i, pos) in positions.iter().enumerate() {
+        let canvas_pos = pos_to_canvas(pos, distance_per_px);
+        if i == 0 {
+            path.move_to(canvas_pos.x, canvas_pos.y);
+        } else {
+            path.line_to(canvas_pos.x, canvas_pos.y);
+        }
+    }
+
+    canvas.stroke(&path, color, width);
 }
 
-pub fn draw_text_custom<T: Renderer>(
-    canvas: &mut Canvas<T>,
-    text: String,
-    pos: &Position,
-    paint: Paint,
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineStyle {
+    Dashed,
+    Dotted,
+}
+
+impl LineStyle {
+    // number of segments drawn and then skipped, repeating
+    fn pattern(&self) -> (usize, usize) {
+        match self {
+            LineStyle::Dashed => (4, 3),
+            LineStyle::Dotted => (1, 2),
+        }
+    }
+}
+
+// The pattern counts segments between positions, so dashes are longer where the positions are further apart.
+pub fn draw_styled_line_thru_positions<D: DrawTarget>(
+    canvas: &mut D,
+    positions: &[Position],
     distance_per_px: f32,
+    color: (u8, u8, u8),
+    style: LineStyle,
 ) {
-    let canvas_pos = pos_to_canvas(pos, distance_per_px);
+    let (on, off) = style.pattern();
+    let width = scaled_width(canvas, 1.);
+    let mut path = Path::new();
+
+    for (i, segment) in positions.windows(2).enumerate() {
+        let phase = i % (on + off);
+        if phase >= on {
+            continue;
+        }
+        if phase == 0 {
+            let start = pos_to_canvas(&segment[0], distance_per_px);
+            path.move_to(start.x, start.y);
+        }
+        let end = pos_to_canvas(&segment[1], distance_per_px);
+        path.line_to(end.x, end.y);
+    }
 
-    draw_text_with_transform(canvas, text, canvas_pos, paint);
+    canvas.stroke(&path, color, width);
 }
 
-pub fn draw_text_font<T: Renderer>(
-    canvas: &mut Canvas<T>,
-    text: String,
-    pos: &Position,
-    font_size: f32,
+// draws separate line segments in a single path
+pub fn draw_segments<D: DrawTarget>(
+    canvas: &mut D,
+    segments: &[(Position, Position)],
     distance_per_px: f32,
+    color: (u8, u8, u8),
 ) {
-    let text_paint = Paint::color(Color::white()).with_font_size(font_size);
+    let mut path = Path::new();
 
-    let canvas_pos = pos_to_canvas(pos, distance_per_px);
+    for (start, end) in segments {
+        let start_px = pos_to_canvas(start, distance_per_px);
+        let end_px = pos_to_canvas(end, distance_per_px);
+        path.move_to(start_px.x, start_px.y);
+        path.line_to(end_px.x, end_px.y);
+    }
 
-    draw_text_with_transform(canvas, text, canvas_pos, text_paint);
+    let width = scaled_width(canvas, 1.);
+    canvas.stroke(&path, color, width);
 }
 
-fn canvas_pos_to_screen<T: Renderer>(canvas: &Canvas<T>, px: &Position) -> Position {
-    let transform = canvas.transform();
-    let screen_x = transform.0[0] * px.x + transform.0[4];
-    let screen_y = transform.0[3] * px.y + transform.0[5];
-    Position::new(screen_x, screen_y)
+// draws small squares that stay the same size on screen, in a single path for speed
+pub fn draw_points<D: DrawTarget>(
+    canvas: &mut D,
+    positions: &[Position],
+    distance_per_px: f32,
+    color: (u8, u8, u8),
+) {
+    let size = scaled_width(canvas, 2.);
+    let mut path = Path::new();
+
+    for pos in positions {
+        let px = pos_to_canvas(pos, distance_per_px);
+        path.rect(px.x - size / 2., px.y - size / 2., size, size);
+    }
+
+    canvas.fill(&path, color);
 }
 
-fn draw_text_with_transform<T: Renderer>(
-    canvas: &mut Canvas<T>,
+pub fn draw_text<D: DrawTarget>(
+    canvas: &mut D,
     text: String,
-    canvas_pos: Position,
-    text_paint: Paint,
+    pos: &Position,
+    distance_per_px: f32,
 ) {
-    // Convert the given canvas position to the actual screen position.
-    // Revert canvas transformation, then write text based on screen position, then restore canvas transformation.
-    // This prevents blurry text due to scaling and anti-aliasing.
-    canvas.save();
-
-    let screen_pos = canvas_pos_to_screen(canvas, &canvas_pos);
+    draw_text_font(canvas, text, pos, 16.0, distance_per_px);
+}
 
-    canvas.reset_transform();
+pub fn draw_text_custom<D: DrawTarget>(
+    canvas: &mut D,
+    text: String,
+    pos: &Position,
+    style: TextStyle,
+    distance_per_px: f32,
+) {
+    let canvas_pos = pos_to_canvas(pos, distance_per_px);
 
-    canvas
-        .fill_text(screen_pos.x, screen_pos.y, text, &text_paint)
-        .expect("failed to write text");
+    canvas.text(canvas_pos, &text, &style);
+}
 
-    canvas.restore();
+pub fn draw_text_font<D: DrawTarget>(
+    canvas: &mut D,
+    text: String,
+    pos: &Position,
+    font_size: f32,
+    distance_per_px: f32,
+) {
+    let style = TextStyle {
+        size: font_size,
+        ..TextStyle::default()
+    };
+    draw_text_custom(canvas, text, pos, style, distance_per_px);
 }
 
 pub fn large_number_formatter(num: f64) -> String {
@@ -322,7 +497,7 @@ pub fn large_number_formatter(num: f64) -> String {
 
         let decimal = abs / (10_f64.powi(pow_of_10));
 
-        let rounded = round_to_decimals(decimal, 3);
+        let rounded: f64 = format!("{:.3}", decimal).parse().unwrap_or(decimal);
         let with_sign = if num.is_sign_negative() {
             rounded.neg()
         } else {
@@ -1,6 +1,14 @@
+#[cfg(feature = "gui")]
+pub mod canvas;
+pub mod contour;
 pub mod draw;
 pub mod graph;
+pub mod parallel;
 pub mod physics;
+pub mod random;
+#[cfg(feature = "raster")]
+pub mod raster;
+pub mod svg;
 
 pub fn midpoint(a: f32, b: f32) -> f32 {
     (a + b) / 2.
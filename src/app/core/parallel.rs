@@ -0,0 +1,60 @@
+// Independent calculations split over several threads, with rayon when the parallel feature is on.
+// It's native only, in the browser everything runs on one thread.
+// Results come back in order and each one is calculated exactly as on one thread,
+// so the simulation is the same however the work was split up.
+
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use rayon::prelude::*;
+
+// f of 0..len in order. On several threads if parallel and there are any, in pieces of at least
+// min_len indices so small jobs stay on one thread, where starting the others would take longer than the work.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub fn map_indices<R: Send>(
+    len: usize,
+    parallel: bool,
+    min_len: usize,
+    f: impl Fn(usize) -> R + Sync + Send,
+) -> Vec<R> {
+    if parallel && len >= 2 * min_len.max(1) && rayon::current_num_threads() > 1 {
+        (0..len)
+            .into_par_iter()
+            .with_min_len(min_len)
+            .map(f)
+            .collect()
+    } else {
+        (0..len).map(f).collect()
+    }
+}
+
+#[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+pub fn map_indices<R: Send>(
+    len: usize,
+    _parallel: bool,
+    _min_len: usize,
+    f: impl Fn(usize) -> R + Sync + Send,
+) -> Vec<R> {
+    (0..len).map(f).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_results_in_order() {
+        let f = |i: usize| (i as f32).sqrt() * 1.5;
+        let serial = map_indices(1000, false, 10, f);
+        // more threads than the machine might have
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        let parallel = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap()
+            .install(|| map_indices(1000, true, 10, f));
+        #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+        let parallel = map_indices(1000, true, 10, f);
+        assert_eq!(serial, parallel);
+        assert_eq!(serial[400], 30.);
+        assert!(map_indices(0, true, 10, f).is_empty());
+    }
+}
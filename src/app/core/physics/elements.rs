@@ -0,0 +1,187 @@
+use std::f64::consts::{PI, TAU};
+
+use super::{Position, Velocity};
+
+// Osculating Keplerian orbit - the ellipse that the body would follow
+// if the central body was the only thing affecting it.
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f64, // km
+    pub eccentricity: f64,
+    pub period: f64, // s
+    // angles in radians, measured in the direction of the orbit
+    pub longitude_of_periapsis: f64,
+    pub mean_longitude: f64,
+}
+
+// Compute the orbital elements from the position and velocity relative to the central body.
+// mu is the gravitational parameter of the central body + orbiting body (km^3/s^2).
+// Returns None if the orbit is not bound.
+pub fn orbital_elements(mu: f64, pos: Position, vel: Velocity) -> Option<OrbitalElements> {
+    let (x, mut y) = (pos.x as f64, pos.y as f64);
+    let (vx, mut vy) = (vel.x as f64, vel.y as f64);
+
+    // Mirror clockwise orbits so that angles always increase along the orbit.
+    if x * vy - y * vx < 0. {
+        y = -y;
+        vy = -vy;
+    }
+
+    let r = (x.powi(2) + y.powi(2)).sqrt();
+    let v_squared = vx.powi(2) + vy.powi(2);
+    let specific_energy = v_squared / 2. - mu / r;
+    if specific_energy >= 0. || r == 0. {
+        return None;
+    }
+
+    let semi_major_axis = -mu / (2. * specific_energy);
+    let period = TAU * (semi_major_axis.powi(3) / mu).sqrt();
+
+    // eccentricity vector points towards periapsis
+    // e = ((v^2 - mu/r) r - (r.v) v) / mu
+    let r_dot_v = x * vx + y * vy;
+    let ex = ((v_squared - mu / r) * x - r_dot_v * vx) / mu;
+    let ey = ((v_squared - mu / r) * y - r_dot_v * vy) / mu;
+    let eccentricity = (ex.powi(2) + ey.powi(2)).sqrt();
+
+    let longitude_of_periapsis = if eccentricity > 1e-9 {
+        ey.atan2(ex)
+    } else {
+        0.
+    };
+
+    // true anomaly -> eccentric anomaly -> mean anomaly
+    let true_anomaly = y.atan2(x) - longitude_of_periapsis;
+    let eccentric_anomaly = ((1. - eccentricity.powi(2)).sqrt() * true_anomaly.sin())
+        .atan2(eccentricity + true_anomaly.cos());
+    let mean_anomaly = eccentric_anomaly - eccentricity * eccentric_anomaly.sin();
+
+    Some(OrbitalElements {
+        semi_major_axis,
+        eccentricity,
+        period,
+        longitude_of_periapsis: normalize_angle(longitude_of_periapsis),
+        mean_longitude: normalize_angle(longitude_of_periapsis + mean_anomaly),
+    })
+}
+
+// Position and velocity relative to the central body, on a counterclockwise orbit.
+// The inverse of orbital_elements, the period isn't used.
+pub fn state_from_elements(mu: f64, elements: &OrbitalElements) -> (Position, Velocity) {
+    let a = elements.semi_major_axis;
+    let e = elements.eccentricity;
+    let mean_anomaly = elements.mean_longitude - elements.longitude_of_periapsis;
+
+    // Kepler's equation M = E - e sin E, by Newton's method
+    let mut eccentric_anomaly = if e < 0.8 { mean_anomaly } else { PI };
+    for _ in 0..50 {
+        let step = (eccentric_anomaly - e * eccentric_anomaly.sin() - mean_anomaly)
+            / (1. - e * eccentric_anomaly.cos());
+        eccentric_anomaly -= step;
+        if step.abs() < 1e-12 {
+            break;
+        }
+    }
+
+    // in the orbit's frame, x towards periapsis
+    let (sin_e, cos_e) = eccentric_anomaly.sin_cos();
+    let b = a * (1. - e.powi(2)).sqrt();
+    let (x, y) = (a * (cos_e - e), b * sin_e);
+    let e_dot = (mu / a.powi(3)).sqrt() / (1. - e * cos_e);
+    let (vx, vy) = (-a * sin_e * e_dot, b * cos_e * e_dot);
+
+    let (sin_w, cos_w) = elements.longitude_of_periapsis.sin_cos();
+    let rotate = |x: f64, y: f64| {
+        (
+            (x * cos_w - y * sin_w) as f32,
+            (x * sin_w + y * cos_w) as f32,
+        )
+    };
+    let (px, py) = rotate(x, y);
+    let (vx, vy) = rotate(vx, vy);
+    (Position::new(px, py), Velocity::new(vx, vy))
+}
+
+// angle in the range [0, 2pi)
+pub fn normalize_angle(angle: f64) -> f64 {
+    angle.rem_euclid(TAU)
+}
+
+// angle in the range (-pi, pi]
+pub fn wrap_angle(angle: f64) -> f64 {
+    let normalized = normalize_angle(angle);
+    if normalized > PI {
+        normalized - TAU
+    } else {
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::core::physics::{circular_velocity, gravitational_parameter};
+
+    use super::*;
+
+    #[test]
+    fn circular_orbit() {
+        let earth_mass = 5.97e24;
+        let pos = Position::new(0., 7000.);
+        let vel = circular_velocity(Position::new(0., 0.), earth_mass, pos);
+
+        let elements = orbital_elements(gravitational_parameter(earth_mass), pos, vel).unwrap();
+
+        assert!((elements.semi_major_axis - 7000.).abs() < 1.);
+        assert!(elements.eccentricity < 1e-3);
+        // about 97 minutes
+        assert!((elements.period / 60. - 97.2).abs() < 0.5);
+    }
+
+    #[test]
+    fn elliptical_orbit_at_periapsis() {
+        let mu: f64 = 398600.;
+        let r = 7000.;
+        let e = 0.2;
+        // vis-viva at periapsis: v^2 = mu (1 + e) / r
+        let v = (mu * (1. + e) / r).sqrt() as f32;
+        let pos = Position::new(r as f32, 0.);
+
+        let counterclockwise = orbital_elements(mu, pos, Velocity::new(0., v)).unwrap();
+        let clockwise = orbital_elements(mu, pos, Velocity::new(0., -v)).unwrap();
+
+        for elements in [counterclockwise, clockwise] {
+            assert!((elements.eccentricity - e).abs() < 1e-4);
+            assert!((elements.semi_major_axis - r / (1. - e)).abs() < 1.);
+            assert!(wrap_angle(elements.longitude_of_periapsis).abs() < 1e-4);
+            assert!(wrap_angle(elements.mean_longitude).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn state_from_elements_round_trip() {
+        let mu: f64 = 398600.;
+        let elements = OrbitalElements {
+            semi_major_axis: 26560.,
+            eccentricity: 0.3,
+            period: 0.,
+            longitude_of_periapsis: 2.,
+            mean_longitude: 4.5,
+        };
+        let (pos, vel) = state_from_elements(mu, &elements);
+        let result = orbital_elements(mu, pos, vel).unwrap();
+
+        assert!((result.semi_major_axis / elements.semi_major_axis - 1.).abs() < 1e-5);
+        assert!((result.eccentricity - elements.eccentricity).abs() < 1e-5);
+        assert!(wrap_angle(result.longitude_of_periapsis - 2.).abs() < 1e-5);
+        assert!(wrap_angle(result.mean_longitude - 4.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn unbound_orbit() {
+        let mu: f64 = 398600.;
+        let pos = Position::new(7000., 0.);
+        let escape_v = (2. * mu / 7000_f64).sqrt() as f32;
+
+        assert!(orbital_elements(mu, pos, Velocity::new(0., escape_v * 1.1)).is_none());
+    }
+}
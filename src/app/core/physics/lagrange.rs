@@ -0,0 +1,189 @@
+// Circular restricted three-body problem: a massless particle moving around two bodies
+// that orbit each other in a circle.
+// A particle placed at one of the Lagrange points and moving with the rotating two-body system
+// stays at the same place relative to both bodies.
+
+use super::{gravitational_parameter, Position, Velocity};
+
+// In units where the distance between the bodies is 1 and the barycenter is at 0,
+// the first body is at -mu, the second body is at 1 - mu.
+// This is the net acceleration along the line through both bodies, in the rotating frame.
+// The collinear points L1, L2 and L3 are where it is 0.
+fn collinear_acceleration(x: f64, mu: f64) -> f64 {
+    let r1 = x + mu;
+    let r2 = x - 1. + mu;
+
+    x - (1. - mu) * r1 / r1.abs().powi(3) - mu * r2 / r2.abs().powi(3)
+}
+
+// binary search for the root between start and end
+fn find_root(mu: f64, mut start: f64, mut end: f64) -> f64 {
+    let start_sign = collinear_acceleration(start, mu).is_sign_negative();
+
+    for _ in 0..100 {
+        let mid = (start + end) / 2.;
+        if collinear_acceleration(mid, mu).is_sign_negative() == start_sign {
+            start = mid;
+        } else {
+            end = mid;
+        }
+    }
+    (start + end) / 2.
+}
+
+// Returns the positions of L1 to L5 for the pair of bodies.
+// L1 is between the bodies, L2 is past the second body, L3 is past the first body.
+// L4 leads the second body in its orbit and L5 trails it,
+// so the direction of the orbit is needed: counterclockwise = true.
+pub fn lagrange_points(
+    m1: f32,
+    pos1: Position,
+    m2: f32,
+    pos2: Position,
+    counterclockwise: bool,
+) -> [Position; 5] {
+    let mu = m2 as f64 / (m1 as f64 + m2 as f64);
+    let epsilon = 1e-9;
+
+    let l1 = find_root(mu, -mu + epsilon, 1. - mu - epsilon);
+    let l2 = find_root(mu, 1. - mu + epsilon, 2.);
+    let l3 = find_root(mu, -2., -mu - epsilon);
+
+    let r = pos2.minus(pos1);
+    let d = r.mag();
+    let x_unit = r.divide(d);
+    // perpendicular, pointing in the direction of the orbit
+    let y_unit = if counterclockwise {
+        x_unit.perpendicular_cw().scale(-1.)
+    } else {
+        x_unit.perpendicular_cw()
+    };
+
+    let barycenter = pos1.add(r.scale(mu as f32));
+    let on_line = |x: f64| barycenter.add(x_unit.scale(x as f32 * d));
+
+    // L4 and L5 form equilateral triangles with the two bodies
+    let triangle_x = x_unit.scale(0.5 * d);
+    let triangle_y = y_unit.scale(3_f32.sqrt() / 2. * d);
+
+    [
+        on_line(l1),
+        on_line(l2),
+        on_line(l3),
+        pos1.add(triangle_x).add(triangle_y),
+        pos1.add(triangle_x).minus(triangle_y),
+    ]
+}
+
+// Velocity of a point at `pos` that rotates rigidly with the two bodies,
+// e.g. for a particle placed at a Lagrange point.
+// Uses the current angular velocity of the pair rather than assuming a circular orbit.
+pub fn co_orbital_velocity(
+    m1: f32,
+    pos1: Position,
+    vel1: Velocity,
+    m2: f32,
+    pos2: Position,
+    vel2: Velocity,
+    pos: Position,
+) -> Velocity {
+    let mass_sum = m1 + m2;
+    let barycenter = pos1.scale(m1 / mass_sum).add(pos2.scale(m2 / mass_sum));
+    let barycenter_vel = vel1.scale(m1 / mass_sum).add(vel2.scale(m2 / mass_sum));
+
+    let r = pos2.minus(pos1);
+    let v = vel2.minus(vel1);
+    // counterclockwise is positive
+    let angular_velocity = r.cross(v) / r.mag().powi(2);
+
+    let offset = pos.minus(barycenter);
+    // w x r for w = (0, 0, angular_velocity)
+    let rotation_vel = Velocity::new(-offset.y, offset.x).scale(angular_velocity);
+
+    barycenter_vel.add(rotation_vel)
+}
+
+// Twice the effective potential in the frame rotating with the two bodies at angular_velocity (rad/s),
+// with positions relative to the barycenter.
+// 2U = w^2 r^2 + 2 mu1 / r1 + 2 mu2 / r2 (km^2/s^2)
+pub fn effective_potential(
+    m1: f32,
+    pos1: Position,
+    m2: f32,
+    pos2: Position,
+    angular_velocity: f32,
+    pos: Position,
+) -> f64 {
+    let r = pos.mag() as f64;
+    let r1 = pos.abs_diff(pos1) as f64;
+    let r2 = pos.abs_diff(pos2) as f64;
+    let w = angular_velocity as f64;
+
+    w.powi(2) * r.powi(2)
+        + 2. * gravitational_parameter(m1) / r1
+        + 2. * gravitational_parameter(m2) / r2
+}
+
+// Jacobi constant of a particle, conserved in the rotating frame: C = 2U - v^2.
+// The particle can only go where 2U >= C, the boundary is the zero-velocity curve.
+// Position and velocity are relative to the rotating frame.
+pub fn jacobi_constant(
+    m1: f32,
+    pos1: Position,
+    m2: f32,
+    pos2: Position,
+    angular_velocity: f32,
+    pos: Position,
+    vel: Velocity,
+) -> f64 {
+    effective_potential(m1, pos1, m2, pos2, angular_velocity, pos) - (vel.mag() as f64).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Earth-Moon L1 is about 326,000 km from Earth, L2 about 449,000 km
+    #[test]
+    fn earth_moon_collinear_points() {
+        let earth = Position::new(0., 0.);
+        let moon = Position::new(384400., 0.);
+
+        let points = lagrange_points(5.97e24, earth, 7.34e22, moon, true);
+
+        assert!(
+            (points[0].x - 326_400.).abs() < 1000.,
+            "L1: {:?}",
+            points[0]
+        );
+        assert!(
+            (points[1].x - 448_900.).abs() < 1000.,
+            "L2: {:?}",
+            points[1]
+        );
+        assert!(
+            (points[2].x + 381_700.).abs() < 1000.,
+            "L3: {:?}",
+            points[2]
+        );
+        assert!(points[0].y.abs() < 1e-3);
+    }
+
+    #[test]
+    fn triangular_points_are_equilateral() {
+        let pos1 = Position::new(-5000., 2000.);
+        let pos2 = Position::new(15000., -3000.);
+        let d = pos1.abs_diff(pos2);
+
+        let points = lagrange_points(1e24, pos1, 3e23, pos2, false);
+
+        for l in &points[3..] {
+            assert!((l.abs_diff(pos1) - d).abs() < 0.1);
+            assert!((l.abs_diff(pos2) - d).abs() < 0.1);
+        }
+        // L4 leads in the direction of the orbit
+        let orbit_direction = pos2.minus(pos1).perpendicular_cw();
+        assert!(points[3].minus(pos2).dot(orbit_direction) > 0.);
+        assert!(points[4].minus(pos2).dot(orbit_direction) < 0.);
+    }
+}
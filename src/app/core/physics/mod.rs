@@ -1,20 +1,32 @@
+pub use elements::{
+    normalize_angle, orbital_elements, state_from_elements, wrap_angle, OrbitalElements,
+};
+pub use lagrange::{co_orbital_velocity, effective_potential, jacobi_constant, lagrange_points};
 pub use vector::{Acceleration, Position, Velocity};
 
+use serde::{Deserialize, Serialize};
+
 use crate::app::orbital::body::Body;
 
+mod elements;
+mod lagrange;
 mod vector;
 
 pub const G: f32 = 6.674e-11; // N m^2 / kg^2
 pub const G_KM: f32 = G * 1e-6; // N km^2 / kg^2 (converted to km)
 pub const R_EARTH_KM: f32 = 6378.;
-pub const R_MOON_KM: f32 = 1740.;
-pub const SUN_EARTH_R_KM: f32 = 149597870.;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Axis {
     X,
     Y,
 }
 
+// mu = GM in km^3/s^2
+pub fn gravitational_parameter(mass: f32) -> f64 {
+    G as f64 * mass as f64 * 1e-9
+}
+
 // calculate the magnitude of the circular velocity
 // v = sqrt(GM/r)
 fn circular_velocity_magnitude(central_mass: f32, r: f32) -> f32 {
@@ -96,11 +108,29 @@ pub fn gravitational_acceleration(
     central_pos: Position,
     orbital_pos: Position,
     central_mass: f32,
+) -> Acceleration {
+    softened_gravitational_acceleration(central_pos, orbital_pos, central_mass, 0.)
+}
+
+// Plummer softening: the distance is replaced by sqrt(r^2 + softening^2),
+// like the central mass is spread over a small sphere instead of being a point,
+// so close encounters don't produce huge accelerations that the integrator can't follow.
+// softening = 0 is the normal inverse square law.
+pub fn softened_gravitational_acceleration(
+    central_pos: Position,
+    orbital_pos: Position,
+    central_mass: f32,
+    softening: f32, // km
 ) -> Acceleration {
     let r = orbital_pos.minus(central_pos);
+    let distance = if softening == 0. {
+        r.mag()
+    } else {
+        (r.mag().powi(2) + softening.powi(2)).sqrt()
+    };
 
     // a = -G * m_central * r_vec / (|r_vec|^3)
-    let cur_a = r.scale(-G_KM * central_mass / (r.mag().powi(3)));
+    let cur_a = r.scale(-G_KM * central_mass / (distance.powi(3)));
     let cur_a_km = cur_a.scale(1e-3);
 
     Acceleration::from(cur_a_km)
@@ -142,6 +172,80 @@ pub fn symplectic_euler_calc(
     (next_r, next_v)
 }
 
+// How bodies are moved forward each tick
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Integrator {
+    #[default]
+    SymplecticEuler,
+    // second order, one more acceleration calculation per tick
+    VelocityVerlet,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 2] = [Integrator::SymplecticEuler, Integrator::VelocityVerlet];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::SymplecticEuler => "Symplectic Euler",
+            Integrator::VelocityVerlet => "Velocity Verlet",
+        }
+    }
+}
+
+// velocity Verlet, in two halves since the acceleration at the new position
+// needs the new positions of all the other bodies
+// r(t + dt) = r(t) + v(t)*dt + a(t)*dt^2/2
+pub fn verlet_position(cur_r: Position, cur_v: Velocity, cur_a: Acceleration, dt: f32) -> Position {
+    cur_r.update(&cur_v, &cur_a, dt)
+}
+
+// v(t + dt) = v(t) + (a(t) + a(t + dt))*dt/2
+pub fn verlet_velocity(
+    cur_v: Velocity,
+    cur_a: Acceleration,
+    next_a: Acceleration,
+    dt: f32,
+) -> Velocity {
+    cur_v.update(&cur_a.add(next_a), dt / 2.)
+}
+
+// Cubic Hermite interpolation between two states that are dt apart.
+// The curve matches the position and velocity at both ends, so it's a good estimate
+// of the state in between ticks. fraction = 0 is the first state, fraction = 1 is the second.
+pub fn hermite_interpolation(
+    p0: Position,
+    v0: Velocity,
+    p1: Position,
+    v1: Velocity,
+    dt: f32,
+    fraction: f32,
+) -> (Position, Velocity) {
+    let s = fraction;
+    let (s2, s3) = (s.powi(2), s.powi(3));
+
+    let h00 = 2. * s3 - 3. * s2 + 1.;
+    let h10 = s3 - 2. * s2 + s;
+    let h01 = -2. * s3 + 3. * s2;
+    let h11 = s3 - s2;
+    let pos = p0
+        .scale(h00)
+        .add(Position::from(v0.scale(h10 * dt)))
+        .add(p1.scale(h01))
+        .add(Position::from(v1.scale(h11 * dt)));
+
+    // derivatives of the basis functions, divided by dt to convert back to velocity
+    let d00 = 6. * s2 - 6. * s;
+    let d10 = 3. * s2 - 4. * s + 1.;
+    let d01 = -6. * s2 + 6. * s;
+    let d11 = 3. * s2 - 2. * s;
+    let vel = Velocity::from(p0.scale(d00 / dt))
+        .add(v0.scale(d10))
+        .add(Velocity::from(p1.scale(d01 / dt)))
+        .add(v1.scale(d11));
+
+    (pos, vel)
+}
+
 // Ek = .5mv^2
 pub fn kinetic_energy(mass: f32, v: Velocity) -> f32 {
     let body_kinetic_mj = 0.5 * mass * v.mag().powi(2); // MJ
@@ -149,12 +253,19 @@ pub fn kinetic_energy(mass: f32, v: Velocity) -> f32 {
     body_kinetic_mj
 }
 
-pub fn gravitational_potential_energy(m1: f32, m2: f32, pos1: Position, pos2: Position) -> f64 {
+// potential energy that matches the softened acceleration, softening = 0 is the normal -GMm/r
+pub fn softened_potential_energy(
+    m1: f32,
+    m2: f32,
+    pos1: Position,
+    pos2: Position,
+    softening: f32,
+) -> f64 {
     // Gravitational energy between two masses
-    // Eg = -G * M * m / r
+    // Eg = -G * M * m / sqrt(r^2 + softening^2)
     let r = pos1.minus(pos2).mag();
 
-    let (m1, m2, r) = (m1 as f64, m2 as f64, r as f64);
+    let (m1, m2, r) = (m1 as f64, m2 as f64, (r as f64).hypot(softening as f64));
 
     let grav_potential_kj = -G_KM as f64 * m1 * m2 / r; // KJ
     let grav_potential_mj = grav_potential_kj * 1e-3; // MJ
@@ -1,8 +1,10 @@
-#[derive(Clone, Copy, Debug, Default)]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Pos;
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vel;
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Acc;
 
 pub trait VectorType {}
@@ -14,7 +16,7 @@ pub type Position = Vector<Pos>;
 pub type Velocity = Vector<Vel>;
 pub type Acceleration = Vector<Acc>;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vector<T: VectorType> {
     _type: T,
     pub x: f32,
@@ -43,6 +45,13 @@ impl<T: VectorType + Default> Vector<T> {
     pub fn perpendicular_cw(self) -> Self {
         Self::new_vec(self._type, self.y, -self.x)
     }
+    pub fn dot<U: VectorType>(self, vec2: Vector<U>) -> f32 {
+        self.x * vec2.x + self.y * vec2.y
+    }
+    // z component of the 3D cross product
+    pub fn cross<U: VectorType>(self, vec2: Vector<U>) -> f32 {
+        self.x * vec2.y - self.y * vec2.x
+    }
 
     pub fn from<SourceType: VectorType>(v: Vector<SourceType>) -> Self {
         Vector::new_vec(T::default(), v.x, v.y)
@@ -59,6 +68,20 @@ impl<T: VectorType + Default> Vector<T> {
     }
 }
 
+// saved as [x, y]
+impl<T: VectorType + Default> Serialize for Vector<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        [self.x, self.y].serialize(serializer)
+    }
+}
+
+impl<'de, T: VectorType + Default> Deserialize<'de> for Vector<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let [x, y] = <[f32; 2]>::deserialize(deserializer)?;
+        Ok(Self::new_vec(T::default(), x, y))
+    }
+}
+
 impl Vector<Pos> {
     pub fn new(x: f32, y: f32) -> Self {
         Vector::new_vec(Pos, x, y)
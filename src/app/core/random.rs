@@ -0,0 +1,72 @@
+// Small seeded random number generator (splitmix64),
+// so generated initial conditions are the same every time for the same seed.
+
+use std::f32::consts::TAU;
+
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    pub fn uniform(&mut self) -> f32 {
+        // top 24 bits fit exactly in an f32
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.uniform()
+    }
+
+    // standard normal distribution, using the Box-Muller transform
+    pub fn normal(&mut self) -> f32 {
+        let u1 = 1. - self.uniform(); // (0, 1] so the log is finite
+        let u2 = self.uniform();
+        (-2. * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut rng1 = Rng::new(42);
+        let mut rng2 = Rng::new(42);
+        let mut rng3 = Rng::new(43);
+
+        let a: Vec<f32> = (0..10).map(|_| rng1.uniform()).collect();
+        let b: Vec<f32> = (0..10).map(|_| rng2.uniform()).collect();
+        let c: Vec<f32> = (0..10).map(|_| rng3.uniform()).collect();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.iter().all(|&x| (0. ..1.).contains(&x)));
+    }
+
+    #[test]
+    fn normal_distribution() {
+        let mut rng = Rng::new(1);
+        let samples: Vec<f32> = (0..10000).map(|_| rng.normal()).collect();
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        let variance =
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / samples.len() as f32;
+
+        assert!(mean.abs() < 0.05);
+        assert!((variance - 1.).abs() < 0.05);
+    }
+}
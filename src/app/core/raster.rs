@@ -0,0 +1,301 @@
+// Drawing to an image on the CPU, for PNG snapshots and GIF animations without a window or GPU.
+// Like the Svg writer, canvas units are zoomed and panned to image px as they're drawn,
+// and text is drawn with the same font as the window.
+
+use std::io::Write;
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+use super::{
+    draw::{fit_area, DrawTarget, Path, PathCommand, TextAlign, TextBaseline, TextStyle},
+    physics::Position,
+};
+
+const FONT: &[u8] = include_bytes!("../../../assets/Roboto-Regular.ttf");
+
+pub struct Raster {
+    pixmap: Pixmap,
+    // image px per canvas unit
+    scale: f32,
+    // image px of the canvas origin
+    offset: Position,
+    font: FontRef<'static>,
+}
+
+fn paint((r, g, b): (u8, u8, u8)) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, 255);
+    paint.anti_alias = true;
+    paint
+}
+
+// None if there's nothing to draw
+fn skia_path(path: &Path) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    for command in path.commands.iter() {
+        match *command {
+            PathCommand::MoveTo(x, y) => builder.move_to(x, y),
+            PathCommand::LineTo(x, y) => builder.line_to(x, y),
+            PathCommand::Circle(x, y, r) => builder.push_circle(x, y, r),
+            PathCommand::Rect(x, y, width, height) => {
+                if let Some(rect) = Rect::from_xywh(x, y, width, height) {
+                    builder.push_rect(rect);
+                }
+            }
+        }
+    }
+    builder.finish()
+}
+
+impl Raster {
+    pub fn new(width: u32, height: u32, scale: f32, offset: Position) -> Self {
+        let mut raster = Self {
+            pixmap: Pixmap::new(width.max(1), height.max(1)).expect("image too large"),
+            scale,
+            offset,
+            font: FontRef::try_from_slice(FONT).expect("invalid font"),
+        };
+        raster.clear();
+        raster
+    }
+
+    // Zoomed and panned so that an area in canvas units (top left, bottom right) fills the image,
+    // leaving a margin in image px on every side.
+    pub fn fit(width: u32, height: u32, area: (Position, Position), margin: f32) -> Self {
+        let (scale, offset) = fit_area(width as f32, height as f32, area, margin);
+        Self::new(width, height, scale, offset)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.pixmap.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.pixmap.height()
+    }
+
+    // black background like the window, to draw the next frame with the same zoom
+    pub fn clear(&mut self) {
+        self.pixmap.fill(tiny_skia::Color::BLACK);
+    }
+
+    // RGBA bytes, row by row. The background is opaque, so premultiplied alpha doesn't change the colors.
+    pub fn rgba(&self) -> &[u8] {
+        self.pixmap.data()
+    }
+
+    pub fn png(&self) -> Result<Vec<u8>, String> {
+        self.pixmap.encode_png().map_err(|e| e.to_string())
+    }
+
+    fn transform(&self) -> Transform {
+        Transform::from_row(self.scale, 0., 0., self.scale, self.offset.x, self.offset.y)
+    }
+
+    // blend a color over a pixel, by how much of the pixel is covered
+    fn blend(&mut self, x: i32, y: i32, color: (u8, u8, u8), coverage: f32) {
+        let (width, height) = (self.width() as i32, self.height() as i32);
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return;
+        }
+        let coverage = coverage.clamp(0., 1.);
+        let i = (y * width + x) as usize * 4;
+        let data = self.pixmap.data_mut();
+        for (channel, value) in [color.0, color.1, color.2, 255].into_iter().enumerate() {
+            let below = data[i + channel] as f32;
+            data[i + channel] = (value as f32 * coverage + below * (1. - coverage)).round() as u8;
+        }
+    }
+}
+
+impl DrawTarget for Raster {
+    fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    fn visible_area(&self) -> (Position, Position) {
+        (
+            Position::new(-self.offset.x / self.scale, -self.offset.y / self.scale),
+            Position::new(
+                (self.width() as f32 - self.offset.x) / self.scale,
+                (self.height() as f32 - self.offset.y) / self.scale,
+            ),
+        )
+    }
+
+    fn stroke(&mut self, path: &Path, color: (u8, u8, u8), width: f32) {
+        let Some(path) = skia_path(path) else {
+            return;
+        };
+        let stroke = Stroke {
+            width,
+            ..Stroke::default()
+        };
+        let transform = self.transform();
+        self.pixmap
+            .stroke_path(&path, &paint(color), &stroke, transform, None);
+    }
+
+    fn fill(&mut self, path: &Path, color: (u8, u8, u8)) {
+        let Some(path) = skia_path(path) else {
+            return;
+        };
+        let transform = self.transform();
+        self.pixmap
+            .fill_path(&path, &paint(color), FillRule::Winding, transform, None);
+    }
+
+    fn text(&mut self, canvas_pos: Position, text: &str, style: &TextStyle) {
+        // the style's size is the em size like femtovg's, ab_glyph scales by the height
+        let units_per_em = self.font.units_per_em().unwrap_or(1.);
+        let px_scale = PxScale::from(style.size * self.font.height_unscaled() / units_per_em);
+        let font = self.font.clone();
+        let scaled = font.as_scaled(px_scale);
+
+        // glyph positions along the line, from the left end
+        let mut glyphs = vec![];
+        let mut x = 0.;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                x += scaled.kern(previous, id);
+            }
+            glyphs.push((id, x));
+            x += scaled.h_advance(id);
+            previous = Some(id);
+        }
+
+        let left = match style.align {
+            TextAlign::Left => 0.,
+            TextAlign::Right => -x,
+        };
+        let baseline = match style.baseline {
+            TextBaseline::Alphabetic => 0.,
+            TextBaseline::Top => scaled.ascent(),
+        };
+        let origin = Position::new(
+            canvas_pos.x * self.scale + self.offset.x + left,
+            canvas_pos.y * self.scale + self.offset.y + baseline,
+        );
+
+        for (id, x) in glyphs {
+            let glyph = id.with_scale_and_position(px_scale, point(origin.x + x, origin.y));
+            let Some(outlined) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            let mut covered = vec![];
+            outlined.draw(|x, y, coverage| covered.push((x, y, coverage)));
+            for (x, y, coverage) in covered {
+                self.blend(
+                    bounds.min.x as i32 + x as i32,
+                    bounds.min.y as i32 + y as i32,
+                    style.color,
+                    coverage,
+                );
+            }
+        }
+    }
+}
+
+// An animated GIF, written a frame at a time so long animations don't have to be kept in memory.
+pub struct Gif<W: Write> {
+    encoder: gif::Encoder<W>,
+    width: u16,
+    height: u16,
+}
+
+impl<W: Write> Gif<W> {
+    pub fn new(writer: W, width: u32, height: u32) -> Result<Self, String> {
+        let (width, height) = (
+            u16::try_from(width).map_err(|_| "image too wide for a GIF")?,
+            u16::try_from(height).map_err(|_| "image too tall for a GIF")?,
+        );
+        let mut encoder =
+            gif::Encoder::new(writer, width, height, &[]).map_err(|e| e.to_string())?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            encoder,
+            width,
+            height,
+        })
+    }
+
+    // delay in hundredths of a second
+    pub fn add_frame(&mut self, raster: &Raster, delay: u16) -> Result<(), String> {
+        if (raster.width(), raster.height()) != (self.width as u32, self.height as u32) {
+            return Err("frames have to be the same size".to_string());
+        }
+        let mut rgba = raster.rgba().to_vec();
+        let mut frame = gif::Frame::from_rgba_speed(self.width, self.height, &mut rgba, 10);
+        frame.delay = delay;
+        self.encoder.write_frame(&frame).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(raster: &Raster, x: u32, y: u32) -> (u8, u8, u8) {
+        let i = ((y * raster.width() + x) * 4) as usize;
+        let data = raster.rgba();
+        (data[i], data[i + 1], data[i + 2])
+    }
+
+    #[test]
+    fn draws_shapes_and_text() {
+        // canvas origin in the middle, 2 px per canvas unit
+        let mut raster = Raster::new(100, 50, 2., Position::new(50., 25.));
+        assert_eq!(
+            raster.visible_area(),
+            (Position::new(-25., -12.5), Position::new(25., 12.5))
+        );
+        assert_eq!(pixel(&raster, 0, 0), (0, 0, 0));
+
+        let mut path = Path::new();
+        path.circle(-10., 0., 3.);
+        raster.fill(&path, (255, 0, 0));
+        assert_eq!(pixel(&raster, 30, 25), (255, 0, 0));
+
+        let mut path = Path::new();
+        path.move_to(0., -10.);
+        path.line_to(0., 10.);
+        raster.stroke(&path, (0, 255, 0), 1.);
+        assert_eq!(pixel(&raster, 50, 10), (0, 255, 0));
+
+        let lit = |raster: &Raster| {
+            raster
+                .rgba()
+                .chunks(4)
+                .filter(|p| p[0] > 128 && p[1] > 128 && p[2] > 128)
+                .count()
+        };
+        assert_eq!(lit(&raster), 0);
+        raster.text(Position::new(5., 5.), "Earth", &TextStyle::default());
+        assert!(lit(&raster) > 20);
+
+        let png = raster.png().unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+
+    #[test]
+    fn writes_gif() {
+        let mut bytes = vec![];
+        {
+            let mut gif = Gif::new(&mut bytes, 20, 10).unwrap();
+            let mut raster = Raster::new(20, 10, 1., Position::new(0., 0.));
+            gif.add_frame(&raster, 4).unwrap();
+            raster.clear();
+            gif.add_frame(&raster, 4).unwrap();
+            assert!(gif
+                .add_frame(&Raster::new(10, 10, 1., Position::new(0., 0.)), 4)
+                .is_err());
+        }
+        assert!(bytes.starts_with(b"GIF89a"));
+    }
+}
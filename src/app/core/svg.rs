@@ -0,0 +1,215 @@
+// Drawing to an SVG image, for figures of trajectories without a window or GPU.
+// Canvas units are zoomed and panned to image px as they're written, like the canvas transform in the window,
+// so lines and text are the same size in the image as on screen.
+
+use std::fmt::Write;
+
+use super::{
+    draw::{fit_area, DrawTarget, Path, PathCommand, TextAlign, TextBaseline, TextStyle},
+    physics::Position,
+};
+
+pub struct Svg {
+    width: f32,
+    height: f32,
+    // image px per canvas unit
+    scale: f32,
+    // image px of the canvas origin
+    offset: Position,
+    elements: String,
+}
+
+fn rgb((r, g, b): (u8, u8, u8)) -> String {
+    format!("rgb({},{},{})", r, g, b)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Svg {
+    pub fn new(width: f32, height: f32, scale: f32, offset: Position) -> Self {
+        Self {
+            width,
+            height,
+            scale,
+            offset,
+            elements: String::new(),
+        }
+    }
+
+    // Zoomed and panned so that an area in canvas units (top left, bottom right) fills the image,
+    // leaving a margin in image px on every side.
+    pub fn fit(width: f32, height: f32, area: (Position, Position), margin: f32) -> Self {
+        let (scale, offset) = fit_area(width, height, area, margin);
+        Self::new(width, height, scale, offset)
+    }
+
+    fn to_image(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            x * self.scale + self.offset.x,
+            y * self.scale + self.offset.y,
+        )
+    }
+
+    // the path's d attribute in image px
+    fn path_data(&self, path: &Path) -> String {
+        let mut d = String::new();
+        for command in path.commands.iter() {
+            let _ = match *command {
+                PathCommand::MoveTo(x, y) => {
+                    let (x, y) = self.to_image(x, y);
+                    write!(d, "M{:.2} {:.2}", x, y)
+                }
+                PathCommand::LineTo(x, y) => {
+                    let (x, y) = self.to_image(x, y);
+                    write!(d, "L{:.2} {:.2}", x, y)
+                }
+                PathCommand::Circle(x, y, r) => {
+                    // two half circles
+                    let (x, y) = self.to_image(x, y);
+                    let r = r * self.scale;
+                    write!(
+                        d,
+                        "M{:.2} {:.2}a{r:.2} {r:.2} 0 1 0 {:.2} 0a{r:.2} {r:.2} 0 1 0 {:.2} 0Z",
+                        x - r,
+                        y,
+                        2. * r,
+                        -2. * r,
+                        r = r
+                    )
+                }
+                PathCommand::Rect(x, y, width, height) => {
+                    let (x, y) = self.to_image(x, y);
+                    write!(
+                        d,
+                        "M{:.2} {:.2}h{:.2}v{:.2}h{:.2}Z",
+                        x,
+                        y,
+                        width * self.scale,
+                        height * self.scale,
+                        -width * self.scale
+                    )
+                }
+            };
+        }
+        d
+    }
+
+    // the whole document, on a black background like the window
+    pub fn finish(&self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"black\"/>\n{}</svg>\n",
+            self.elements,
+            w = self.width,
+            h = self.height,
+        )
+    }
+}
+
+impl DrawTarget for Svg {
+    fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    fn visible_area(&self) -> (Position, Position) {
+        (
+            Position::new(-self.offset.x / self.scale, -self.offset.y / self.scale),
+            Position::new(
+                (self.width - self.offset.x) / self.scale,
+                (self.height - self.offset.y) / self.scale,
+            ),
+        )
+    }
+
+    fn stroke(&mut self, path: &Path, color: (u8, u8, u8), width: f32) {
+        if path.commands.is_empty() {
+            return;
+        }
+        let d = self.path_data(path);
+        let _ = writeln!(
+            self.elements,
+            "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{:.2}\"/>",
+            d,
+            rgb(color),
+            width * self.scale
+        );
+    }
+
+    fn fill(&mut self, path: &Path, color: (u8, u8, u8)) {
+        if path.commands.is_empty() {
+            return;
+        }
+        let d = self.path_data(path);
+        let _ = writeln!(self.elements, "<path d=\"{}\" fill=\"{}\"/>", d, rgb(color));
+    }
+
+    fn text(&mut self, canvas_pos: Position, text: &str, style: &TextStyle) {
+        let (x, y) = self.to_image(canvas_pos.x, canvas_pos.y);
+        let anchor = match style.align {
+            TextAlign::Left => "start",
+            TextAlign::Right => "end",
+        };
+        let baseline = match style.baseline {
+            TextBaseline::Alphabetic => "alphabetic",
+            TextBaseline::Top => "hanging",
+        };
+        let _ = writeln!(
+            self.elements,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"Roboto, sans-serif\" font-size=\"{}\" fill=\"{}\" text-anchor=\"{}\" dominant-baseline=\"{}\">{}</text>",
+            x,
+            y,
+            style.size,
+            rgb(style.color),
+            anchor,
+            baseline,
+            escape(text)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_elements() {
+        // canvas origin in the middle, 2 px per canvas unit
+        let mut svg = Svg::new(100., 50., 2., Position::new(50., 25.));
+        assert_eq!(
+            svg.visible_area(),
+            (Position::new(-25., -12.5), Position::new(25., 12.5))
+        );
+
+        let mut path = Path::new();
+        path.move_to(0., 0.);
+        path.line_to(10., -5.);
+        svg.stroke(&path, (255, 0, 0), 0.5);
+        svg.text(Position::new(1., 1.), "a < b", &TextStyle::default());
+
+        let document = svg.finish();
+        assert!(document.contains(
+            "<path d=\"M50.00 25.00L70.00 15.00\" fill=\"none\" stroke=\"rgb(255,0,0)\" stroke-width=\"1.00\"/>"
+        ));
+        assert!(document.contains("x=\"52.00\" y=\"27.00\""));
+        assert!(document.contains(">a &lt; b</text>"));
+    }
+
+    #[test]
+    fn fits_area() {
+        let svg = Svg::fit(
+            200.,
+            100.,
+            (Position::new(0., 0.), Position::new(10., 10.)),
+            10.,
+        );
+        // limited by the height: 80 px for 10 units, centered
+        assert_eq!(svg.scale(), 8.);
+        assert_eq!(svg.to_image(0., 0.), (60., 10.));
+        assert_eq!(svg.to_image(10., 10.), (140., 90.));
+    }
+}
@@ -1,12 +1,24 @@
 pub mod core;
 pub mod orbital;
+#[cfg(feature = "gui")]
 pub mod simple;
+#[cfg(feature = "gui")]
 use femtovg::{renderer::WGPURenderer, Canvas};
 
-pub trait App {
+// the part of an app that runs without a window
+pub trait Simulation {
     fn run(&mut self);
+}
+
+#[cfg(feature = "gui")]
+pub trait App: Simulation {
     fn draw(&self, canvas: &mut Canvas<WGPURenderer>);
     fn ui(&mut self, ctx: &egui::Context);
     fn panel_width(&self) -> f32;
     fn focused_pos(&self) -> Option<(f32, f32)>;
+    // Advance anything that moves on its own between frames, returns true while it needs another frame.
+    // elapsed is the wall-clock time since the last frame that animated, s (0 for the first one).
+    fn animate(&mut self, _elapsed: f32) -> bool {
+        false
+    }
 }
@@ -0,0 +1,627 @@
+// Running ticks away from the window's thread, so it keeps drawing while a long run is computed.
+// The window keeps everything that's been recorded (the trajectories and the timeline's ticks),
+// the run only gets the current state. Every so often the run sends a snapshot of its current
+// state and the points recorded since the last one, which the window puts after its own history,
+// so neither side ever copies the whole history.
+// Natively the run is on a thread. In the browser it's in a Web Worker (web/worker.js) with its own
+// copy of the wasm module, and the state goes there and back as a checkpoint. Checkpoints don't
+// include branches or the measurements collected while running, so simulations using those
+// run in the window instead, a frame's budget of ticks at a time.
+// Cancelling keeps the last state that was received and drops the ticks run after it.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use super::{body::Body, timeline::Ticks, IndexChange, Orbital};
+use crate::app::Simulation;
+
+// ticks run between checks for cancelling, and progress updates
+const CHUNK_TICKS: usize = 100;
+
+// Recorded states split off a simulation: every state but the latest one of each node.
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    // by node index
+    trajectories: Vec<Vec<Body>>,
+    // of the removed bodies, in the order they were removed
+    removed: Vec<Vec<Body>>,
+    ticks: Ticks,
+    branches: Vec<History>,
+    // changes to the body indices while these states were recorded, oldest first
+    index_changes: Vec<IndexChange>,
+}
+
+impl History {
+    // Add the states recorded after these. The bodies removed in between take their trajectories
+    // with them, and the barycenters that were rebuilt start over.
+    pub fn append(&mut self, later: History) {
+        for &change in later.index_changes.iter() {
+            self.remap(change);
+        }
+        self.index_changes.extend(later.index_changes);
+
+        append_by_index(&mut self.trajectories, later.trajectories);
+        append_by_index(&mut self.removed, later.removed);
+        self.ticks.append(later.ticks);
+        let mut branches = later.branches.into_iter();
+        for (branch, later) in self.branches.iter_mut().zip(branches.by_ref()) {
+            branch.append(later);
+        }
+        self.branches.extend(branches);
+    }
+
+    fn remap(&mut self, change: IndexChange) {
+        let mut trajectories = vec![];
+        for (i, trajectory) in std::mem::take(&mut self.trajectories)
+            .into_iter()
+            .enumerate()
+        {
+            match change.apply(i) {
+                Some(j) => {
+                    if trajectories.len() <= j {
+                        trajectories.resize_with(j + 1, Vec::new);
+                    }
+                    trajectories[j] = trajectory;
+                }
+                None if matches!(change, IndexChange::Removed { index, .. } if index == i) => {
+                    self.removed.push(trajectory);
+                }
+                None => {}
+            }
+        }
+        self.trajectories = trajectories;
+    }
+}
+
+fn append_by_index(earlier: &mut Vec<Vec<Body>>, later: Vec<Vec<Body>>) {
+    let mut later = later.into_iter();
+    for (earlier, mut later) in earlier.iter_mut().zip(later.by_ref()) {
+        earlier.append(&mut later);
+    }
+    earlier.extend(later);
+}
+
+// The current state of a run with no history, and what it recorded since the last snapshot.
+pub struct Snapshot {
+    sim: Box<Orbital>,
+    history: History,
+}
+
+impl Snapshot {
+    // a later snapshot of the same run, recorded after this one
+    fn append(&mut self, later: Snapshot) {
+        self.sim = later.sim;
+        self.history.append(later.history);
+    }
+}
+
+pub enum Update {
+    // state part way through, to draw
+    Snapshot(Snapshot),
+    Finished(Snapshot),
+    Failed(String),
+}
+
+impl Orbital {
+    // Runs ticks in chunks, calling after_chunk with the number of ticks done after each one.
+    // Stops early when after_chunk returns false or the simulation stops, returns the ticks done.
+    pub fn run_chunked(
+        &mut self,
+        ticks: usize,
+        mut after_chunk: impl FnMut(&mut Orbital, usize) -> bool,
+    ) -> usize {
+        if !self.started {
+            return 0;
+        }
+        let num_ticks = self.num_ticks;
+        let mut done = 0;
+        while done < ticks && !self.stopped {
+            let chunk = CHUNK_TICKS.min(ticks - done);
+            self.num_ticks = chunk as i32;
+            self.run();
+            self.num_ticks = num_ticks;
+            done += chunk;
+            if !after_chunk(self, done) {
+                break;
+            }
+        }
+        done
+    }
+
+    // What the run would need that a checkpoint doesn't have, so it can't run in a Web Worker
+    pub fn not_in_checkpoint(&self) -> Option<&'static str> {
+        if !self.branches.is_empty() {
+            Some("branches")
+        } else if self.chaos.enabled {
+            Some("chaos indicators")
+        } else if self.poincare.enabled {
+            Some("the Poincaré section")
+        } else if self.cluster.enabled {
+            Some("cluster statistics")
+        } else if self.escapes.enabled {
+            Some("escapes")
+        } else if !self.resonances.tracked.is_empty() {
+            Some("tracked resonances")
+        } else {
+            None
+        }
+    }
+
+    // Split off what's been recorded, leaving the latest state of every node to continue from.
+    fn take_history(&mut self) -> History {
+        let trajectories = self
+            .hierarchy
+            .node_weights_mut()
+            .map(|body| {
+                let latest = body.trajectory.pop();
+                let earlier = std::mem::take(&mut body.trajectory);
+                body.trajectory.extend(latest);
+                earlier
+            })
+            .collect();
+        History {
+            trajectories,
+            removed: self
+                .removed
+                .iter_mut()
+                .map(|(_, body)| std::mem::take(&mut body.trajectory))
+                .collect(),
+            ticks: self.timeline.take_ticks(),
+            branches: self
+                .branches
+                .iter_mut()
+                .map(|branch| branch.sim.take_history())
+                .collect(),
+            index_changes: self.take_index_changes(),
+        }
+    }
+
+    // Put back the states recorded before the current ones.
+    fn prepend_history(&mut self, history: History) {
+        let earlier = history.trajectories.into_iter();
+        for (body, mut earlier) in self.hierarchy.node_weights_mut().zip(earlier) {
+            earlier.append(&mut body.trajectory);
+            body.trajectory = earlier;
+        }
+        for ((_, body), mut earlier) in self.removed.iter_mut().zip(history.removed) {
+            earlier.append(&mut body.trajectory);
+            body.trajectory = earlier;
+        }
+        self.timeline.prepend_ticks(history.ticks);
+        for (branch, earlier) in self.branches.iter_mut().zip(history.branches) {
+            branch.sim.prepend_history(earlier);
+        }
+        let mut index_changes = history.index_changes;
+        index_changes.append(&mut self.index_changes);
+        self.index_changes = index_changes;
+    }
+
+    // Copy of the current state to run elsewhere, without the history.
+    fn detached(&mut self) -> Orbital {
+        if self.timeline.is_rewound() {
+            self.discard_later_history();
+        }
+        let history = self.take_history();
+        let sim = self.clone();
+        self.prepend_history(history);
+        sim
+    }
+
+    // The state to draw, and what was recorded since the last snapshot.
+    fn snapshot(&mut self) -> Snapshot {
+        let history = self.take_history();
+        Snapshot {
+            sim: Box::new(self.clone()),
+            history,
+        }
+    }
+
+    // Continue from a snapshot of a run that started from this simulation, keeping the history here.
+    pub fn continue_from(&mut self, snapshot: Snapshot) {
+        let mut history = self.take_history();
+        history.append(snapshot.history);
+        let mut sim = *snapshot.sim;
+        sim.prepend_history(history);
+        if sim.hierarchy.node_count() == self.hierarchy.node_count() {
+            sim.focused = self.focused;
+        }
+        *self = sim;
+    }
+}
+
+// Every update received, in order, as one. Nothing comes after the run finished or failed.
+fn combine(updates: impl Iterator<Item = Update>) -> Option<Update> {
+    let mut combined: Option<Update> = None;
+    for update in updates {
+        combined = match (combined, update) {
+            (None, update) => Some(update),
+            (Some(Update::Snapshot(mut earlier)), Update::Snapshot(later)) => {
+                earlier.append(later);
+                Some(Update::Snapshot(earlier))
+            }
+            (Some(Update::Snapshot(mut earlier)), Update::Finished(later)) => {
+                earlier.append(later);
+                Some(Update::Finished(earlier))
+            }
+            (Some(Update::Snapshot(_)), Update::Failed(e)) => Some(Update::Failed(e)),
+            (finished, _) => finished,
+        };
+    }
+    combined
+}
+
+// A run of ticks on the window's thread, for simulations that can't run in the background.
+// Each frame runs chunks of ticks until its time budget is used up.
+pub struct ForegroundRun {
+    total: usize,
+    done: usize,
+    // why it's not running in the background
+    pub reason: String,
+}
+
+impl ForegroundRun {
+    pub fn new(ticks: usize, reason: String) -> Self {
+        Self {
+            total: ticks,
+            done: 0,
+            reason,
+        }
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (self.done, self.total)
+    }
+
+    // Runs ticks for a frame, returns true once they're all done or the simulation stopped.
+    pub fn run_for(&mut self, sim: &mut Orbital, budget: Duration) -> bool {
+        let started = Instant::now();
+        self.done += sim.run_chunked(self.total - self.done, |_, _| started.elapsed() < budget);
+        self.done >= self.total || sim.stopped || !sim.started
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::BackgroundRun;
+#[cfg(target_arch = "wasm32")]
+pub use web::{run_in_worker, BackgroundRun};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            mpsc, Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::{combine, Orbital, Update};
+
+    // time between snapshots sent to the window
+    const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub struct BackgroundRun {
+        total: usize,
+        done: Arc<AtomicUsize>,
+        cancelled: Arc<AtomicBool>,
+        updates: mpsc::Receiver<Update>,
+    }
+
+    impl BackgroundRun {
+        // why the simulation can't run in the background, if it can't
+        pub fn unsupported(_sim: &Orbital) -> Option<&'static str> {
+            None
+        }
+
+        // Runs the ticks on a thread, starting from the current state of the simulation.
+        // The updates received are for continue_from on the same simulation.
+        pub fn start(sim: &mut Orbital, ticks: usize) -> Result<Self, String> {
+            let mut sim = sim.detached();
+            let done = Arc::new(AtomicUsize::new(0));
+            let cancelled = Arc::new(AtomicBool::new(false));
+            let (sender, updates) = mpsc::channel();
+
+            let (thread_done, thread_cancelled) = (done.clone(), cancelled.clone());
+            thread::Builder::new()
+                .name("simulation".to_string())
+                .spawn(move || {
+                    let mut last_snapshot = Instant::now();
+                    sim.run_chunked(ticks, |sim, done| {
+                        thread_done.store(done, Ordering::Relaxed);
+                        if thread_cancelled.load(Ordering::Relaxed) {
+                            return false;
+                        }
+                        if last_snapshot.elapsed() >= SNAPSHOT_INTERVAL {
+                            last_snapshot = Instant::now();
+                            return sender.send(Update::Snapshot(sim.snapshot())).is_ok();
+                        }
+                        true
+                    });
+                    let _ = sender.send(Update::Finished(sim.snapshot()));
+                })
+                .map_err(|e| e.to_string())?;
+
+            Ok(Self {
+                total: ticks,
+                done,
+                cancelled,
+                updates,
+            })
+        }
+
+        // ticks done and ticks to run
+        pub fn progress(&self) -> (usize, usize) {
+            (self.done.load(Ordering::Relaxed), self.total)
+        }
+
+        // Every update since the last poll as one, without waiting for one.
+        pub fn poll(&mut self) -> Option<Update> {
+            let mut updates = vec![];
+            loop {
+                match self.updates.try_recv() {
+                    Ok(update) => updates.push(update),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        updates.push(Update::Failed("the simulation thread stopped".to_string()));
+                        break;
+                    }
+                }
+            }
+            combine(updates.into_iter())
+        }
+
+        // the thread stops after its current chunk, and what it ran since the last snapshot is dropped
+        pub fn cancel(self) {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use serde::{Deserialize, Serialize};
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_time::Instant;
+
+    use super::{super::checkpoint::Checkpoint, combine, History, Orbital, Snapshot, Update};
+
+    // time between snapshots sent to the page, parsing them takes a little while
+    const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(500);
+
+    // a snapshot as it's sent from the worker
+    #[derive(Serialize, Deserialize)]
+    struct WorkerSnapshot {
+        checkpoint: Checkpoint,
+        history: History,
+    }
+
+    // messages from the worker are [kind, value]
+    #[derive(Default)]
+    struct Inbox {
+        done: usize,
+        // snapshot JSON, and whether the run finished with it
+        received: Vec<(String, bool)>,
+        error: Option<String>,
+    }
+
+    pub struct BackgroundRun {
+        total: usize,
+        // the state the run started from, for what's not in the snapshots
+        base: Orbital,
+        inbox: Rc<RefCell<Inbox>>,
+        worker: web_sys::Worker,
+        // has to live as long as the worker
+        _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+    }
+
+    fn js_error(value: JsValue) -> String {
+        format!("{:?}", value)
+    }
+
+    impl BackgroundRun {
+        // why the simulation can't run in the worker, if it can't
+        pub fn unsupported(sim: &Orbital) -> Option<&'static str> {
+            sim.not_in_checkpoint()
+        }
+
+        // Runs the ticks in a Web Worker, starting from the current state of the simulation.
+        // The updates received are for continue_from on the same simulation.
+        pub fn start(sim: &mut Orbital, ticks: usize) -> Result<Self, String> {
+            let options = web_sys::WorkerOptions::new();
+            options.set_type(web_sys::WorkerType::Module);
+            let worker =
+                web_sys::Worker::new_with_options("./worker.js", &options).map_err(js_error)?;
+
+            let inbox = Rc::new(RefCell::new(Inbox::default()));
+            let worker_inbox = inbox.clone();
+            let on_message = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(
+                move |event: web_sys::MessageEvent| {
+                    let message = js_sys::Array::from(&event.data());
+                    let kind = message.get(0).as_string().unwrap_or_default();
+                    let value = message.get(1);
+                    let mut inbox = worker_inbox.borrow_mut();
+                    match (kind.as_str(), value.as_string()) {
+                        ("progress", _) => inbox.done = value.as_f64().unwrap_or_default() as usize,
+                        ("snapshot", Some(json)) => inbox.received.push((json, false)),
+                        ("finished", Some(json)) => inbox.received.push((json, true)),
+                        (_, e) => inbox.error = Some(e.unwrap_or("the worker failed".to_string())),
+                    }
+                },
+            );
+            worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+            // the trajectories are only the latest states, so this is the current state
+            let base = sim.detached();
+            let message = js_sys::Array::of2(
+                &JsValue::from_str(&base.checkpoint(true).to_json()),
+                &JsValue::from_f64(ticks as f64),
+            );
+            worker.post_message(&message).map_err(js_error)?;
+
+            Ok(Self {
+                total: ticks,
+                base,
+                inbox,
+                worker,
+                _on_message: on_message,
+            })
+        }
+
+        pub fn progress(&self) -> (usize, usize) {
+            (self.inbox.borrow().done, self.total)
+        }
+
+        fn snapshot(&self, json: &str) -> Result<Snapshot, String> {
+            let WorkerSnapshot {
+                checkpoint,
+                history,
+            } = serde_json::from_str(json).map_err(|e| e.to_string())?;
+            let mut sim = self.base.clone();
+            sim.load_checkpoint(checkpoint);
+            sim.take_index_changes();
+            // runs in the worker don't remove bodies
+            sim.removed = self.base.removed.clone();
+            sim.removed_conditions = self.base.removed_conditions.clone();
+            Ok(Snapshot {
+                sim: Box::new(sim),
+                history,
+            })
+        }
+
+        // Every update since the last poll as one.
+        pub fn poll(&mut self) -> Option<Update> {
+            let (received, error) = {
+                let mut inbox = self.inbox.borrow_mut();
+                (std::mem::take(&mut inbox.received), inbox.error.take())
+            };
+            let mut updates: Vec<Update> = received
+                .iter()
+                .map(|(json, finished)| match self.snapshot(json) {
+                    Ok(snapshot) if *finished => Update::Finished(snapshot),
+                    Ok(snapshot) => Update::Snapshot(snapshot),
+                    Err(e) => Update::Failed(e),
+                })
+                .collect();
+            updates.extend(error.map(Update::Failed));
+            let update = combine(updates.into_iter());
+            if matches!(update, Some(Update::Finished(_) | Update::Failed(_))) {
+                self.worker.terminate();
+            }
+            update
+        }
+
+        pub fn cancel(self) {
+            self.worker.terminate();
+        }
+    }
+
+    fn post(kind: &str, value: JsValue) {
+        let scope: web_sys::DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+        let _ = scope.post_message(&js_sys::Array::of2(&JsValue::from_str(kind), &value));
+    }
+
+    fn post_snapshot(kind: &str, sim: &mut Orbital) {
+        let history = sim.take_history();
+        let snapshot = WorkerSnapshot {
+            checkpoint: sim.checkpoint(true),
+            history,
+        };
+        match serde_json::to_string(&snapshot) {
+            Ok(json) => post(kind, JsValue::from_str(&json)),
+            Err(e) => post("error", JsValue::from_str(&e.to_string())),
+        }
+    }
+
+    // Runs in the Web Worker: continues from the current state for a number of ticks,
+    // sending what it recorded every so often.
+    pub fn run_in_worker(checkpoint: &str, ticks: usize) {
+        let checkpoint = match Checkpoint::from_json(checkpoint) {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => return post("error", JsValue::from_str(&e.to_string())),
+        };
+        let mut sim = Orbital::new();
+        sim.load_checkpoint(checkpoint);
+        sim.take_index_changes();
+
+        let mut last_snapshot = Instant::now();
+        sim.run_chunked(ticks, |sim, done| {
+            post("progress", JsValue::from_f64(done as f64));
+            if last_snapshot.elapsed() >= SNAPSHOT_INTERVAL {
+                last_snapshot = Instant::now();
+                post_snapshot("snapshot", sim);
+            }
+            true
+        });
+        post_snapshot("finished", &mut sim);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(t: f32, changes: Vec<IndexChange>) -> Update {
+        let mut sim = Orbital::new();
+        sim.t = t;
+        Update::Snapshot(Snapshot {
+            sim: Box::new(sim),
+            history: History {
+                index_changes: changes,
+                ..Default::default()
+            },
+        })
+    }
+
+    #[test]
+    fn combines_updates_with_every_index_change() {
+        assert!(combine(std::iter::empty()).is_none());
+
+        let updates = vec![
+            update(1., vec![IndexChange::Inserted(0)]),
+            update(2., vec![]),
+            update(3., vec![IndexChange::Truncated(2)]),
+        ];
+        let Some(Update::Snapshot(snapshot)) = combine(updates.into_iter()) else {
+            panic!("expected a snapshot");
+        };
+        assert_eq!(snapshot.sim.t, 3.);
+        assert_eq!(
+            snapshot.history.index_changes,
+            vec![IndexChange::Inserted(0), IndexChange::Truncated(2)]
+        );
+
+        // nothing after the run finished
+        let updates = vec![Update::Failed("stopped".to_string()), update(4., vec![])];
+        assert!(matches!(
+            combine(updates.into_iter()),
+            Some(Update::Failed(_))
+        ));
+    }
+
+    #[test]
+    fn removed_body_takes_its_trajectory() {
+        let states = |n: usize| vec![Body::default(); n];
+        let mut history = History {
+            trajectories: vec![states(1), states(2), states(3), states(4)],
+            ..Default::default()
+        };
+        // body 1 of 3 removed, the barycenter after them is new
+        history.append(History {
+            trajectories: vec![states(10), states(10), states(1)],
+            removed: vec![states(5)],
+            index_changes: vec![IndexChange::Removed {
+                index: 1,
+                num_bodies: 3,
+            }],
+            ..Default::default()
+        });
+        let lengths = |t: &Vec<Vec<Body>>| t.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(lengths(&history.trajectories), vec![11, 13, 1]);
+        assert_eq!(lengths(&history.removed), vec![7]);
+    }
+}
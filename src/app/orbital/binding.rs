@@ -0,0 +1,366 @@
+// Whether each body is bound to its parent in the hierarchy and to the whole system.
+// The energy is the two-body energy between the body and the rest of the group as one point mass,
+// e.g. for the parent it's the parent barycenter without the body itself.
+// A body with positive energy can still be pulled back while the group's gravity dominates,
+// so it's only escaped once it's moving away and beyond the tidal radius of the group.
+// The whole system has no tidal radius, there moving away with positive energy is enough.
+
+use petgraph::{
+    graph::{DiGraph, NodeIndex},
+    Direction,
+};
+
+use crate::app::core::physics::gravitational_parameter;
+
+use super::body::Body;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Binding {
+    Bound,
+    // positive energy, but still approaching or within the tidal radius
+    Unbound,
+    Escaped,
+}
+
+impl Binding {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Binding::Bound => "bound",
+            Binding::Unbound => "unbound",
+            Binding::Escaped => "escaped",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Classification {
+    pub system: Binding,
+    // specific orbital energy, km^2/s^2
+    pub system_energy: f64,
+    // km from the barycenter of all the other bodies
+    pub system_distance: f64,
+    // binding and specific orbital energy relative to the parent,
+    // None when the parent is the whole system
+    pub parent: Option<(Binding, f64)>,
+}
+
+// a group of bodies as a point mass, in f64 since mass * position overflows f32 for large systems
+#[derive(Clone, Copy, Debug)]
+struct PointMass {
+    mass: f64,
+    pos: [f64; 2],
+    vel: [f64; 2],
+}
+
+impl PointMass {
+    fn of(body: &Body) -> Self {
+        Self {
+            mass: body.mass as f64,
+            pos: [body.absolute_pos.x as f64, body.absolute_pos.y as f64],
+            vel: [body.absolute_vel.x as f64, body.absolute_vel.y as f64],
+        }
+    }
+
+    fn combined(points: impl Iterator<Item = PointMass>) -> Option<Self> {
+        let points: Vec<PointMass> = points.collect();
+        let mass: f64 = points.iter().map(|p| p.mass).sum();
+        if mass <= 0. {
+            return None;
+        }
+        let weighted = |get: fn(&PointMass) -> [f64; 2]| {
+            points.iter().fold([0., 0.], |acc, p| {
+                let x = get(p);
+                [acc[0] + x[0] * p.mass / mass, acc[1] + x[1] * p.mass / mass]
+            })
+        };
+        Some(Self {
+            mass,
+            pos: weighted(|p| p.pos),
+            vel: weighted(|p| p.vel),
+        })
+    }
+
+    // the group without one of its members
+    fn without(&self, member: &PointMass) -> Option<Self> {
+        let mass = self.mass - member.mass;
+        if mass <= 0. {
+            return None;
+        }
+        let remove = |total: [f64; 2], part: [f64; 2]| {
+            [
+                (self.mass * total[0] - member.mass * part[0]) / mass,
+                (self.mass * total[1] - member.mass * part[1]) / mass,
+            ]
+        };
+        Some(Self {
+            mass,
+            pos: remove(self.pos, member.pos),
+            vel: remove(self.vel, member.vel),
+        })
+    }
+
+    // (specific orbital energy, distance, radial velocity) of the other point mass relative to this one
+    fn relative_orbit(&self, other: &PointMass) -> (f64, f64, f64) {
+        let r = [other.pos[0] - self.pos[0], other.pos[1] - self.pos[1]];
+        let v = [other.vel[0] - self.vel[0], other.vel[1] - self.vel[1]];
+        let distance = r[0].hypot(r[1]);
+        let mu = gravitational_parameter((self.mass + other.mass) as f32);
+        let energy = 0.5 * (v[0].powi(2) + v[1].powi(2)) - mu / distance;
+        let radial_velocity = (r[0] * v[0] + r[1] * v[1]) / distance;
+        (energy, distance, radial_velocity)
+    }
+}
+
+fn parent(hierarchy: &DiGraph<Body, ()>, nx: NodeIndex) -> Option<NodeIndex> {
+    hierarchy.neighbors_directed(nx, Direction::Incoming).next()
+}
+
+// Hill radius of the group in the field of the rest of its own parent group,
+// None for the root which has nothing around it
+fn tidal_radius(hierarchy: &DiGraph<Body, ()>, group: NodeIndex) -> Option<f64> {
+    let outer = PointMass::of(&hierarchy[parent(hierarchy, group)?]);
+    let group = PointMass::of(&hierarchy[group]);
+    let rest = outer.without(&group)?;
+    let (_, distance, _) = rest.relative_orbit(&group);
+    Some(distance * (group.mass / (3. * rest.mass)).cbrt())
+}
+
+fn binding(energy: f64, radial_velocity: f64, beyond_tidal_radius: bool) -> Binding {
+    if energy < 0. {
+        Binding::Bound
+    } else if radial_velocity > 0. && beyond_tidal_radius {
+        Binding::Escaped
+    } else {
+        Binding::Unbound
+    }
+}
+
+// Classification of every body, by index in the hierarchy. None for barycenters.
+// Binding, specific energy and distance of every body relative to the bodies that are still bound.
+// Escapers are taken out of the system one at a time, otherwise a body escaping in one direction
+// drags the barycenter of the rest along with it and makes the others look unbound too.
+fn system_bindings(leaves: &[PointMass]) -> Vec<Option<(Binding, f64, f64)>> {
+    let mut members = vec![true; leaves.len()];
+    let mut bindings = vec![None; leaves.len()];
+
+    for _ in 0..leaves.len() {
+        let bound = leaves
+            .iter()
+            .zip(members.iter())
+            .filter_map(|(point, &member)| member.then_some(*point));
+        let Some(system) = PointMass::combined(bound) else {
+            break;
+        };
+        bindings = leaves
+            .iter()
+            .zip(members.iter())
+            .map(|(point, &member)| {
+                let rest = if member {
+                    system.without(point)?
+                } else {
+                    system
+                };
+                let (energy, distance, radial_velocity) = rest.relative_orbit(point);
+                Some((binding(energy, radial_velocity, true), energy, distance))
+            })
+            .collect();
+
+        // take out the most unbound member, until only bound members are left
+        let most_unbound = bindings
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| members[i])
+            .filter_map(|(i, b)| match b {
+                Some((Binding::Escaped, energy, _)) => Some((i, *energy)),
+                _ => None,
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match most_unbound {
+            Some((i, _)) if members.iter().filter(|&&m| m).count() > 2 => members[i] = false,
+            _ => break,
+        }
+    }
+    bindings
+}
+
+// Classification of every body, by index in the hierarchy. None for barycenters.
+pub fn classify(hierarchy: &DiGraph<Body, ()>) -> Vec<Option<Classification>> {
+    let leaves: Vec<PointMass> = hierarchy
+        .node_weights()
+        .filter(|b| !b.is_barycenter)
+        .map(PointMass::of)
+        .collect();
+    // leaves come first in the hierarchy, so their indices are the same
+    let system = system_bindings(&leaves);
+
+    hierarchy
+        .node_indices()
+        .map(|nx| {
+            let body = &hierarchy[nx];
+            if body.is_barycenter {
+                return None;
+            }
+            let point = PointMass::of(body);
+
+            let (system_binding, system_energy, system_distance) = (*system.get(nx.index())?)?;
+
+            // no tidal radius means the parent is the root, which is the same as the whole system
+            let parent = parent(hierarchy, nx).and_then(|parent_nx| {
+                let tidal_radius = tidal_radius(hierarchy, parent_nx)?;
+                let rest = PointMass::of(&hierarchy[parent_nx]).without(&point)?;
+                let (energy, distance, radial_velocity) = rest.relative_orbit(&point);
+                Some((
+                    binding(energy, radial_velocity, distance > tidal_radius),
+                    energy,
+                ))
+            });
+
+            Some(Classification {
+                system: system_binding,
+                system_energy,
+                parent,
+                system_distance,
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct Escapes {
+    pub enabled: bool,
+    // remove escaped bodies that are further than removal_distance from the rest of the system
+    pub remove_escapers: bool,
+    pub removal_distance: f32, // km
+    pub classifications: Vec<Option<Classification>>,
+}
+
+impl Default for Escapes {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            remove_escapers: false,
+            removal_distance: 1e6,
+            classifications: vec![],
+        }
+    }
+}
+
+// a change in classification of a body since the last check
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition {
+    pub body: usize,
+    pub escaped: bool, // false = captured
+    pub system: bool,  // relative to the whole system instead of the parent
+}
+
+impl Escapes {
+    pub fn clear(&mut self) {
+        self.classifications.clear();
+    }
+
+    // Classify again and return the bodies that escaped or were captured since the last time.
+    pub fn update(&mut self, hierarchy: &DiGraph<Body, ()>) -> Vec<Transition> {
+        if !self.enabled {
+            return vec![];
+        }
+        let classifications = classify(hierarchy);
+        let mut transitions = vec![];
+
+        // the hierarchy is rebuilt when a body is removed, so only compare when the indices still match
+        if classifications.len() == self.classifications.len() {
+            for (body, (old, new)) in self
+                .classifications
+                .iter()
+                .zip(classifications.iter())
+                .enumerate()
+            {
+                let (Some(old), Some(new)) = (old, new) else {
+                    continue;
+                };
+                let parent = match (old.parent, new.parent) {
+                    (Some((old, _)), Some((new, _))) => Some((false, old, new)),
+                    _ => None,
+                };
+                for (system, old, new) in parent.into_iter().chain([(true, old.system, new.system)])
+                {
+                    if old != Binding::Escaped && new == Binding::Escaped {
+                        transitions.push(Transition {
+                            body,
+                            escaped: true,
+                            system,
+                        });
+                    } else if old != Binding::Bound && new == Binding::Bound {
+                        transitions.push(Transition {
+                            body,
+                            escaped: false,
+                            system,
+                        });
+                    }
+                }
+            }
+        }
+
+        self.classifications = classifications;
+        transitions
+    }
+
+    // escaped bodies that are far enough to be removed, highest index first so removing doesn't shift the others
+    pub fn to_remove(&self) -> Vec<usize> {
+        if !self.remove_escapers {
+            return vec![];
+        }
+        self.classifications
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(i, c)| {
+                let c = c.as_ref()?;
+                let far = c.system_distance > self.removal_distance as f64;
+                (c.system == Binding::Escaped && far).then_some(i)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{
+        core::physics::{circular_velocity, Position, Velocity},
+        orbital::tree::build_hierarchy,
+    };
+
+    use super::*;
+
+    fn classify_satellite(vel: Velocity) -> Classification {
+        let earth = Body::earth();
+        let pos = Position::new(10000., 0.);
+        let satellite = Body {
+            name: "Satellite".to_string(),
+            mass: 1.,
+            absolute_pos: pos,
+            absolute_vel: vel,
+            ..Body::default()
+        };
+        let (hierarchy, _) = build_hierarchy(&[earth, satellite]);
+        classify(&hierarchy)[1].unwrap()
+    }
+
+    #[test]
+    fn bound_unbound_and_escaped() {
+        let pos = Position::new(10000., 0.);
+        let circular = circular_velocity(Position::default(), Body::earth().mass, pos);
+
+        let orbiting = classify_satellite(circular);
+        assert_eq!(orbiting.system, Binding::Bound);
+        assert!(orbiting.system_energy < 0.);
+
+        // faster than escape velocity, but moving towards the earth
+        let incoming = classify_satellite(Velocity::new(-2. * circular.mag(), 0.));
+        assert_eq!(incoming.system, Binding::Unbound);
+
+        let outgoing = classify_satellite(Velocity::new(2. * circular.mag(), 0.));
+        assert_eq!(outgoing.system, Binding::Escaped);
+        // the parent is the root, which is the whole system
+        assert!(outgoing.parent.is_none());
+    }
+}
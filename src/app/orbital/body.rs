@@ -1,15 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    generators::{galaxy, plummer, ClusterSettings, GalaxySettings, RingSettings},
+    particles::TestParticles,
+};
 use crate::app::core::physics::{
-    circ_velocity_barycenter, circ_velocity_bodies, Acceleration, Position, Velocity, R_EARTH_KM,
-    R_MOON_KM, SUN_EARTH_R_KM,
+    gravitational_parameter, Acceleration, Position, Velocity, R_EARTH_KM,
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Body {
     pub name: String,
     pub pos: Position,
     pub v: Velocity,
     pub mass: f32,
     pub radius: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub trajectory: Vec<Body>,
     pub computed_a: Acceleration,
     pub absolute_pos: Position,
@@ -84,26 +90,16 @@ impl Body {
         self.trajectory.push(self.copy());
     }
 
-    // --------------- Constructors ------------------
-    // starting conditions for a low earth orbit, modeled after the ISS
-    pub fn outer_low() -> Self {
-        let r = 400. + R_EARTH_KM;
-        let x = 3000_f32;
-        let y = (r.powi(2) - x.powi(2)).sqrt();
-        let position = Position::new(x, y);
-
-        Self {
-            name: "Orbiting Object".to_string(),
-            mass: 400000., // kg
-            absolute_pos: position,
-            lock_to_circular_velocity: true,
-            selected_vel_lock: 0,
-            trajectory: Vec::new(),
-            default_expanded: true,
-            color: (255, 0, 0),
-            ..Default::default()
-        }
+    // go back to a recorded state, keeping the trajectory
+    pub fn restore(&mut self, state: &Body) {
+        self.pos = state.pos;
+        self.absolute_pos = state.absolute_pos;
+        self.v = state.v;
+        self.absolute_vel = state.absolute_vel;
+        self.computed_a = state.computed_a;
     }
+
+    // --------------- Constructors ------------------
     pub fn earth() -> Self {
         Self {
             name: "Earth".to_string(),
@@ -112,37 +108,9 @@ impl Body {
             ..Default::default()
         }
     }
-    pub fn moon() -> Self {
-        let earth_mass = Self::earth().mass;
-        let earth_pos = Self::earth().absolute_pos;
-        let position = Position::new(0., 3.844e5 + R_EARTH_KM);
-        let moon_mass = 7.34e22;
-
-        Self {
-            name: "Moon".to_string(),
-            mass: moon_mass,
-            radius: R_MOON_KM,
-            absolute_pos: position,
-            absolute_vel: circ_velocity_barycenter(moon_mass, position, earth_mass, earth_pos).0, // km/s
-            default_expanded: true,
-            color: (160, 160, 160),
-            ..Default::default()
-        }
-    }
-    pub fn sun() -> Self {
-        Self {
-            name: "Sun".to_string(),
-            absolute_pos: Position::new(0., 0.),
-            mass: 1.989e30,
-            radius: 6.963e5,
-            is_fixed: false,
-            color: (255, 255, 0),
-            ..Default::default()
-        }
-    }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Preset {
     pub bodies: Vec<Body>,
     pub name: String,
@@ -150,6 +118,10 @@ pub struct Preset {
     pub dt: f32,
     pub ticks_per_press: i32,
     pub draw_frequency: u32,
+    pub particles: TestParticles,
+    pub softening: f32, // km
+    // star cluster: flat hierarchy and cluster statistics
+    pub is_cluster: bool,
 }
 
 impl Preset {
@@ -161,248 +133,127 @@ impl Preset {
             ..Default::default()
         }
     }
-    pub fn defaults() -> Vec<Self> {
-        let fixed_earth = Body {
-            is_fixed: true,
-            lock_to_circular_velocity: false,
-            lock_to_escape_velocity: false,
-            ..Body::earth()
-        };
+    // Two disk galaxies passing each other, like Toomre & Toomre (1972).
+    // The disks rotate in the same direction as the encounter (prograde), which makes long tidal tails.
+    // Only the two cores are bodies, the disks are test particles so the hierarchy stays small.
+    // impact_parameter: km, relative_velocity: km/s at the start
+    pub fn galaxy_collision(
+        impact_parameter: f32,
+        relative_velocity: f32,
+        particles_per_galaxy: usize,
+    ) -> Self {
+        let settings = GalaxySettings::default();
+        let separation = Self::galaxy_separation();
 
-        vec![
-            Preset {
-                bodies: vec![fixed_earth, Body::outer_low()],
-                name: String::from("Small object orbiting Earth"),
-                distance_per_px: 150,
-                ..Preset::default()
+        let (core1, disk1) = galaxy(
+            "Galaxy 1",
+            Position::new(-separation / 2., impact_parameter / 2.),
+            Velocity::new(relative_velocity / 2., 0.),
+            &GalaxySettings {
+                ring: RingSettings {
+                    count: particles_per_galaxy,
+                    seed: 1,
+                    color: (150, 180, 255),
+                    ..settings.ring.clone()
+                },
+                ..settings.clone()
             },
-            Self::sun_earth_moon(),
-            Self::earth_moon(),
-            Self::three_body(),
-            Self::equal_binary(),
-            Self::unequal_binary(),
-        ]
-    }
-
-    // to keep the barycenter stationary, all bodies should start with an initialized velocity,
-    // or all bodies start with no velocity and use velocity lock
-    pub fn earth_moon() -> Self {
-        let barycenter_earth = Body {
-            is_fixed: false,
-            lock_to_circular_velocity: true,
-            selected_vel_lock: 1,
-            ..Body::earth()
-        };
-        let moon_orbiting_earth = Body {
-            absolute_vel: Velocity::default(),
-            lock_to_circular_velocity: true,
-            selected_vel_lock: 0,
-            ..Body::moon()
-        };
-
-        Preset {
-            bodies: vec![barycenter_earth, moon_orbiting_earth],
-            name: String::from("Moon orbiting Earth"),
-            distance_per_px: 4000,
-            ticks_per_press: 100000,
-            ..Preset::default()
-        }
-    }
-    pub fn three_body() -> Self {
-        let mut b1 = Body {
-            name: String::from("1"),
-            radius: 1000.,
-            mass: 1e21,
-            absolute_pos: Position::new(-5000., -5000.),
-            selected_vel_lock: 1,
-            color: (0, 255, 0),
-            ..Default::default()
-        };
-        let mut b2 = Body {
-            name: String::from("2"),
-            absolute_pos: Position::new(0., 5000.),
-            selected_vel_lock: 0,
-            color: (255, 0, 0),
-            ..b1.clone()
-        };
-        let b3: Body = Body {
-            name: String::from("3"),
-            absolute_pos: Position::new(7000., -5000.),
-            absolute_vel: Velocity::new(-0.2, 0.08),
-            lock_to_circular_velocity: false,
-            color: (255, 200, 0),
-            ..b1.clone()
-        };
+        );
+        let (core2, disk2) = galaxy(
+            "Galaxy 2",
+            Position::new(separation / 2., -impact_parameter / 2.),
+            Velocity::new(-relative_velocity / 2., 0.),
+            &GalaxySettings {
+                ring: RingSettings {
+                    count: particles_per_galaxy,
+                    seed: 2,
+                    color: (255, 190, 120),
+                    ..settings.ring.clone()
+                },
+                ..settings
+            },
+        );
 
-        let (circ_v_1, circ_v_2) = circ_velocity_bodies(&b1, &b2);
-        b1.absolute_vel = circ_v_1;
-        b2.absolute_vel = circ_v_2;
+        let mut particles = disk1;
+        particles.extend(&disk2);
 
         Self {
-            name: String::from("Three body"),
-            distance_per_px: 300,
-            bodies: vec![b1, b2, b3],
+            name: "Galaxy collision".to_string(),
+            bodies: vec![core1, core2],
+            particles,
+            distance_per_px: 500_000,
+            dt: 1e5,
+            ticks_per_press: 100,
+            draw_frequency: 1_000_000,
             ..Preset::default()
         }
     }
 
-    pub fn sun_earth_moon() -> Self {
-        let sun = Body {
-            lock_to_circular_velocity: true,
-            selected_vel_lock: 3,
-            ..Body::sun()
-        };
-
-        let earth = Body {
-            absolute_pos: Position::new(0., SUN_EARTH_R_KM),
-            lock_to_circular_velocity: true,
-            selected_vel_lock: 2,
-            ..Body::earth()
-        };
-
-        let default_moon = Body::moon();
-        let moon = Body {
-            absolute_pos: earth.absolute_pos.add(default_moon.absolute_pos),
-            absolute_vel: Velocity::default(),
-            lock_to_circular_velocity: true,
-            selected_vel_lock: 1,
-            ..default_moon
-        };
-
-        Self {
-            name: "Sun + Earth + Moon".to_string(),
-            bodies: vec![sun, earth, moon],
-            distance_per_px: 1400000,
-            dt: 50.,
-            ticks_per_press: 100000,
-            draw_frequency: 24 * 60 * 60,
-        }
+    // starting distance between the cores along the direction of motion, far enough that the disks don't overlap
+    fn galaxy_separation() -> f32 {
+        4. * GalaxySettings::default().ring.outer_radius
     }
 
-    pub fn _hierarchy_test() -> Self {
-        let base = Self::sun_earth_moon();
-
-        let cluster_y = -599_597_870_f32;
-        let earth_2 = Body {
-            name: "Earth 2".to_string(),
-            absolute_pos: Position::new(0., cluster_y - 5_978_700.),
-            ..Body::earth()
-        };
-        let moon_2 = Body {
-            name: "Moon 2".to_string(),
-            absolute_pos: earth_2.absolute_pos.add(Body::moon().absolute_pos),
-            ..Body::moon()
-        };
-
-        let earth_3 = Body {
-            name: "Earth 3".to_string(),
-            absolute_pos: Position::new(0., cluster_y + 9_597_870.),
-            ..Body::earth()
-        };
-        let moon_3 = Body {
-            name: "Moon 3".to_string(),
-            absolute_pos: earth_3.absolute_pos.add(Body::moon().absolute_pos),
-            ..Body::moon()
-        };
-        let third = Body {
-            name: "Third body".to_string(),
-            absolute_pos: earth_3.absolute_pos.minus(Body::moon().absolute_pos),
-            ..Body::moon()
-        };
-        let small = Body {
-            name: "Small test particle".to_string(),
-            mass: 10.,
-            absolute_pos: third.absolute_pos.add(Position::new(100., 100.)),
-            ..Body::default()
-        };
-
-        let bodies = [
-            base.bodies,
-            vec![earth_2, moon_2, earth_3, moon_3, third, small],
-        ]
-        .concat();
-
-        Self {
-            name: "hierarchy_test".to_string(),
-            bodies,
-            ..base
-        }
+    // relative velocity at the starting separation where the cores are just unbound
+    // v = sqrt(2 G (m1 + m2) / r)
+    pub fn galaxy_parabolic_velocity() -> f32 {
+        let mu = gravitational_parameter(2. * GalaxySettings::default().core_mass);
+        (2. * mu / Self::galaxy_separation() as f64).sqrt() as f32
     }
 
-    // binary system with equal masses and circular velocities
-    // both bodies will move in the exact same circle
-    pub fn equal_binary() -> Self {
-        let body1 = Body {
-            name: "1".to_string(),
-            mass: 1.23e22,
-            radius: 8000.,
-            absolute_pos: Position::new(50000., 0.),
-            lock_to_circular_velocity: true,
-            selected_vel_lock: 1,
-            default_expanded: true,
-            ..Body::default()
-        };
-        let body2 = Body {
-            name: "2".to_string(),
-            absolute_pos: Position::new(-50000., 0.),
-            lock_to_circular_velocity: true,
-            selected_vel_lock: 0,
-            color: (220, 0, 0),
-            default_expanded: false,
-            ..body1.clone()
-        };
-
+    // Equal mass stars in virial equilibrium, with softening to smooth out close encounters.
+    // One press runs about 2 Hénon time units, a little less than a crossing time.
+    pub fn plummer_cluster(settings: &ClusterSettings) -> Self {
+        let time_unit = settings.time_unit();
         Self {
-            name: "Equal circular binary system".to_string(),
-            bodies: vec![body1, body2],
-            distance_per_px: 1000,
-            dt: 10.,
-            ticks_per_press: 10000,
-            draw_frequency: 24 * 60 * 60,
+            name: "Star cluster (Plummer)".to_string(),
+            bodies: plummer(settings),
+            distance_per_px: (settings.length_unit / 100.) as i32,
+            dt: time_unit / 500.,
+            ticks_per_press: 1000,
+            draw_frequency: (time_unit / 10.) as u32,
+            softening: settings.softening_km(),
+            is_cluster: true,
             ..Preset::default()
         }
     }
 
-    // binary system with unequal masses
-    // If circular velocity is enabled, both bodies will travel in different-sized circles.
-    // If velocities are slightly changed, both bodies will travel in elliptical orbits.
-    // If the overall system velocity is changed, then both bodies will be orbiting a moving barycenter.
-    pub fn unequal_binary() -> Self {
-        let body1_pos = Position::new(-100000., 150000.);
-        let body2_pos = Position::new(-200000., 150000.);
+    // Zoom to fit every body, and a dt small enough for the fastest orbit:
+    // each body's period around the nearest heavier body, as if it were circular.
+    pub fn imported(name: &str, bodies: Vec<Body>) -> Self {
+        let max_distance = bodies
+            .iter()
+            .map(|b| b.absolute_pos.mag())
+            .fold(0., f32::max);
 
-        let mut body1 = Body {
-            name: "1".to_string(),
-            mass: 6.23e22,
-            radius: 8000.,
-            absolute_pos: body1_pos,
-            lock_to_circular_velocity: false,
-            selected_vel_lock: 1,
-            default_expanded: true,
-            ..Body::default()
+        let shortest_period = bodies
+            .iter()
+            .filter_map(|body| {
+                bodies
+                    .iter()
+                    .filter(|other| other.mass > body.mass)
+                    .map(|other| {
+                        let r = body.absolute_pos.minus(other.absolute_pos).mag() as f64;
+                        let mu = gravitational_parameter(other.mass + body.mass);
+                        std::f64::consts::TAU * (r.powi(3) / mu).sqrt()
+                    })
+                    .reduce(f64::min)
+            })
+            .fold(f64::INFINITY, f64::min);
+        let dt = if shortest_period.is_finite() {
+            (shortest_period / 2000.).max(1.) as f32
+        } else {
+            60.
         };
-        let mut body2 = Body {
-            name: "2".to_string(),
-            mass: 1.23e22,
-            absolute_pos: body2_pos,
-            lock_to_circular_velocity: false,
-            selected_vel_lock: 0,
-            color: (220, 0, 0),
-            ..body1.clone()
-        };
-
-        let (body1_circ_v, body2_circ_v) = circ_velocity_bodies(&body1, &body2);
-        body1.absolute_vel = body1_circ_v.add(Velocity::new(0.02, -0.02));
-        body2.absolute_vel = body2_circ_v;
 
         Self {
-            name: "Unequal binary system".to_string(),
-            bodies: vec![body1, body2],
-            distance_per_px: 2000,
-            dt: 10.,
-            ticks_per_press: 10000,
-            draw_frequency: 24 * 60 * 60,
-            ..Preset::default()
+            name: name.to_string(),
+            bodies,
+            distance_per_px: ((max_distance / 300.) as i32).max(1),
+            dt,
+            ticks_per_press: 1000,
+            draw_frequency: (dt * 10.) as u32,
+            ..Default::default()
         }
     }
 }
@@ -0,0 +1,44 @@
+// Alternative versions of the simulation forked from the main one, e.g. to see what a small nudge does.
+// Each branch is a full copy that runs in lockstep with the main simulation,
+// bodies are matched by name to measure how far the branch has moved away from the original.
+
+use super::{body::Body, Orbital};
+
+#[derive(Clone)]
+pub struct Branch {
+    pub name: String,
+    pub sim: Orbital,
+}
+
+impl Branch {
+    pub fn new(name: &str, sim: Orbital) -> Self {
+        Self {
+            name: name.to_string(),
+            sim,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    pub name: String,
+    pub distance: f32, // km
+    pub velocity: f32, // km/s
+}
+
+// difference between the bodies with the same name, in the order of the main simulation
+pub fn divergence(main: &[&Body], branch: &[&Body]) -> Vec<Divergence> {
+    main.iter()
+        .filter(|b| !b.is_barycenter)
+        .filter_map(|b| {
+            let other = branch
+                .iter()
+                .find(|other| !other.is_barycenter && other.name == b.name)?;
+            Some(Divergence {
+                name: b.name.clone(),
+                distance: b.absolute_pos.abs_diff(other.absolute_pos),
+                velocity: b.absolute_vel.minus(other.absolute_vel).mag(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,148 @@
+// Chaos indicators from the variational equations.
+// A tangent vector (small offset of every body's position and velocity) is propagated
+// with the linearized equations of motion alongside the main state.
+// In a chaotic system the offset grows exponentially, in a regular one it grows at most linearly.
+//
+// Maximal Lyapunov exponent: average exponential growth rate of the offset, 1/s.
+// MEGNO (Mean Exponential Growth factor of Nearby Orbits): time-weighted average of the growth rate,
+// converges to 2 for regular (quasi-periodic) orbits and grows like λt/2 for chaotic ones,
+// so it separates the two much sooner than the Lyapunov exponent.
+//
+// Uses direct N-body forces between all bodies, independent of the hierarchy.
+
+use crate::app::core::physics::{gravitational_parameter, Position};
+
+#[derive(Clone, Copy, Debug)]
+pub struct ChaosIndicators {
+    pub t: f64, // s since the indicators started
+    pub lyapunov_exponent: f64,
+    pub megno: f64,
+    pub mean_megno: f64,
+}
+
+#[derive(Clone, Default)]
+pub struct Chaos {
+    pub enabled: bool,
+    // tangent vector: (position offset, velocity offset) of every body, normalized to 1 after each tick
+    offsets: Vec<([f64; 2], [f64; 2])>,
+    t: f64,
+    // sum of ln(growth) over each tick
+    log_growth: f64,
+    // integral of t * d(ln growth), MEGNO is 2/t times this
+    weighted_log_growth: f64,
+    // integral of MEGNO over time, for the mean
+    megno_integral: f64,
+}
+
+impl Chaos {
+    pub fn clear(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            ..Self::default()
+        };
+    }
+
+    pub fn indicators(&self) -> Option<ChaosIndicators> {
+        if self.t <= 0. {
+            return None;
+        }
+        Some(ChaosIndicators {
+            t: self.t,
+            lyapunov_exponent: self.log_growth / self.t,
+            megno: self.megno(),
+            mean_megno: self.megno_integral / self.t,
+        })
+    }
+
+    fn megno(&self) -> f64 {
+        if self.t > 0. {
+            2. * self.weighted_log_growth / self.t
+        } else {
+            0.
+        }
+    }
+
+    // Same initial offset every time so runs can be compared.
+    fn initialize(&mut self, num_bodies: usize) {
+        self.clear();
+        self.offsets = (0..num_bodies)
+            .map(|i| {
+                let angle = i as f64 * 2.4; // spread the directions out
+                ([angle.cos(), angle.sin()], [0., 0.])
+            })
+            .collect();
+        self.normalize();
+    }
+
+    fn norm(&self) -> f64 {
+        self.offsets
+            .iter()
+            .map(|(dr, dv)| dr[0].powi(2) + dr[1].powi(2) + dv[0].powi(2) + dv[1].powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn normalize(&mut self) -> f64 {
+        let norm = self.norm();
+        for (dr, dv) in self.offsets.iter_mut() {
+            for x in dr.iter_mut().chain(dv.iter_mut()) {
+                *x /= norm;
+            }
+        }
+        norm
+    }
+
+    // Advance the tangent vector by one tick, using the positions and masses at the start of the tick.
+    // Uses symplectic euler like the main integration.
+    pub fn step(&mut self, bodies: &[(Position, f32)], dt: f32) {
+        if !self.enabled {
+            return;
+        }
+        if self.offsets.len() != bodies.len() {
+            self.initialize(bodies.len());
+        }
+        let dt = dt as f64;
+
+        // da_i = sum over j of mu_j (d / r^3 - 3 (r.d) r / r^5)
+        // with r = r_j - r_i and d = dr_j - dr_i
+        let mut accelerations = vec![[0_f64; 2]; bodies.len()];
+        for (i, &(pos_i, mass_i)) in bodies.iter().enumerate() {
+            for (j, &(pos_j, mass_j)) in bodies.iter().enumerate().skip(i + 1) {
+                let r = [(pos_j.x - pos_i.x) as f64, (pos_j.y - pos_i.y) as f64];
+                let (dr_i, dr_j) = (self.offsets[i].0, self.offsets[j].0);
+                let d = [dr_j[0] - dr_i[0], dr_j[1] - dr_i[1]];
+
+                let r_squared = r[0].powi(2) + r[1].powi(2);
+                let r3 = r_squared.powf(1.5);
+                let r_dot_d = r[0] * d[0] + r[1] * d[1];
+                let term = [
+                    d[0] / r3 - 3. * r_dot_d * r[0] / (r3 * r_squared),
+                    d[1] / r3 - 3. * r_dot_d * r[1] / (r3 * r_squared),
+                ];
+
+                let (mu_i, mu_j) = (
+                    gravitational_parameter(mass_i),
+                    gravitational_parameter(mass_j),
+                );
+                for k in 0..2 {
+                    accelerations[i][k] += mu_j * term[k];
+                    accelerations[j][k] -= mu_i * term[k];
+                }
+            }
+        }
+
+        for ((dr, dv), da) in self.offsets.iter_mut().zip(accelerations.iter()) {
+            for k in 0..2 {
+                dv[k] += da[k] * dt;
+                dr[k] += dv[k] * dt;
+            }
+        }
+
+        let growth = self.normalize().ln();
+        let t_mid = self.t + dt / 2.;
+        self.t += dt;
+        self.log_growth += growth;
+        self.weighted_log_growth += t_mid * growth;
+        self.megno_integral += self.megno() * dt;
+    }
+}
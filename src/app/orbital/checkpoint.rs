@@ -0,0 +1,256 @@
+// Saving the whole simulation to a file and loading it again to continue from the same state.
+// The file is JSON with a format version, which is increased whenever the format changes
+// so that older files are rejected instead of loading into the wrong fields.
+// The hierarchy is saved as its nodes and edges so that loading doesn't regroup the bodies.
+// Trajectories are optional since they're most of the file, without them the loaded
+// simulation starts its timeline at the saved state.
+// Measurements collected while running (resonances, Poincaré points, chaos indicators,
+// cluster samples and escapes) aren't saved and start over after loading.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::core::physics::Integrator;
+
+use super::{body::Body, events::Events, particles::TestParticles, timeline::Timeline};
+
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    pub dt: f32,
+    // files from before the integrator could be chosen used symplectic Euler
+    #[serde(default)]
+    pub integrator: Integrator,
+    pub t: f32,
+    pub num_ticks: i32,
+    pub distance_per_px: f32,
+    pub draw_frequency: u32,
+    pub softening: f32, // km
+    pub flat_hierarchy: bool,
+    pub started: bool,
+    pub stopped: bool,
+    // total energy at the start, that the energy error is relative to
+    pub initial_e: f64,
+    // hierarchy nodes by index, and (parent, child) edges
+    pub bodies: Vec<Body>,
+    pub edges: Vec<(usize, usize)>,
+    pub root: usize,
+    pub particles: TestParticles,
+    pub events: Events,
+    // only with trajectories, since it indexes into them
+    pub timeline: Option<Timeline>,
+    pub removed: Vec<(usize, Body)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CheckpointError {
+    Parse(String),
+    Version(u32),
+    Invalid(String),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Parse(e) => write!(f, "not a checkpoint file: {}", e),
+            CheckpointError::Version(v) => write!(
+                f,
+                "checkpoint version {} isn't supported, expected {}",
+                v, VERSION
+            ),
+            CheckpointError::Invalid(e) => write!(f, "invalid checkpoint: {}", e),
+        }
+    }
+}
+
+impl Checkpoint {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("checkpoint is always serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, CheckpointError> {
+        // check the version first, a different version can fail to parse for other reasons
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u32,
+        }
+        let versioned: Versioned =
+            serde_json::from_str(json).map_err(|e| CheckpointError::Parse(e.to_string()))?;
+        if versioned.version != VERSION {
+            return Err(CheckpointError::Version(versioned.version));
+        }
+
+        let checkpoint: Checkpoint =
+            serde_json::from_str(json).map_err(|e| CheckpointError::Parse(e.to_string()))?;
+        checkpoint.validate()?;
+        Ok(checkpoint)
+    }
+
+    fn validate(&self) -> Result<(), CheckpointError> {
+        let num_nodes = self.bodies.len();
+        if self.root >= num_nodes {
+            return Err(CheckpointError::Invalid(format!(
+                "root {} but only {} bodies",
+                self.root, num_nodes
+            )));
+        }
+        if let Some(&(parent, child)) = self
+            .edges
+            .iter()
+            .find(|&&(parent, child)| parent >= num_nodes || child >= num_nodes)
+        {
+            return Err(CheckpointError::Invalid(format!(
+                "edge {} -> {} but only {} bodies",
+                parent, child, num_nodes
+            )));
+        }
+        // one parent each, and following the parents from any body ends instead of going around
+        let mut parents = vec![None; num_nodes];
+        for &(parent, child) in &self.edges {
+            if let Some(other) = parents[child].replace(parent) {
+                return Err(CheckpointError::Invalid(format!(
+                    "body {} has parents {} and {}",
+                    child, other, parent
+                )));
+            }
+        }
+        for start in 0..num_nodes {
+            let mut node = start;
+            for _ in 0..=num_nodes {
+                match parents[node] {
+                    Some(parent) => node = parent,
+                    None => break,
+                }
+            }
+            if parents[node].is_some() {
+                return Err(CheckpointError::Invalid(format!(
+                    "body {} is its own ancestor",
+                    node
+                )));
+            }
+        }
+        self.particles.check().map_err(CheckpointError::Invalid)?;
+        if let Some(timeline) = &self.timeline {
+            timeline
+                .check(&self.bodies)
+                .map_err(CheckpointError::Invalid)?;
+        }
+        if self.dt <= 0. {
+            return Err(CheckpointError::Invalid(format!("dt is {}", self.dt)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        core::physics::{Position, Velocity},
+        orbital::Orbital,
+        Simulation,
+    };
+
+    #[test]
+    fn rejects_other_versions() {
+        let json = r#"{"version": 999, "bodies": []}"#;
+        assert_eq!(
+            Checkpoint::from_json(json).err(),
+            Some(CheckpointError::Version(999))
+        );
+        assert!(matches!(
+            Checkpoint::from_json("not json"),
+            Err(CheckpointError::Parse(_))
+        ));
+    }
+
+    // a running simulation with barycenters and a test particle, as saved
+    fn saved() -> serde_json::Value {
+        let mut sim = Orbital::new();
+        sim.load_preset(3);
+        sim.prepare();
+        sim.start();
+        sim.particles.add(
+            Position::new(1e6, 0.),
+            Velocity::new(0., 1.),
+            (255, 255, 255),
+        );
+        sim.num_ticks = 10;
+        sim.run();
+        serde_json::to_value(sim.checkpoint(true)).unwrap()
+    }
+
+    fn load(json: &serde_json::Value) -> Result<Checkpoint, CheckpointError> {
+        Checkpoint::from_json(&json.to_string())
+    }
+
+    #[test]
+    fn rejects_particle_arrays_of_different_lengths() {
+        let json = saved();
+        assert!(load(&json).is_ok());
+        for field in ["x", "vy", "active", "initial"] {
+            let mut json = json.clone();
+            json["particles"][field].as_array_mut().unwrap().pop();
+            assert!(
+                matches!(load(&json), Err(CheckpointError::Invalid(_))),
+                "{}",
+                field
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_timeline_that_disagrees_with_trajectories() {
+        let json = saved();
+        let mut shorter_steps = json.clone();
+        shorter_steps["timeline"]["steps"]
+            .as_array_mut()
+            .unwrap()
+            .pop();
+        let mut more_ticks = json.clone();
+        more_ticks["timeline"]["times"]
+            .as_array_mut()
+            .unwrap()
+            .push(1.into());
+        more_ticks["timeline"]["steps"]
+            .as_array_mut()
+            .unwrap()
+            .push(1.into());
+        more_ticks["timeline"]["integrators"]
+            .as_array_mut()
+            .unwrap()
+            .clear();
+        let mut first_tick = json.clone();
+        first_tick["timeline"]["first_tick"] = 11.into();
+        let mut viewing = json.clone();
+        viewing["timeline"]["viewing"] = 10.into();
+
+        for json in [shorter_steps, more_ticks, first_tick, viewing] {
+            assert!(matches!(load(&json), Err(CheckpointError::Invalid(_))));
+        }
+    }
+
+    #[test]
+    fn rejects_edges_that_are_not_a_tree() {
+        let json = saved();
+        let edges = json["edges"].as_array().unwrap();
+        let first = edges[0].clone();
+        let mut two_parents = json.clone();
+        two_parents["edges"]
+            .as_array_mut()
+            .unwrap()
+            .push(first.clone());
+        let mut cycle = json.clone();
+        cycle["edges"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!([first[1], json["root"]]));
+
+        for json in [two_parents, cycle] {
+            assert!(matches!(load(&json), Err(CheckpointError::Invalid(_))));
+        }
+    }
+}
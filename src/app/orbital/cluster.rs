@@ -0,0 +1,194 @@
+// Statistics of a star cluster over time.
+// Virial ratio: Q = T / |W| is 0.5 in equilibrium, larger while expanding and smaller while contracting.
+// Half-mass radius: distance from the center of mass that encloses half of the mass.
+// Core radius: the same for 10% of the mass. Two-body encounters carry energy outwards,
+// so the halo slowly expands while the core contracts until it collapses.
+// Escapers: stars with positive energy relative to the rest of the cluster
+// that are already outside twice the half-mass radius.
+
+use crate::app::core::physics::{gravitational_parameter, Position, Velocity};
+
+// position, velocity and mass of one star
+pub type Star = (Position, Velocity, f32);
+
+#[derive(Clone, Copy, Debug)]
+pub struct ClusterSample {
+    pub t: f32,
+    pub virial_ratio: f64,
+    pub half_mass_radius: f32, // km
+    pub core_radius: f32,      // km
+    pub escapers: usize,
+}
+
+#[derive(Clone, Default)]
+pub struct ClusterStats {
+    pub enabled: bool,
+    pub samples: Vec<ClusterSample>,
+    last_sample_t: Option<f32>,
+}
+
+impl ClusterStats {
+    pub fn clear(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            ..Self::default()
+        };
+    }
+
+    // drop the samples after t, when going back in time
+    pub fn truncate(&mut self, t: f32) {
+        self.samples.retain(|sample| sample.t <= t);
+        self.last_sample_t = self.samples.last().map(|sample| sample.t);
+    }
+
+    pub fn is_due(&self, t: f32, interval: f32) -> bool {
+        match self.last_sample_t {
+            Some(last_t) => t - last_t >= interval,
+            None => true,
+        }
+    }
+
+    pub fn sample(&mut self, stars: &[Star], softening: f32, t: f32) {
+        if !self.enabled {
+            return;
+        }
+        self.last_sample_t = Some(t);
+        if let Some(sample) = measure(stars, softening, t) {
+            self.samples.push(sample);
+        }
+    }
+
+    pub fn latest(&self) -> Option<&ClusterSample> {
+        self.samples.last()
+    }
+
+    // sample with the smallest core radius so far, compared to the first sample it shows how far the core has collapsed
+    pub fn deepest_core(&self) -> Option<&ClusterSample> {
+        self.samples
+            .iter()
+            .min_by(|a, b| a.core_radius.total_cmp(&b.core_radius))
+    }
+}
+
+// softening: km, the same as in the force law so the potential energy matches
+pub fn measure(stars: &[Star], softening: f32, t: f32) -> Option<ClusterSample> {
+    if stars.len() < 2 {
+        return None;
+    }
+    let total_mass: f64 = stars.iter().map(|&(_, _, m)| m as f64).sum();
+    let weighted_mean = |get: &dyn Fn(&Star) -> [f32; 2]| {
+        stars.iter().fold([0., 0.], |acc, star| {
+            let [x, y] = get(star);
+            let m = star.2 as f64 / total_mass;
+            [acc[0] + x as f64 * m, acc[1] + y as f64 * m]
+        })
+    };
+    let center = weighted_mean(&|(p, _, _)| [p.x, p.y]);
+    let center_vel = weighted_mean(&|(_, v, _)| [v.x, v.y]);
+
+    // distance from the center, kinetic and potential energy per unit mass (km^2/s^2)
+    let mut radii = Vec::with_capacity(stars.len());
+    let mut specific_kinetic = Vec::with_capacity(stars.len());
+    let mut specific_potential = vec![0_f64; stars.len()];
+    for (i, &(p, v, _)) in stars.iter().enumerate() {
+        radii.push((p.x as f64 - center[0]).hypot(p.y as f64 - center[1]));
+        specific_kinetic.push(
+            0.5 * ((v.x as f64 - center_vel[0]).powi(2) + (v.y as f64 - center_vel[1]).powi(2)),
+        );
+
+        for (j, &(p2, _, m2)) in stars.iter().enumerate().skip(i + 1) {
+            let distance = ((p.x - p2.x) as f64)
+                .hypot((p.y - p2.y) as f64)
+                .hypot(softening as f64);
+            specific_potential[i] -= gravitational_parameter(m2) / distance;
+            specific_potential[j] -= gravitational_parameter(stars[i].2) / distance;
+        }
+    }
+
+    let kinetic: f64 = stars
+        .iter()
+        .zip(specific_kinetic.iter())
+        .map(|(&(_, _, m), k)| m as f64 * k)
+        .sum();
+    // every pair is counted twice
+    let potential: f64 = stars
+        .iter()
+        .zip(specific_potential.iter())
+        .map(|(&(_, _, m), phi)| 0.5 * m as f64 * phi)
+        .sum();
+
+    // Lagrangian radii
+    let mut by_radius: Vec<(f64, f64)> = radii
+        .iter()
+        .zip(stars.iter())
+        .map(|(&r, &(_, _, m))| (r, m as f64))
+        .collect();
+    by_radius.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let lagrangian_radius = |fraction: f64| {
+        let mut enclosed = 0.;
+        for &(r, m) in by_radius.iter() {
+            enclosed += m;
+            if enclosed >= fraction * total_mass {
+                return r as f32;
+            }
+        }
+        by_radius.last().map(|&(r, _)| r as f32).unwrap_or(0.)
+    };
+    let half_mass_radius = lagrangian_radius(0.5);
+
+    let escapers = (0..stars.len())
+        .filter(|&i| {
+            specific_kinetic[i] + specific_potential[i] > 0.
+                && radii[i] > 2. * half_mass_radius as f64
+        })
+        .count();
+
+    Some(ClusterSample {
+        t,
+        virial_ratio: kinetic / potential.abs(),
+        half_mass_radius,
+        core_radius: lagrangian_radius(0.1),
+        escapers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::orbital::generators::{plummer, ClusterSettings};
+
+    use super::*;
+
+    #[test]
+    fn plummer_cluster_in_virial_equilibrium() {
+        let settings = ClusterSettings {
+            count: 200,
+            ..ClusterSettings::default()
+        };
+        let stars: Vec<Star> = plummer(&settings)
+            .iter()
+            .map(|b| (b.absolute_pos, b.absolute_vel, b.mass))
+            .collect();
+
+        let sample = measure(&stars, 0., 0.).unwrap();
+        assert!((sample.virial_ratio - 0.5).abs() < 1e-3);
+        // projecting can leave a few of the outermost stars unbound
+        assert!(sample.escapers <= stars.len() / 50);
+        assert!(sample.core_radius < sample.half_mass_radius);
+
+        // total energy is -1/4 in Hénon units, and the half-mass radius of a Plummer model is about 0.77
+        let energy_unit = gravitational_parameter(settings.total_mass) * settings.total_mass as f64
+            / settings.length_unit as f64;
+        let potential: f64 = (0..stars.len())
+            .flat_map(|i| (i + 1..stars.len()).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                -gravitational_parameter(stars[i].2) * stars[j].2 as f64
+                    / stars[i].0.abs_diff(stars[j].0) as f64
+            })
+            .sum();
+        let energy = potential / 2.;
+        assert!((energy / energy_unit + 0.25).abs() < 1e-3);
+
+        let half_mass_radius = sample.half_mass_radius / settings.length_unit;
+        assert!(half_mass_radius > 0.4 && half_mass_radius < 1.2);
+    }
+}
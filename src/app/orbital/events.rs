@@ -0,0 +1,297 @@
+// Detecting events during the simulation, like periapsis or closest approach.
+// Each condition is a value that crosses zero when the event happens.
+// A sign change between two ticks means the event happened somewhere within that tick,
+// then the exact time is found with a binary search on the state interpolated within the tick.
+
+use serde::{Deserialize, Serialize};
+
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use crate::app::core::{
+    midpoint,
+    physics::{hermite_interpolation, Position},
+};
+
+use super::body::Body;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EventCondition {
+    // radial velocity relative to the body's parent changes sign
+    Apsis {
+        body: usize,
+    },
+    // distance between the bodies stops decreasing and starts increasing
+    ClosestApproach {
+        body1: usize,
+        body2: usize,
+    },
+    // body crosses the line through `point` pointing in `direction`
+    LineCrossing {
+        body: usize,
+        point: Position,
+        direction: Position,
+    },
+    // simulation time reaches t
+    Time {
+        t: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EventKind {
+    Periapsis,
+    Apoapsis,
+    ClosestApproach,
+    // positive = crossed to the left side of the line direction
+    LineCrossing { positive: bool },
+    Time,
+    // from the parent in the hierarchy, or from the whole system
+    Escape { system: bool },
+    Capture { system: bool },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Event {
+    pub t: f32,
+    pub kind: EventKind,
+    // states of the involved bodies at the time of the event
+    pub bodies: Vec<Body>,
+}
+
+impl Event {
+    pub fn description(&self) -> String {
+        let names: Vec<String> = self.bodies.iter().map(|b| b.name.clone()).collect();
+        match self.kind {
+            EventKind::Periapsis => format!("Periapsis: {}", names.join(", ")),
+            EventKind::Apoapsis => format!("Apoapsis: {}", names.join(", ")),
+            EventKind::ClosestApproach => format!("Closest approach: {}", names.join(" - ")),
+            EventKind::LineCrossing { positive } => {
+                let direction = if positive { "+" } else { "-" };
+                format!("Line crossing ({}): {}", direction, names.join(", "))
+            }
+            EventKind::Time => "Time reached".to_string(),
+            EventKind::Escape { system: true } => format!("Escape from system: {}", names[0]),
+            EventKind::Capture { system: true } => format!("Capture by system: {}", names[0]),
+            // the second body is the parent
+            EventKind::Escape { system: false } => {
+                format!("Escape: {} from {}", names[0], names[1])
+            }
+            EventKind::Capture { system: false } => {
+                format!("Capture: {} by {}", names[0], names[1])
+            }
+        }
+    }
+
+    // the distance that is relevant to the event, e.g. the periapsis distance
+    pub fn distance(&self) -> Option<f32> {
+        match (self.kind, self.bodies.as_slice()) {
+            (EventKind::Periapsis | EventKind::Apoapsis, [b]) => Some(b.pos.mag()),
+            (EventKind::ClosestApproach, [b1, b2]) => {
+                Some(b1.absolute_pos.abs_diff(b2.absolute_pos))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl EventCondition {
+    fn body_indices(&self) -> Vec<usize> {
+        match *self {
+            EventCondition::Apsis { body } => vec![body],
+            EventCondition::ClosestApproach { body1, body2 } => vec![body1, body2],
+            EventCondition::LineCrossing { body, .. } => vec![body],
+            EventCondition::Time { .. } => vec![],
+        }
+    }
+
+    // the value that crosses zero when the event happens
+    fn value(&self, bodies: &[Body], t: f32) -> f32 {
+        match (self, bodies) {
+            (EventCondition::Apsis { .. }, [b]) => b.pos.dot(b.v),
+            (EventCondition::ClosestApproach { .. }, [b1, b2]) => {
+                let r = b1.absolute_pos.minus(b2.absolute_pos);
+                let v = b1.absolute_vel.minus(b2.absolute_vel);
+                r.dot(v)
+            }
+            (
+                EventCondition::LineCrossing {
+                    point, direction, ..
+                },
+                [b],
+            ) => direction.cross(b.absolute_pos.minus(*point)),
+            (EventCondition::Time { t: target }, _) => t - target,
+            _ => 0.,
+        }
+    }
+
+    // which kind of event a sign change corresponds to, if any
+    fn kind(&self, increasing: bool) -> Option<EventKind> {
+        match self {
+            EventCondition::Apsis { .. } if increasing => Some(EventKind::Periapsis),
+            EventCondition::Apsis { .. } => Some(EventKind::Apoapsis),
+            // decreasing is a maximum distance
+            EventCondition::ClosestApproach { .. } if increasing => {
+                Some(EventKind::ClosestApproach)
+            }
+            EventCondition::ClosestApproach { .. } => None,
+            EventCondition::LineCrossing { .. } => Some(EventKind::LineCrossing {
+                positive: increasing,
+            }),
+            EventCondition::Time { .. } if increasing => Some(EventKind::Time),
+            EventCondition::Time { .. } => None,
+        }
+    }
+}
+
+// state of the body at a fraction of the way through the last tick
+pub(super) fn body_at(body: &Body, dt: f32, fraction: f32) -> Option<Body> {
+    let len = body.trajectory.len();
+    if len < 2 {
+        return None;
+    }
+    let (prev, cur) = (&body.trajectory[len - 2], &body.trajectory[len - 1]);
+
+    let (pos, v) = hermite_interpolation(prev.pos, prev.v, cur.pos, cur.v, dt, fraction);
+    let (absolute_pos, absolute_vel) = hermite_interpolation(
+        prev.absolute_pos,
+        prev.absolute_vel,
+        cur.absolute_pos,
+        cur.absolute_vel,
+        dt,
+        fraction,
+    );
+
+    Some(Body {
+        pos,
+        v,
+        absolute_pos,
+        absolute_vel,
+        ..cur.copy()
+    })
+}
+
+fn bodies_at(
+    hierarchy: &DiGraph<Body, ()>,
+    indices: &[usize],
+    dt: f32,
+    fraction: f32,
+) -> Option<Vec<Body>> {
+    indices
+        .iter()
+        .map(|&i| {
+            let body = hierarchy.node_weight(NodeIndex::new(i))?;
+            body_at(body, dt, fraction)
+        })
+        .collect()
+}
+
+// Binary search for the fraction of the tick where the value crosses zero.
+// Same approach as ConstAcceleration::find_intercept.
+pub(super) fn find_crossing(value_at: impl Fn(f32) -> f32, start_value: f32) -> f32 {
+    let (mut start, mut end) = (0_f32, 1_f32);
+
+    for _ in 0..50 {
+        let mid = midpoint(start, end);
+        let mid_value = value_at(mid);
+
+        if mid_value == 0. || end - start < 1e-6 {
+            return mid;
+        }
+        if (mid_value < 0.) == (start_value < 0.) {
+            start = mid;
+        } else {
+            end = mid;
+        }
+    }
+    midpoint(start, end)
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Events {
+    pub conditions: Vec<EventCondition>,
+    pub log: Vec<Event>,
+    pub pause_on_event: bool,
+}
+
+impl Events {
+    pub fn add_condition(&mut self, condition: EventCondition) {
+        if !self.conditions.contains(&condition) {
+            self.conditions.push(condition);
+        }
+    }
+
+    pub fn remove_condition(&mut self, i: usize) {
+        if i < self.conditions.len() {
+            self.conditions.remove(i);
+        }
+    }
+
+    // Change every body index, for when a body is inserted or removed.
+    // Conditions on a body that was removed (remap returns None) are removed too, and returned as they were.
+    pub fn remap_indices(&mut self, remap: impl Fn(usize) -> Option<usize>) -> Vec<EventCondition> {
+        let mut dropped = vec![];
+        self.conditions.retain_mut(|condition| {
+            let original = *condition;
+            let kept = match condition {
+                EventCondition::Apsis { body } | EventCondition::LineCrossing { body, .. } => {
+                    remap(*body).map(|i| *body = i).is_some()
+                }
+                EventCondition::ClosestApproach { body1, body2 } => {
+                    match (remap(*body1), remap(*body2)) {
+                        (Some(i1), Some(i2)) => {
+                            (*body1, *body2) = (i1, i2);
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                EventCondition::Time { .. } => true,
+            };
+            if !kept {
+                dropped.push(original);
+            }
+            kept
+        });
+        dropped
+    }
+
+    // Check every condition over the last tick, which went from t_start to t_start + dt.
+    // Returns the number of events that happened.
+    pub fn detect(&mut self, hierarchy: &DiGraph<Body, ()>, t_start: f32, dt: f32) -> usize {
+        let mut new_events = vec![];
+
+        for condition in self.conditions.iter() {
+            let indices = condition.body_indices();
+            let value_at = |fraction: f32| {
+                bodies_at(hierarchy, &indices, dt, fraction)
+                    .map(|bodies| condition.value(&bodies, t_start + fraction * dt))
+            };
+
+            let (Some(start_value), Some(end_value)) = (value_at(0.), value_at(1.)) else {
+                continue;
+            };
+
+            let increasing = start_value < 0. && end_value >= 0.;
+            let decreasing = start_value > 0. && end_value <= 0.;
+            if !increasing && !decreasing {
+                continue;
+            }
+            let Some(kind) = condition.kind(increasing) else {
+                continue;
+            };
+
+            let fraction = find_crossing(|f| value_at(f).unwrap_or(0.), start_value);
+            let bodies = bodies_at(hierarchy, &indices, dt, fraction).unwrap_or_default();
+
+            new_events.push(Event {
+                t: t_start + fraction * dt,
+                kind,
+                bodies,
+            });
+        }
+
+        let count = new_events.len();
+        self.log.extend(new_events);
+        count
+    }
+}
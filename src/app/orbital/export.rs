@@ -0,0 +1,224 @@
+// Getting the simulation's data out for analysis elsewhere.
+// Every body records its state each tick, so the export is taken from the trajectories,
+// sampled at an interval of simulation time rather than every tick.
+// Two tables: the state of each body (one row per body per sample), and the energy metrics
+// from Analysis (one row per sample). They're written as CSV or JSON Lines (one JSON object per line).
+
+use serde::Serialize;
+
+use crate::helpers::files;
+
+use super::timeline::Timeline;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    // s of simulation time between samples, 0 for every tick
+    pub interval: f32,
+    pub include_barycenters: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Csv,
+            interval: 0.,
+            include_barycenters: false,
+        }
+    }
+}
+
+// One body at one tick. Positions in km, velocities in km/s, accelerations in km/s^2.
+// Relative values are to the body's parent in the hierarchy, and the acceleration is
+// the one used for the step, which is relative too.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BodySample {
+    pub t: f32,
+    pub tick: usize,
+    pub body: String,
+    pub parent: String,
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub rel_x: f32,
+    pub rel_y: f32,
+    pub rel_vx: f32,
+    pub rel_vy: f32,
+    pub ax: f32,
+    pub ay: f32,
+}
+
+// Energy at one tick, in MJ like the side panel
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MetricsSample {
+    pub t: f32,
+    pub tick: usize,
+    pub kinetic_e: f64,
+    pub gravitational_e: f64,
+    pub total_e: f64,
+    // change in total energy since the start, %
+    pub energy_error: f64,
+}
+
+// rows for a CSV file
+pub trait Record: Serialize {
+    const HEADER: &'static str;
+    fn csv_row(&self) -> String;
+}
+
+// names can have commas and quotes
+fn csv_text(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+impl Record for BodySample {
+    const HEADER: &'static str = "t,tick,body,parent,x,y,vx,vy,rel_x,rel_y,rel_vx,rel_vy,ax,ay";
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.t,
+            self.tick,
+            csv_text(&self.body),
+            csv_text(&self.parent),
+            self.x,
+            self.y,
+            self.vx,
+            self.vy,
+            self.rel_x,
+            self.rel_y,
+            self.rel_vx,
+            self.rel_vy,
+            self.ax,
+            self.ay
+        )
+    }
+}
+
+impl Record for MetricsSample {
+    const HEADER: &'static str = "t,tick,kinetic_e,gravitational_e,total_e,energy_error";
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.t,
+            self.tick,
+            self.kinetic_e,
+            self.gravitational_e,
+            self.total_e,
+            self.energy_error
+        )
+    }
+}
+
+pub fn to_csv<R: Record>(records: &[R]) -> String {
+    let mut csv = format!("{}\n", R::HEADER);
+    for record in records {
+        csv.push_str(&record.csv_row());
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn to_json_lines<R: Record>(records: &[R]) -> String {
+    records
+        .iter()
+        .map(|r| serde_json::to_string(r).expect("samples only have numbers and strings") + "\n")
+        .collect()
+}
+
+pub fn format<R: Record>(records: &[R], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Csv => to_csv(records),
+        ExportFormat::JsonLines => to_json_lines(records),
+    }
+}
+
+// Recorded ticks to export: the first one, then the first tick at least interval after the last sample.
+// Before the simulation starts nothing is recorded, so that's only the current state at tick 0.
+pub fn sampled_ticks(timeline: &Timeline, interval: f32) -> Vec<usize> {
+    if timeline.is_empty() {
+        return vec![0];
+    }
+    let mut ticks = vec![];
+    let mut next_t = f32::MIN;
+    for tick in timeline.first_tick()..=timeline.last_tick() {
+        let t = timeline.time_at(tick).unwrap_or_default();
+        if t >= next_t {
+            ticks.push(tick);
+            next_t = t + interval;
+        }
+    }
+    ticks
+}
+
+// the two tables, formatted
+pub struct Export {
+    pub bodies: String,
+    pub metrics: String,
+    pub format: ExportFormat,
+}
+
+impl Export {
+    // file names from a name like "export": export_bodies.csv and export_metrics.csv
+    pub fn file_names(&self, name: &str) -> (String, String) {
+        let extension = self.format.extension();
+        (
+            format!("{}_bodies.{}", name, extension),
+            format!("{}_metrics.{}", name, extension),
+        )
+    }
+
+    // files on native, downloads on the web
+    pub fn save(&self, name: &str) -> Result<(String, String), String> {
+        let (bodies_file, metrics_file) = self.file_names(name);
+        files::save_text(&bodies_file, &self.bodies)?;
+        files::save_text(&metrics_file, &self.metrics)?;
+        Ok((bodies_file, metrics_file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_records() {
+        let samples = [MetricsSample {
+            t: 1.5,
+            tick: 2,
+            kinetic_e: 10.,
+            gravitational_e: -20.,
+            total_e: -10.,
+            energy_error: 0.,
+        }];
+        assert_eq!(
+            to_csv(&samples),
+            "t,tick,kinetic_e,gravitational_e,total_e,energy_error\n1.5,2,10,-20,-10,0\n"
+        );
+        assert_eq!(
+            to_json_lines(&samples),
+            "{\"t\":1.5,\"tick\":2,\"kinetic_e\":10.0,\"gravitational_e\":-20.0,\"total_e\":-10.0,\"energy_error\":0.0}\n"
+        );
+        assert_eq!(csv_text("Sun, \"Sol\""), "\"Sun, \"\"Sol\"\"\"");
+    }
+}
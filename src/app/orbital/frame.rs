@@ -0,0 +1,187 @@
+// Reference frame that rotates with a pair of bodies.
+// The origin is the barycenter of the pair and the x axis always points from the first body
+// to the second, so for a circular orbit both bodies appear fixed on the x axis.
+
+use crate::app::core::physics::{jacobi_constant, Position, Velocity};
+
+use super::body::Body;
+
+// what the Jacobi constant and zero-velocity curve are shown for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JacobiSubject {
+    // index in the test particles
+    Particle(usize),
+    // node in the hierarchy
+    Body(usize),
+}
+
+impl Default for JacobiSubject {
+    fn default() -> Self {
+        JacobiSubject::Particle(0)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RotatingFrame {
+    origin: Position,
+    origin_vel: Velocity,
+    x_unit: Position,
+    angular_velocity: f32, // rad/s, counterclockwise is positive
+    masses: (f32, f32),
+    separation: f32,
+}
+
+impl RotatingFrame {
+    pub fn new(body1: &Body, body2: &Body) -> Self {
+        let mass_sum = body1.mass + body2.mass;
+        let origin = body1
+            .absolute_pos
+            .scale(body1.mass / mass_sum)
+            .add(body2.absolute_pos.scale(body2.mass / mass_sum));
+        let origin_vel = body1
+            .absolute_vel
+            .scale(body1.mass / mass_sum)
+            .add(body2.absolute_vel.scale(body2.mass / mass_sum));
+
+        let r = body2.absolute_pos.minus(body1.absolute_pos);
+        let v = body2.absolute_vel.minus(body1.absolute_vel);
+        let separation = r.mag();
+
+        Self {
+            origin,
+            origin_vel,
+            x_unit: r.divide(separation),
+            angular_velocity: r.cross(v) / separation.powi(2),
+            masses: (body1.mass, body2.mass),
+            separation,
+        }
+    }
+
+    // convert an absolute position to the position in this frame
+    pub fn position(&self, pos: Position) -> Position {
+        let offset = pos.minus(self.origin);
+        let y_unit = Position::new(-self.x_unit.y, self.x_unit.x);
+
+        Position::new(offset.dot(self.x_unit), offset.dot(y_unit))
+    }
+
+    // convert an absolute position and velocity to the velocity in this frame
+    pub fn velocity(&self, pos: Position, vel: Velocity) -> Velocity {
+        let offset = pos.minus(self.origin);
+        let rotation_vel = Velocity::new(-offset.y, offset.x).scale(self.angular_velocity);
+        let relative_vel = vel.minus(self.origin_vel).minus(rotation_vel);
+
+        let y_unit = Position::new(-self.x_unit.y, self.x_unit.x);
+        Velocity::new(relative_vel.dot(self.x_unit), relative_vel.dot(y_unit))
+    }
+
+    pub fn angular_velocity(&self) -> f32 {
+        self.angular_velocity
+    }
+
+    // positions of the two bodies in this frame, always on the x axis
+    pub fn body_positions(&self) -> (Position, Position) {
+        let mass_sum = self.masses.0 + self.masses.1;
+        (
+            Position::new(-self.separation * self.masses.1 / mass_sum, 0.),
+            Position::new(self.separation * self.masses.0 / mass_sum, 0.),
+        )
+    }
+
+    pub fn masses(&self) -> (f32, f32) {
+        self.masses
+    }
+
+    // of a particle at an absolute position and velocity
+    pub fn jacobi_constant(&self, pos: Position, vel: Velocity) -> f64 {
+        let (pos1, pos2) = self.body_positions();
+        jacobi_constant(
+            self.masses.0,
+            pos1,
+            self.masses.1,
+            pos2,
+            self.angular_velocity,
+            self.position(pos),
+            self.velocity(pos, vel),
+        )
+    }
+}
+
+// Indices of the points of a trajectory that are graphed, every step ticks and the current position.
+pub fn graphed_indices(len: usize, step: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..len).step_by(step.max(1)).collect();
+    // always include the current position
+    if len > 1 && indices.last() != Some(&(len - 1)) {
+        indices.push(len - 1);
+    }
+    indices
+}
+
+// graphed points of a trajectory without a rotating frame
+pub fn graphed_positions(trajectory: &[Body], step: usize) -> Vec<Position> {
+    graphed_indices(trajectory.len(), step)
+        .into_iter()
+        .map(|i| trajectory[i].absolute_pos)
+        .collect()
+}
+
+// The frame at each point of a trajectory.
+// Trajectories all end at the current tick, so they are aligned from the end.
+pub fn trajectory_in_frame(
+    trajectory: &[Body],
+    body1: &Body,
+    body2: &Body,
+    step: usize,
+) -> Vec<Position> {
+    let frame_at = |i: usize| {
+        let ticks_from_end = trajectory.len() - i;
+        let state_at = |b: &Body| {
+            let len = b.trajectory.len();
+            match len.checked_sub(ticks_from_end) {
+                Some(j) => b.trajectory[j].copy(),
+                None => b.trajectory.first().unwrap_or(b).copy(),
+            }
+        };
+        RotatingFrame::new(&state_at(body1), &state_at(body2))
+    };
+
+    graphed_indices(trajectory.len(), step)
+        .into_iter()
+        .map(|i| frame_at(i).position(trajectory[i].absolute_pos))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pair_is_fixed_on_x_axis() {
+        let body1 = Body {
+            mass: 3.,
+            absolute_pos: Position::new(1., 1.),
+            ..Body::default()
+        };
+        let body2 = Body {
+            mass: 1.,
+            absolute_pos: Position::new(1., 5.),
+            ..Body::default()
+        };
+        let frame = RotatingFrame::new(&body1, &body2);
+
+        let (pos1, pos2) = frame.body_positions();
+        let actual1 = frame.position(body1.absolute_pos);
+        let actual2 = frame.position(body2.absolute_pos);
+
+        assert!(actual1.abs_diff(pos1) < 1e-5);
+        assert!(actual2.abs_diff(pos2) < 1e-5);
+        assert!((pos2.x - 3.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn graphed_indices_end_at_current_position() {
+        assert_eq!(graphed_indices(10, 4), vec![0, 4, 8, 9]);
+        assert_eq!(graphed_indices(9, 4), vec![0, 4, 8]);
+        assert_eq!(graphed_indices(1, 4), vec![0]);
+    }
+}
@@ -0,0 +1,271 @@
+// Generating many test particles at once, e.g. planetary rings or asteroid belts,
+// or many bodies at once, e.g. star clusters.
+
+use std::f64::consts::TAU;
+
+use serde::Deserialize;
+
+use crate::app::core::{
+    physics::{circular_velocity, gravitational_parameter, Position, Velocity},
+    random::Rng,
+};
+
+use super::{body::Body, particles::TestParticles};
+
+#[derive(Clone, Debug)]
+pub struct RingSettings {
+    pub inner_radius: f32, // km
+    pub outer_radius: f32, // km
+    pub count: usize,
+    // Random velocity added to the circular velocity, as a fraction of it.
+    // Similar to giving each particle a small random eccentricity.
+    pub dispersion: f32,
+    pub seed: u64,
+    pub color: (u8, u8, u8),
+}
+
+impl Default for RingSettings {
+    fn default() -> Self {
+        Self {
+            inner_radius: 20000.,
+            outer_radius: 30000.,
+            count: 1000,
+            dispersion: 0.,
+            seed: 1,
+            color: (210, 190, 150),
+        }
+    }
+}
+
+// Particles spread evenly over the area of a ring around the parent,
+// each moving at the circular velocity around the parent plus the parent's own velocity.
+pub fn ring(parent: &Body, settings: &RingSettings) -> TestParticles {
+    let mut rng = Rng::new(settings.seed);
+    let mut particles = TestParticles::default();
+    let (inner, outer) = (settings.inner_radius, settings.outer_radius);
+
+    for _ in 0..settings.count {
+        // uniform in area, not in radius, so the inner edge isn't denser
+        let r = rng.range(inner.powi(2), outer.powi(2)).sqrt();
+        let angle = rng.range(0., std::f32::consts::TAU);
+        let pos = parent
+            .absolute_pos
+            .add(Position::new(r * angle.cos(), r * angle.sin()));
+
+        let circular = circular_velocity(parent.absolute_pos, parent.mass, pos);
+        let random =
+            Velocity::new(rng.normal(), rng.normal()).scale(settings.dispersion * circular.mag());
+        let vel = parent.absolute_vel.add(circular).add(random);
+
+        particles.add(pos, vel, settings.color);
+    }
+    particles
+}
+
+// Disk galaxy: a massive core with a rotationally supported disk of test particles.
+// Real galaxy masses and distances overflow f32 (e.g. mass * position for the barycenter),
+// but the dynamics are scale free, so scaled down masses and distances give the same shapes.
+#[derive(Clone, Debug)]
+pub struct GalaxySettings {
+    pub core_mass: f32,
+    pub core_radius: f32,
+    pub ring: RingSettings,
+}
+
+impl Default for GalaxySettings {
+    fn default() -> Self {
+        Self {
+            core_mass: 1e27,
+            core_radius: 2e6,
+            ring: RingSettings {
+                inner_radius: 1e7,
+                outer_radius: 5e7,
+                count: 3000,
+                ..RingSettings::default()
+            },
+        }
+    }
+}
+
+pub fn galaxy(
+    name: &str,
+    pos: Position,
+    vel: Velocity,
+    settings: &GalaxySettings,
+) -> (Body, TestParticles) {
+    let core = Body {
+        name: name.to_string(),
+        mass: settings.core_mass,
+        radius: settings.core_radius,
+        absolute_pos: pos,
+        absolute_vel: vel,
+        color: settings.ring.color,
+        ..Body::default()
+    };
+    let disk = ring(&core, &settings.ring);
+    (core, disk)
+}
+
+// Star cluster of equal mass stars.
+// Generated in Hénon units (G = M = 1, total energy = -1/4),
+// then converted to kg, km and s using the total mass and the length unit.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClusterSettings {
+    pub count: usize,
+    pub total_mass: f32,  // kg
+    pub length_unit: f32, // km, about the virial radius
+    pub softening: f32,   // Hénon length units
+    pub seed: u64,
+}
+
+impl Default for ClusterSettings {
+    fn default() -> Self {
+        Self {
+            count: 100,
+            total_mass: 1e27,
+            length_unit: 1e7,
+            softening: 0.02,
+            seed: 1,
+        }
+    }
+}
+
+impl ClusterSettings {
+    // sqrt(GM/R), km/s
+    pub fn velocity_unit(&self) -> f32 {
+        (gravitational_parameter(self.total_mass) / self.length_unit as f64).sqrt() as f32
+    }
+
+    // R/V, s. The crossing time is 2 * sqrt(2) time units.
+    pub fn time_unit(&self) -> f32 {
+        self.length_unit / self.velocity_unit()
+    }
+
+    pub fn softening_km(&self) -> f32 {
+        self.softening * self.length_unit
+    }
+}
+
+// Plummer model, sampled in 3D (Aarseth, Hénon & Wielen 1974) and projected onto the plane.
+// Projecting brings stars closer together and drops the z velocity, so afterwards the velocities
+// are scaled to virial equilibrium (2T + W = 0) and the whole cluster to total energy -1/4.
+pub fn plummer(settings: &ClusterSettings) -> Vec<Body> {
+    let mut rng = Rng::new(settings.seed);
+    let n = settings.count;
+
+    let mut positions: Vec<[f64; 2]> = Vec::with_capacity(n);
+    let mut velocities: Vec<[f64; 2]> = Vec::with_capacity(n);
+    for _ in 0..n {
+        // radius that encloses a random fraction of the mass,
+        // cut off at 99% so that a few stars don't start extremely far out
+        let enclosed = rng.range(0., 0.99) as f64;
+        let r = 1. / (enclosed.powf(-2. / 3.) - 1.).sqrt();
+
+        // speed as a fraction of the escape speed, distributed as q^2 (1 - q^2)^3.5
+        let q = loop {
+            let (x, y) = (rng.uniform() as f64, 0.1 * rng.uniform() as f64);
+            if y < x.powi(2) * (1. - x.powi(2)).powf(3.5) {
+                break x;
+            }
+        };
+        let v = q * 2_f64.sqrt() * (1. + r.powi(2)).powf(-0.25);
+
+        positions.push(projected_isotropic(&mut rng, r));
+        velocities.push(projected_isotropic(&mut rng, v));
+    }
+
+    // center of mass at rest at the origin
+    for coords in [&mut positions, &mut velocities] {
+        let mean = coords.iter().fold([0., 0.], |acc, c| {
+            [acc[0] + c[0] / n as f64, acc[1] + c[1] / n as f64]
+        });
+        for c in coords.iter_mut() {
+            *c = [c[0] - mean[0], c[1] - mean[1]];
+        }
+    }
+
+    if n >= 2 {
+        let m = 1. / n as f64;
+        let kinetic: f64 = velocities
+            .iter()
+            .map(|v| 0.5 * m * (v[0].powi(2) + v[1].powi(2)))
+            .sum();
+        let mut potential = 0.;
+        for (i, p1) in positions.iter().enumerate() {
+            for p2 in positions[i + 1..].iter() {
+                potential -= m * m / (p1[0] - p2[0]).hypot(p1[1] - p2[1]);
+            }
+        }
+
+        // T = -W/2 makes E = W/2, then scaling distances by s divides W by s, so s = -2W gives E = -1/4
+        let scale = -2. * potential;
+        let velocity_scale = (-potential / (2. * kinetic)).sqrt() / scale.sqrt();
+        for (p, v) in positions.iter_mut().zip(velocities.iter_mut()) {
+            *p = [p[0] * scale, p[1] * scale];
+            *v = [v[0] * velocity_scale, v[1] * velocity_scale];
+        }
+    }
+
+    let (length_unit, velocity_unit) = (settings.length_unit, settings.velocity_unit());
+    positions
+        .iter()
+        .zip(velocities.iter())
+        .enumerate()
+        .map(|(i, (p, v))| Body {
+            name: format!("Star {}", i + 1),
+            mass: settings.total_mass / n as f32,
+            radius: 0.,
+            absolute_pos: Position::new(p[0] as f32, p[1] as f32).scale(length_unit),
+            absolute_vel: Velocity::new(v[0] as f32, v[1] as f32).scale(velocity_unit),
+            color: (255, 230, 180),
+            ..Body::default()
+        })
+        .collect()
+}
+
+// x and y components of a vector pointing in a random direction in 3D
+fn projected_isotropic(rng: &mut Rng, magnitude: f64) -> [f64; 2] {
+    let cos_theta = rng.range(-1., 1.) as f64;
+    let sin_theta = (1. - cos_theta.powi(2)).sqrt();
+    let phi = TAU * rng.uniform() as f64;
+    [
+        magnitude * sin_theta * phi.cos(),
+        magnitude * sin_theta * phi.sin(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_within_radii_with_circular_velocity() {
+        let parent = Body {
+            absolute_pos: Position::new(1000., -500.),
+            absolute_vel: Velocity::new(1., 2.),
+            ..Body::earth()
+        };
+        let settings = RingSettings {
+            count: 500,
+            ..RingSettings::default()
+        };
+
+        let particles = ring(&parent, &settings);
+
+        assert_eq!(particles.len(), 500);
+        for i in 0..particles.len() {
+            let r = particles.position(i).abs_diff(parent.absolute_pos);
+            assert!(r >= settings.inner_radius - 0.1 && r <= settings.outer_radius + 0.1);
+
+            let relative_vel = particles.velocity(i).minus(parent.absolute_vel);
+            let expected =
+                circular_velocity(parent.absolute_pos, parent.mass, particles.position(i));
+            assert!(relative_vel.abs_diff(expected) < 1e-4);
+        }
+
+        // same seed gives the same ring
+        let again = ring(&parent, &settings);
+        assert_eq!(particles.x, again.x);
+    }
+}
@@ -0,0 +1,217 @@
+// The windowed app: the simulation plus what's only needed to show it,
+// the UI's forms and the view's selections.
+
+use std::cell::Cell;
+
+use femtovg::{renderer::WGPURenderer, Canvas};
+
+use crate::app::{
+    core::{draw::DrawTarget, physics::Position},
+    App, Simulation,
+};
+
+use super::{
+    background::{BackgroundRun, ForegroundRun, Update},
+    frame::JacobiSubject,
+    generators::ClusterSettings,
+    realtime::FRAME_BUDGET,
+    timeline::Playback,
+    ui,
+    view::View,
+    Orbital,
+};
+
+pub struct OrbitalApp {
+    pub sim: Orbital,
+    pub(super) ui_state: UiState,
+    // run of num_ticks that's computed away from the window, its snapshots are applied to sim
+    pub(super) background: Option<BackgroundRun>,
+    // run of num_ticks in the window, a frame's budget at a time, when it can't run in the background
+    pub(super) foreground: Option<ForegroundRun>,
+}
+
+impl Simulation for OrbitalApp {
+    fn run(&mut self) {
+        if self.is_running() || !self.sim.started {
+            return;
+        }
+        self.sim.realtime.pause();
+        let ticks = self.sim.num_ticks.max(0) as usize;
+        let reason = match BackgroundRun::unsupported(&self.sim) {
+            Some(reason) => format!("{} can't run in the background", reason),
+            None => match BackgroundRun::start(&mut self.sim, ticks) {
+                Ok(run) => {
+                    self.background = Some(run);
+                    return;
+                }
+                Err(e) => format!("no background run: {}", e),
+            },
+        };
+        log::info!("running in the window, {}", reason);
+        self.foreground = Some(ForegroundRun::new(ticks, reason));
+    }
+}
+
+impl App for OrbitalApp {
+    fn draw(&self, canvas: &mut Canvas<WGPURenderer>) {
+        let view = View {
+            covered_left: self.ui_state.panel_width,
+            ..self.ui_state.view.clone()
+        };
+        self.sim.draw(canvas, &view);
+
+        // the part of the window that's not under the panel, for images of the current view
+        let (mut top_left, bottom_right) = canvas.visible_area();
+        top_left.x += self.ui_state.panel_width / DrawTarget::scale(canvas);
+        self.ui_state.visible_area.set((top_left, bottom_right));
+    }
+
+    fn ui(&mut self, ctx: &egui::Context) {
+        ui::ui(self, ctx);
+        ui::controls_panel(self, ctx);
+        ui::events_window(self, ctx);
+        ui::poincare_window(self, ctx);
+        if !self.sim.started {
+            self.sim.prepare();
+        }
+        self.sim.analyze();
+        self.apply_index_changes();
+    }
+
+    fn panel_width(&self) -> f32 {
+        self.ui_state.panel_width
+    }
+
+    // a background or foreground run, real-time playback, or timeline playback
+    fn animate(&mut self, elapsed: f32) -> bool {
+        if let Some(run) = &mut self.background {
+            match run.poll() {
+                Some(Update::Snapshot(snapshot)) => self.sim.continue_from(snapshot),
+                Some(Update::Finished(snapshot)) => {
+                    self.sim.continue_from(snapshot);
+                    self.background = None;
+                }
+                Some(Update::Failed(e)) => {
+                    log::error!("background run failed: {}", e);
+                    self.background = None;
+                }
+                None => {}
+            }
+            self.apply_index_changes();
+            // one more frame to draw the finished run
+            return true;
+        }
+        if let Some(run) = &mut self.foreground {
+            if run.run_for(&mut self.sim, FRAME_BUDGET) {
+                self.foreground = None;
+            }
+            self.apply_index_changes();
+            return true;
+        }
+        if self.sim.realtime.playing {
+            if self.sim.play_for(elapsed) > 0 {
+                self.apply_index_changes();
+            }
+            return self.sim.realtime.playing;
+        }
+        match self.sim.timeline.next_tick() {
+            Some(tick) => {
+                self.sim.seek(tick);
+                true
+            }
+            None => {
+                self.sim.timeline.playback = Playback::Paused;
+                false
+            }
+        }
+    }
+
+    fn focused_pos(&self) -> Option<(f32, f32)> {
+        let sim = &self.sim;
+        let focused_pos = sim
+            .hierarchy
+            .node_weight(sim.focused?)
+            .expect("invalid index")
+            .absolute_pos;
+        Some(
+            sim.display_pos(focused_pos, &self.ui_state.view)
+                .divide(sim.distance_per_px)
+                .to_tuple(),
+        )
+    }
+}
+
+impl Default for OrbitalApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrbitalApp {
+    pub fn new() -> Self {
+        Self {
+            sim: Orbital::new(),
+            ui_state: UiState::new(),
+            background: None,
+            foreground: None,
+        }
+    }
+
+    // a run of num_ticks is in progress, in the background or in the window
+    pub(super) fn is_running(&self) -> bool {
+        self.background.is_some() || self.foreground.is_some()
+    }
+
+    // bodies selected in the UI follow the bodies when others are added or removed
+    fn apply_index_changes(&mut self) {
+        let ui_state = &mut self.ui_state;
+        for change in self.sim.take_index_changes() {
+            let jacobi_body = match &mut ui_state.view.frame.jacobi_of {
+                JacobiSubject::Body(i) => Some(i),
+                JacobiSubject::Particle(_) => None,
+            };
+            for i in [
+                &mut ui_state.view.lagrange.body1,
+                &mut ui_state.view.lagrange.body2,
+                &mut ui_state.view.frame.body1,
+                &mut ui_state.view.frame.body2,
+                &mut ui_state.ring.parent,
+            ]
+            .into_iter()
+            .chain(jacobi_body)
+            {
+                *i = change.apply(*i).unwrap_or_default();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(super) struct UiState {
+    pub panel_width: f32,
+    pub event_form: ui::EventForm,
+    pub view: View,
+    // canvas units (top left, bottom right) shown when last drawn
+    pub visible_area: Cell<(Position, Position)>,
+    pub ring: ui::RingForm,
+    pub galaxy: ui::GalaxyForm,
+    pub branch: ui::BranchForm,
+    pub checkpoint: ui::CheckpointForm,
+    pub export: ui::ExportForm,
+    pub poincare: ui::PoincareForm,
+    #[cfg(feature = "raster")]
+    pub image: ui::ImageForm,
+    pub horizons: ui::ImportForm,
+    pub tle: ui::ImportForm,
+    pub cluster: ClusterSettings,
+}
+impl UiState {
+    fn new() -> Self {
+        Self {
+            panel_width: 300.,
+            horizons: ui::ImportForm::new("horizons.txt"),
+            tle: ui::ImportForm::new("satellites.tle"),
+            ..Default::default()
+        }
+    }
+}
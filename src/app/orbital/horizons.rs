@@ -0,0 +1,395 @@
+// Importing real starting states from JPL Horizons vector tables
+// (Ephemeris Type: Vector Table, saved as text). Several exports can be pasted into one file,
+// one per body, all with the same center and start time. The first state in each table is used.
+//
+// Our plane is the ecliptic, so the tables should use the ecliptic reference plane,
+// X and Y are kept and Z is dropped. Units can be km/s, km/day or AU/day.
+// Horizons doesn't give masses, they come from the table below by the body's ID,
+// and the radius comes from the table's header when it has one.
+// The center body (usually the Sun) is added at the origin unless it's also one of the targets.
+
+use std::fmt;
+
+use crate::app::core::physics::{Position, Velocity, R_EARTH_KM};
+
+use super::body::Body;
+
+const AU_KM: f64 = 149_597_870.7;
+const DAY_S: f64 = 86_400.;
+
+type Color = (u8, u8, u8);
+// position (km) and velocity (km/s) in the plane
+type Vectors = ([f64; 2], [f64; 2]);
+
+// NAIF ID, mass (kg), mean radius (km), color
+// barycenters (1 to 9) have the mass of the whole system and the radius of the planet
+const BODIES: [(i32, f32, f32, Color); 37] = [
+    (10, 1.989e30, 6.963e5, (255, 255, 0)),
+    (1, 3.301e23, 2439.7, (170, 160, 150)),
+    (2, 4.867e24, 6051.8, (230, 200, 140)),
+    (3, 6.0434e24, R_EARTH_KM, (80, 140, 255)),
+    (4, 6.4171e23, 3389.5, (220, 90, 50)),
+    (5, 1.8986e27, 69911., (220, 180, 130)),
+    (6, 5.6846e26, 58232., (230, 210, 150)),
+    (7, 8.6829e25, 25362., (160, 220, 230)),
+    (8, 1.0243e26, 24622., (90, 120, 240)),
+    (9, 1.4616e22, 1188.3, (200, 180, 160)),
+    (199, 3.301e23, 2439.7, (170, 160, 150)),
+    (299, 4.867e24, 6051.8, (230, 200, 140)),
+    (399, 5.97e24, R_EARTH_KM, (80, 140, 255)),
+    (301, 7.34e22, 1740., (160, 160, 160)),
+    (499, 6.4171e23, 3389.5, (220, 90, 50)),
+    (401, 1.0659e16, 11.1, (150, 130, 120)),
+    (402, 1.4762e15, 6.2, (150, 130, 120)),
+    (599, 1.8982e27, 69911., (220, 180, 130)),
+    (501, 8.932e22, 1821.6, (240, 220, 100)),
+    (502, 4.800e22, 1560.8, (210, 190, 170)),
+    (503, 1.482e23, 2634.1, (170, 160, 150)),
+    (504, 1.076e23, 2410.3, (120, 110, 100)),
+    (699, 5.683e26, 58232., (230, 210, 150)),
+    (601, 3.75e19, 198.2, (200, 200, 200)),
+    (602, 1.08e20, 252.1, (240, 240, 255)),
+    (603, 6.17e20, 531.1, (210, 210, 210)),
+    (604, 1.095e21, 561.4, (200, 200, 200)),
+    (605, 2.307e21, 763.8, (190, 190, 190)),
+    (606, 1.345e23, 2574.7, (230, 180, 90)),
+    (608, 1.806e21, 734.5, (180, 170, 160)),
+    (799, 8.681e25, 25362., (160, 220, 230)),
+    (899, 1.024e26, 24622., (90, 120, 240)),
+    (801, 2.14e22, 1353.4, (210, 200, 200)),
+    (999, 1.303e22, 1188.3, (200, 180, 160)),
+    (901, 1.586e21, 606., (170, 170, 170)),
+    // dwarf planets in the main belt
+    (2000001, 9.38e20, 469.7, (160, 160, 150)),
+    (2000004, 2.59e20, 262.7, (180, 180, 170)),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum HorizonsError {
+    // line number in the file
+    Format(usize, String),
+    NoStates,
+    UnknownBody(String),
+    Mismatch(String),
+}
+
+impl fmt::Display for HorizonsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HorizonsError::Format(line, e) => write!(f, "line {}: {}", line, e),
+            HorizonsError::NoStates => write!(f, "no vector table found between $$SOE and $$EOE"),
+            HorizonsError::UnknownBody(name) => {
+                write!(
+                    f,
+                    "no mass known for {}, it can be added after importing",
+                    name
+                )
+            }
+            HorizonsError::Mismatch(e) => write!(f, "tables don't match: {}", e),
+        }
+    }
+}
+
+// a body from the header of a table, e.g. "Earth (399)"
+#[derive(Clone, Debug, PartialEq)]
+struct Named {
+    name: String,
+    id: Option<i32>,
+}
+
+// the first state in one table, in km and km/s
+#[derive(Clone, Debug)]
+struct State {
+    target: Named,
+    center: Named,
+    radius: Option<f32>,
+    jd: f64,
+    pos: [f64; 2],
+    vel: [f64; 2],
+}
+
+#[derive(Clone, Copy)]
+enum Units {
+    KmS,
+    KmD,
+    AuD,
+}
+
+impl Units {
+    // factors to km and km/s
+    fn scale(&self) -> (f64, f64) {
+        match self {
+            Units::KmS => (1., 1.),
+            Units::KmD => (1., 1. / DAY_S),
+            Units::AuD => (AU_KM, AU_KM / DAY_S),
+        }
+    }
+}
+
+// header fields of the table being read
+#[derive(Default)]
+struct Header {
+    target: Option<Named>,
+    center: Option<Named>,
+    radius: Option<f32>,
+    units: Option<Units>,
+    // column names of a CSV table, the line above $$SOE
+    columns: Vec<String>,
+}
+
+// "Earth (399)" or "Jupiter Barycenter (5)"
+fn parse_name(value: &str) -> Named {
+    // anything after the name, like {source: DE441}
+    let value = value.split('{').next().unwrap_or_default().trim();
+    match value.rfind('(') {
+        Some(open) => {
+            let id = value[open + 1..].trim_end_matches(')').trim().parse().ok();
+            Named {
+                name: value[..open].trim().to_string(),
+                id,
+            }
+        }
+        None => Named {
+            name: value.to_string(),
+            id: None,
+        },
+    }
+}
+
+// " X =-2.48E+07 Y = 1.44E+08 Z = 2.1E+04" into (X, -2.48e7), (Y, 1.44e8), (Z, 2.1e4)
+fn key_values(line: &str) -> Vec<(&str, f64)> {
+    let parts: Vec<&str> = line.split('=').collect();
+    parts
+        .windows(2)
+        .filter_map(|pair| {
+            let key = pair[0].split_whitespace().last()?;
+            let value = pair[1].split_whitespace().next()?.parse().ok()?;
+            Some((key, value))
+        })
+        .collect()
+}
+
+fn header_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let (name, value) = line.split_once(':')?;
+    (name.trim() == key).then_some(value.trim())
+}
+
+pub fn parse(source: &str) -> Result<Vec<Body>, HorizonsError> {
+    let states = read_states(source)?;
+    bodies_from_states(&states)
+}
+
+fn read_states(source: &str) -> Result<Vec<State>, HorizonsError> {
+    let mut states = vec![];
+    let mut header = Header::default();
+    let mut previous_line = "";
+    let mut lines = source.lines().enumerate();
+
+    while let Some((i, line)) = lines.next() {
+        let format_error = |e: &str| HorizonsError::Format(i + 1, e.to_string());
+
+        if let Some(value) = header_value(line, "Target body name") {
+            header = Header {
+                target: Some(parse_name(value)),
+                ..Header::default()
+            };
+        } else if let Some(value) = header_value(line, "Center body name") {
+            header.center = Some(parse_name(value));
+        } else if let Some(value) = header_value(line, "Target radii") {
+            header.radius = value.split([',', ' ']).find_map(|r| r.parse::<f32>().ok());
+        } else if let Some(value) = header_value(line, "Output units") {
+            header.units = Some(match value.split_whitespace().next() {
+                Some("KM-S") => Units::KmS,
+                Some("KM-D") => Units::KmD,
+                Some("AU-D") => Units::AuD,
+                _ => return Err(format_error(&format!("unsupported units {}", value))),
+            });
+        } else if let Some(value) =
+            header_value(line, "Reference frame").or_else(|| header_value(line, "Reference plane"))
+        {
+            if !value.to_lowercase().contains("ecliptic") {
+                return Err(format_error(&format!(
+                    "reference plane is {}, export with the ecliptic plane",
+                    value
+                )));
+            }
+        } else if line.trim() == "$$SOE" {
+            let target = header
+                .target
+                .clone()
+                .ok_or_else(|| format_error("table without a Target body name"))?;
+            let center = header
+                .center
+                .clone()
+                .ok_or_else(|| format_error("table without a Center body name"))?;
+            let units = header.units.unwrap_or(Units::KmS);
+            if previous_line.contains(',') {
+                header.columns = previous_line
+                    .split(',')
+                    .map(|c| c.trim().to_string())
+                    .collect();
+            }
+            let (jd, (pos, vel)) = read_first_state(&mut lines, &header.columns)?;
+            let (to_km, to_km_s) = units.scale();
+            states.push(State {
+                target,
+                center,
+                radius: header.radius,
+                jd,
+                pos: pos.map(|p| p * to_km),
+                vel: vel.map(|v| v * to_km_s),
+            });
+        }
+        if !line.trim_start().starts_with('*') && !line.trim().is_empty() {
+            previous_line = line;
+        }
+    }
+
+    if states.is_empty() {
+        return Err(HorizonsError::NoStates);
+    }
+    Ok(states)
+}
+
+// the julian date, position and velocity of the first state after $$SOE
+fn read_first_state<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    columns: &[String],
+) -> Result<(f64, Vectors), HorizonsError> {
+    let mut jd = None;
+    let mut values: Vec<(String, f64)> = vec![];
+    let mut last_line = 0;
+
+    for (i, line) in lines.by_ref() {
+        last_line = i + 1;
+        if line.trim() == "$$EOE" {
+            break;
+        }
+        let found = |key: &str, values: &[(String, f64)]| values.iter().any(|(k, _)| k == key);
+        if ["X", "Y", "VX", "VY"].iter().all(|k| found(k, &values)) {
+            // skip the rest of the table
+            continue;
+        }
+        if !columns.is_empty() {
+            // CSV: one line per state, named by the column line
+            let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+            jd = fields.first().and_then(|f| f.parse().ok());
+            values = columns
+                .iter()
+                .zip(fields)
+                .filter_map(|(c, f)| Some((c.clone(), f.parse().ok()?)))
+                .collect();
+        } else if jd.is_none() {
+            // "2460310.500000000 = A.D. 2024-Jan-01 00:00:00.0000 TDB"
+            jd = line.split_whitespace().next().and_then(|f| f.parse().ok());
+        } else {
+            values.extend(
+                key_values(line)
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v)),
+            );
+        }
+    }
+
+    let get = |key: &str| {
+        values
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| HorizonsError::Format(last_line, format!("no {} in the table", key)))
+    };
+    let jd = jd.ok_or_else(|| HorizonsError::Format(last_line, "no date in the table".into()))?;
+    Ok((jd, ([get("X")?, get("Y")?], [get("VX")?, get("VY")?])))
+}
+
+fn known_body(named: &Named) -> Result<(f32, f32, Color), HorizonsError> {
+    let id = named.id;
+    BODIES
+        .iter()
+        .find(|(body_id, ..)| Some(*body_id) == id)
+        .map(|&(_, mass, radius, color)| (mass, radius, color))
+        .ok_or_else(|| match id {
+            Some(id) => HorizonsError::UnknownBody(format!("{} ({})", named.name, id)),
+            None => HorizonsError::UnknownBody(named.name.clone()),
+        })
+}
+
+fn bodies_from_states(states: &[State]) -> Result<Vec<Body>, HorizonsError> {
+    let first = &states[0];
+    for state in states {
+        if state.center != first.center {
+            return Err(HorizonsError::Mismatch(format!(
+                "{} is relative to {}, {} is relative to {}",
+                first.target.name, first.center.name, state.target.name, state.center.name
+            )));
+        }
+        // within a second
+        if (state.jd - first.jd).abs() * DAY_S > 1. {
+            return Err(HorizonsError::Mismatch(format!(
+                "{} starts at JD {}, {} starts at JD {}",
+                first.target.name, first.jd, state.target.name, state.jd
+            )));
+        }
+    }
+
+    let mut bodies = vec![];
+    let center_is_target = states.iter().any(|s| s.target == first.center);
+    if !center_is_target {
+        let (mass, radius, color) = known_body(&first.center)?;
+        bodies.push(Body {
+            name: first.center.name.clone(),
+            mass,
+            radius,
+            color,
+            ..Body::default()
+        });
+    }
+    for state in states {
+        if bodies.iter().any(|b: &Body| b.name == state.target.name) {
+            return Err(HorizonsError::Mismatch(format!(
+                "{} is in more than one table",
+                state.target.name
+            )));
+        }
+        let (mass, radius, color) = known_body(&state.target)?;
+        bodies.push(Body {
+            name: state.target.name.clone(),
+            mass,
+            radius: state.radius.unwrap_or(radius),
+            absolute_pos: Position::new(state.pos[0] as f32, state.pos[1] as f32),
+            absolute_vel: Velocity::new(state.vel[0] as f32, state.vel[1] as f32),
+            color,
+            ..Body::default()
+        });
+    }
+    Ok(bodies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_text_and_csv_tables() {
+        let text = "Target body name: Io (501)\nCenter body name: Jupiter (599)\nOutput units    : KM-D\n$$SOE\n2460310.500000000 = A.D. 2024-Jan-01 00:00:00.0000 TDB \n X = 4.2E+05 Y =-1.0E+03 Z = 1.0E+01\n VX= 8.64E+05 VY= 0.0E+00 VZ= 0.0E+00\n$$EOE\n";
+        let states = read_states(text).unwrap();
+        assert_eq!(states[0].pos, [4.2e5, -1e3]);
+        assert_eq!(states[0].vel, [10., 0.]);
+        assert_eq!(states[0].center.id, Some(599));
+
+        let csv = "Target body name: Io (501)\nCenter body name: Jupiter (599)\nOutput units    : KM-S\n****\n            JDTDB,            Calendar Date (TDB),                      X,                      Y,                      Z,                     VX,                     VY,                     VZ,\n****\n$$SOE\n2460310.500000000, A.D. 2024-Jan-01 00:00:00.0000,  4.2E+05, -1.0E+03,  1.0E+01,  1.0E+01,  2.0E+00,  0.0E+00,\n$$EOE\n";
+        let states = read_states(csv).unwrap();
+        assert_eq!(states[0].jd, 2460310.5);
+        assert_eq!(states[0].pos, [4.2e5, -1e3]);
+        assert_eq!(states[0].vel, [10., 2.]);
+    }
+
+    #[test]
+    fn rejects_equatorial_tables() {
+        let text = "Target body name: Io (501)\nReference frame : ICRF\n$$SOE\n$$EOE\n";
+        assert!(matches!(
+            read_states(text),
+            Err(HorizonsError::Format(2, _))
+        ));
+    }
+}
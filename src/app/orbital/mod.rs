@@ -1,126 +1,125 @@
+pub mod background;
+pub mod binding;
 pub mod body;
+pub mod branch;
+pub mod chaos;
+pub mod checkpoint;
+pub mod cluster;
+pub mod events;
+pub mod export;
+pub mod frame;
+pub mod generators;
+#[cfg(feature = "gui")]
+mod gui;
+pub mod horizons;
+pub mod particles;
+pub mod poincare;
+pub mod realtime;
+pub mod resonance;
+pub mod scenario;
+#[cfg(feature = "raster")]
+pub mod snapshot;
+pub mod timeline;
+pub mod tle;
 mod tree;
+#[cfg(feature = "gui")]
 mod ui;
+pub mod view;
 
+#[cfg(feature = "gui")]
+pub use gui::OrbitalApp;
+
+use binding::Escapes;
 use body::{Body, Preset};
+use branch::{divergence, Branch, Divergence};
+use chaos::{Chaos, ChaosIndicators};
+use checkpoint::Checkpoint;
+use cluster::{ClusterStats, Star};
+use events::{Event, EventCondition, EventKind, Events};
+use export::{BodySample, Export, ExportSettings, MetricsSample};
+use frame::{JacobiSubject, RotatingFrame};
+use generators::{ring, ClusterSettings, RingSettings};
+use horizons::HorizonsError;
 use log::{debug, log_enabled, Level};
+use particles::{Source, TestParticles};
 use petgraph::graph::{DiGraph, NodeIndex};
-use std::{collections::HashMap, f32};
-use tree::build_hierarchy;
-
-use crate::app::core::graph::parent_node_or_default;
+use poincare::PoincareSection;
+use realtime::RealTime;
+use resonance::Resonances;
+use scenario::ScenarioError;
+use serde::{Deserialize, Serialize};
+use std::f32;
+use timeline::{Playback, Timeline};
+use tle::TleError;
+use tree::{build_flat_hierarchy, build_hierarchy};
+
+use crate::app::core::{graph::parent_node_or_default, parallel};
 
 use super::{
-    core::{
-        draw::{draw_body, draw_line_thru_points, draw_text, draw_tick_marks, get_scale},
-        physics::{
-            circ_velocity_barycenter, escape_velocity_barycenter, gravitational_acceleration,
-            gravitational_potential_energy, kinetic_energy, symplectic_euler_calc, Acceleration,
-            Position, Velocity,
-        },
+    core::physics::{
+        circ_velocity_barycenter, co_orbital_velocity, escape_velocity_barycenter, kinetic_energy,
+        lagrange_points, softened_gravitational_acceleration, softened_potential_energy,
+        symplectic_euler_calc, verlet_position, verlet_velocity, Acceleration, Integrator,
+        Position, Velocity,
     },
-    App,
+    Simulation,
 };
 
+// fewest sibling groups, and bodies in a group, worth handing to another thread
+const GROUPS_PER_THREAD: usize = 8;
+const BODIES_PER_THREAD: usize = 16;
+
+#[derive(Clone)]
 pub struct Orbital {
-    ui_state: UiState,
     pub dt: f32,
     pub t: f32,
     pub num_ticks: i32,
+    pub integrator: Integrator,
     distance_per_px: f32,
     draw_frequency: u32, // graph a point every X seconds
     started: bool,
     stopped: bool,
     bodies: Vec<Body>,
     presets: Vec<Preset>,
+    // scenario files that couldn't be loaded
+    pub scenario_errors: Vec<ScenarioError>,
     pub analysis: Analysis,
+    pub events: Events,
+    pub resonances: Resonances,
+    pub poincare: PoincareSection,
+    pub chaos: Chaos,
+    pub particles: TestParticles,
+    pub cluster: ClusterStats,
+    pub escapes: Escapes,
+    pub timeline: Timeline,
+    pub realtime: RealTime,
+    // forked copies that run alongside this one
+    pub branches: Vec<Branch>,
+    // bodies removed while running and their index at the time, so reset can put them back
+    removed: Vec<(usize, Body)>,
+    // event and Poincaré conditions dropped with each of the removed bodies, restored with them
+    removed_conditions: Vec<DroppedConditions>,
+    // Plummer softening length in the force law, km
+    pub softening: f32,
+    // forces on many bodies or particles calculated on several threads, if the build can (see core::parallel)
+    pub parallel: bool,
+    // all bodies as siblings instead of grouping them by distance and mass, for star clusters
+    flat_hierarchy: bool,
     hierarchy: DiGraph<Body, ()>,
     root: NodeIndex,
     focused: Option<NodeIndex>,
+    // not yet taken by take_index_changes
+    index_changes: Vec<IndexChange>,
 }
 
-impl App for Orbital {
+impl Simulation for Orbital {
     fn run(&mut self) {
-        if !self.started || self.stopped {
-            return;
-        }
-        for _ in 0..self.num_ticks {
-            self.run_euler();
-        }
-        self.t += self.dt * self.num_ticks as f32;
-        self.analyze();
-
-        if log_enabled!(Level::Debug) {
-            debug!("");
-            for b in self.bodies_vec().iter() {
-                debug!("{}: abs: {:?}, rel: {:?}", b.name, b.absolute_pos, b.pos);
-            }
-        }
-    }
-
-    fn draw(&self, canvas: &mut femtovg::Canvas<femtovg::renderer::WGPURenderer>) {
-        let (x_distance_range, y_distance_range) = self.distance_range(canvas);
-        draw_tick_marks(
-            canvas,
-            x_distance_range,
-            y_distance_range,
-            self.distance_per_px,
-        );
-
-        let ticks_per_graph_point = (self.draw_frequency as f32 / self.dt).ceil() as usize;
-
-        for b in self.bodies_vec().iter() {
-            draw_body(canvas, b, self.distance_per_px);
-
-            draw_line_thru_points(
-                canvas,
-                &b.trajectory,
-                ticks_per_graph_point,
-                self.distance_per_px,
-                b.color,
-            );
-
-            if !b.is_barycenter {
-                draw_text(
-                    canvas,
-                    b.name.clone(),
-                    &b.absolute_pos,
-                    self.distance_per_px,
-                );
-            }
-        }
-    }
-
-    fn ui(&mut self, ctx: &egui::Context) {
-        ui::ui(self, ctx);
-        ui::controls_panel(self, ctx);
-        if !self.started {
-            // need to set velocities before so that barycenter grouping knows the bodies' velocities
-            // need to set velocities after to apply the circular velocities set by the grouping
-            // TODO refactor? calculate circular velocity while grouping?
-            self.set_velocities();
-            self.refresh_hierarchy();
-            self.set_velocities();
-        }
-        self.analyze();
-    }
-
-    fn panel_width(&self) -> f32 {
-        self.ui_state.panel_width
-    }
-
-    fn focused_pos(&self) -> Option<(f32, f32)> {
-        if let Some(focused_idx) = self.focused {
-            Some(
-                self.hierarchy
-                    .node_weight(focused_idx)
-                    .expect("invalid index")
-                    .absolute_pos
-                    .divide(self.distance_per_px)
-                    .to_tuple(),
-            )
-        } else {
-            None
+        self.run_ticks();
+        // same ticks in every branch so they stay in step
+        for branch in self.branches.iter_mut() {
+            branch.sim.dt = self.dt;
+            branch.sim.num_ticks = self.num_ticks;
+            branch.sim.run();
         }
     }
 }
@@ -128,66 +127,119 @@ impl App for Orbital {
 impl Orbital {
     pub fn new() -> Self {
         let mut app = Self {
-            ui_state: UiState::new(),
             dt: 1.,
             t: 0.,
             num_ticks: 1000,
+            integrator: Integrator::default(),
             distance_per_px: 150.,
             draw_frequency: 100,
             started: false,
             stopped: false,
             bodies: vec![Body::earth()],
-            presets: Preset::defaults(),
+            presets: vec![],
+            scenario_errors: vec![],
             analysis: Analysis::default(),
+            events: Events::default(),
+            resonances: Resonances::default(),
+            poincare: PoincareSection::default(),
+            chaos: Chaos::default(),
+            particles: TestParticles::default(),
+            cluster: ClusterStats::default(),
+            escapes: Escapes::default(),
+            timeline: Timeline::default(),
+            realtime: RealTime::default(),
+            branches: vec![],
+            removed: vec![],
+            removed_conditions: vec![],
+            softening: 0.,
+            parallel: true,
+            flat_hierarchy: false,
             hierarchy: DiGraph::new(),
             root: NodeIndex::new(0),
             focused: None,
+            index_changes: vec![],
         };
+        app.reload_scenarios();
         app.load_preset(0);
         app
     }
 
-    fn distance_range(
-        &self,
-        canvas: &mut femtovg::Canvas<femtovg::renderer::WGPURenderer>,
-    ) -> ((f32, f32), (f32, f32)) {
-        let (width, height) = (canvas.width(), canvas.height());
-        let transform = canvas.transform().0;
-        let (offset_x, offset_y) = (transform[4], transform[5]);
-
-        let scale = get_scale(canvas);
-        let min_x_px = -(offset_x - self.ui_state.panel_width) / scale; // account for the side panel taking away some space
-        let max_x_px = (width as f32 - offset_x) / scale;
-        let max_y_px = offset_y / scale;
-        let min_y_px = -(height as f32 - offset_y) / scale;
-
-        let y_range = (
-            min_y_px * self.distance_per_px,
-            max_y_px * self.distance_per_px,
-        );
-        let x_range = (
-            min_x_px * self.distance_per_px,
-            max_x_px * self.distance_per_px,
-        );
-        (x_range, y_range)
+    // Built-in presets, and on native the scenario files in the scenarios directory.
+    pub fn reload_scenarios(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            (self.presets, self.scenario_errors) = scenario::load_dir(scenario::SCENARIO_DIR);
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.presets = scenario::embedded();
+        }
+        for error in &self.scenario_errors {
+            log::warn!("scenario {}", error);
+        }
     }
 
     pub fn load_preset(&mut self, preset_num: usize) {
-        self.focused = None;
+        if let Some(preset) = self.presets.get(preset_num).cloned() {
+            self.apply_preset(&preset);
+        }
+    }
 
-        let preset = self.presets.get(preset_num);
+    // Same as the galaxy collision preset but with a chosen encounter.
+    pub fn load_galaxy_collision(&mut self, impact_parameter: f32, relative_velocity: f32) {
+        let preset = Preset::galaxy_collision(impact_parameter, relative_velocity, 3000);
+        self.apply_preset(&preset);
+    }
 
-        match preset {
-            Some(preset) => {
-                // this is the one place that self.bodies should be set
-                self.bodies = preset.bodies.clone();
-                self.distance_per_px = preset.distance_per_px as f32;
-                self.num_ticks = preset.ticks_per_press;
-                self.dt = preset.dt;
-                self.draw_frequency = preset.draw_frequency;
-            }
-            None => {}
-        };
+    // Same as the star cluster preset but with different settings.
+    pub fn load_cluster(&mut self, settings: &ClusterSettings) {
+        self.apply_preset(&Preset::plummer_cluster(settings));
+    }
+
+    pub fn presets(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    // A preset that isn't in the list, like a scenario file given on the command line or an import.
+    pub fn load_scenario(&mut self, preset: &Preset) {
+        self.reset();
+        self.apply_preset(preset);
+    }
+
+    // Bodies from JPL Horizons vector tables, returns how many were added.
+    pub fn import_horizons(&mut self, tables: &str) -> Result<usize, HorizonsError> {
+        let bodies = horizons::parse(tables)?;
+        let num_bodies = bodies.len();
+        self.load_scenario(&Preset::imported("Horizons import", bodies));
+        Ok(num_bodies)
+    }
+
+    // Earth and satellites from two-line element sets, returns how many satellites were added.
+    pub fn import_tle(&mut self, text: &str) -> Result<usize, TleError> {
+        let sets = tle::parse(text)?;
+        self.load_scenario(&Preset::imported("Satellites", tle::bodies(&sets)));
+        Ok(sets.len())
+    }
+
+    fn apply_preset(&mut self, preset: &Preset) {
+        self.focused = None;
+        // conditions refer to bodies by index, so they don't carry over to other presets
+        self.events = Events::default();
+        self.poincare = PoincareSection::default();
+
+        // this is the one place that self.bodies should be set
+        self.bodies = preset.bodies.clone();
+        self.particles = preset.particles.clone();
+        self.distance_per_px = preset.distance_per_px as f32;
+        self.num_ticks = preset.ticks_per_press;
+        self.dt = preset.dt;
+        self.draw_frequency = preset.draw_frequency;
+        self.softening = preset.softening;
+        self.flat_hierarchy = preset.is_cluster;
+        self.removed.clear();
+        self.removed_conditions.clear();
+        self.cluster = ClusterStats::default();
+        self.cluster.enabled = preset.is_cluster;
         self.create_hierarchy();
     }
 
@@ -204,16 +256,23 @@ impl Orbital {
         self.hierarchy.node_weights_mut().collect()
     }
     pub fn refresh_hierarchy(&mut self) {
-        let (hierarchy, root_index) = build_hierarchy(&self.original_bodies());
+        let (hierarchy, root_index) = self.build_hierarchy(&self.original_bodies());
         self.hierarchy = hierarchy;
         self.root = root_index;
     }
     fn create_hierarchy(&mut self) {
-        let (hierarchy, root_index) = build_hierarchy(&self.bodies);
+        let (hierarchy, root_index) = self.build_hierarchy(&self.bodies);
         self.hierarchy = hierarchy;
         self.root = root_index;
     }
-    fn bodies_list(&self) -> Vec<String> {
+    fn build_hierarchy(&self, bodies: &[Body]) -> (DiGraph<Body, ()>, NodeIndex) {
+        if self.flat_hierarchy {
+            build_flat_hierarchy(bodies)
+        } else {
+            build_hierarchy(bodies)
+        }
+    }
+    pub fn bodies_list(&self) -> Vec<String> {
         self.bodies_vec()
             .iter()
             .enumerate()
@@ -235,6 +294,120 @@ impl Orbital {
             .collect()
     }
 
+    // Add a body to the initial conditions.
+    pub fn add_body(&mut self, body: Body) {
+        let mut bodies = self.original_bodies();
+        let num_bodies = bodies.len();
+
+        // barycenters come after the original bodies in the hierarchy, so their indices shift by one
+        let shift = IndexChange::Inserted(num_bodies);
+        for b in bodies.iter_mut() {
+            b.selected_vel_lock = shift.apply(b.selected_vel_lock).unwrap_or_default();
+        }
+        self.remap_indices(shift);
+
+        bodies.push(body);
+        self.bodies = bodies;
+        self.create_hierarchy();
+    }
+
+    // Change every stored body index, for when a body is inserted or removed.
+    // The change is kept for anything outside the simulation that refers to bodies, like the UI's selections.
+    // Returns the conditions on bodies that are gone, with the indices from before the change.
+    fn remap_indices(&mut self, change: IndexChange) -> DroppedConditions {
+        let dropped = DroppedConditions {
+            events: self.events.remap_indices(|i| change.apply(i)),
+            poincare: self.poincare.remap_indices(|i| change.apply(i)),
+        };
+        self.index_changes.push(change);
+        dropped
+    }
+
+    // Changes to body indices since the last call, oldest first.
+    pub fn take_index_changes(&mut self) -> Vec<IndexChange> {
+        std::mem::take(&mut self.index_changes)
+    }
+
+    // Add a ring of test particles around one of the bodies.
+    pub fn add_ring(&mut self, parent: usize, settings: &RingSettings) {
+        let Some(parent) = self.hierarchy.node_weight(NodeIndex::new(parent)) else {
+            return;
+        };
+        let particles = ring(parent, settings);
+        self.particles.extend(&particles);
+    }
+
+    // Lagrange points of a pair of bodies, in absolute positions.
+    pub fn lagrange_points(&self, body1: usize, body2: usize) -> Option<[Position; 5]> {
+        if body1 == body2 {
+            return None;
+        }
+        let b1 = self.hierarchy.node_weight(NodeIndex::new(body1))?;
+        let b2 = self.hierarchy.node_weight(NodeIndex::new(body2))?;
+
+        let relative_pos = b2.absolute_pos.minus(b1.absolute_pos);
+        let relative_vel = b2.absolute_vel.minus(b1.absolute_vel);
+        let counterclockwise = relative_pos.cross(relative_vel) >= 0.;
+
+        Some(lagrange_points(
+            b1.mass,
+            b1.absolute_pos,
+            b2.mass,
+            b2.absolute_pos,
+            counterclockwise,
+        ))
+    }
+
+    // Add a test particle at one of the Lagrange points of a pair of bodies (0 = L1),
+    // moving with the pair so it stays at the Lagrange point.
+    pub fn add_lagrange_particle(&mut self, body1: usize, body2: usize, point: usize) {
+        let Some(points) = self.lagrange_points(body1, body2) else {
+            return;
+        };
+        let (b1, b2) = (
+            &self.hierarchy[NodeIndex::new(body1)],
+            &self.hierarchy[NodeIndex::new(body2)],
+        );
+        let pos = points[point];
+        let vel = co_orbital_velocity(
+            b1.mass,
+            b1.absolute_pos,
+            b1.absolute_vel,
+            b2.mass,
+            b2.absolute_pos,
+            b2.absolute_vel,
+            pos,
+        );
+        self.particles.add(pos, vel, (255, 0, 255));
+    }
+
+    pub fn rotating_frame(&self, body1: usize, body2: usize) -> Option<RotatingFrame> {
+        if body1 == body2 {
+            return None;
+        }
+        let body1 = self.hierarchy.node_weight(NodeIndex::new(body1))?;
+        let body2 = self.hierarchy.node_weight(NodeIndex::new(body2))?;
+        Some(RotatingFrame::new(body1, body2))
+    }
+
+    // Jacobi constant of a test particle or a body in the frame rotating with body1 and body2
+    pub fn jacobi_constant(&self, body1: usize, body2: usize, of: JacobiSubject) -> Option<f64> {
+        let frame = self.rotating_frame(body1, body2)?;
+        let (pos, vel) = match of {
+            JacobiSubject::Particle(i) => {
+                if i >= self.particles.len() || !self.particles.active[i] {
+                    return None;
+                }
+                (self.particles.position(i), self.particles.velocity(i))
+            }
+            JacobiSubject::Body(i) => {
+                let body = self.hierarchy.node_weight(NodeIndex::new(i))?;
+                (body.absolute_pos, body.absolute_vel)
+            }
+        };
+        Some(frame.jacobi_constant(pos, vel))
+    }
+
     // Set circular or orbital velocity for any body that is locked to one of those.
     // Only applies when setting initial conditions before starting.
     pub fn set_velocities(&mut self) {
@@ -296,6 +469,68 @@ impl Orbital {
         }
     }
 
+    // integrate num_ticks ticks of this simulation only
+    fn run_ticks(&mut self) {
+        if !self.started {
+            return;
+        }
+        if self.timeline.is_rewound() {
+            self.discard_later_history();
+        }
+        if self.stopped {
+            return;
+        }
+        for _ in 0..self.num_ticks {
+            // tangent vector uses the state at the start of the tick, same as the main update
+            self.step_chaos();
+            let sources = (!self.particles.is_empty()).then(|| self.particle_sources());
+            self.run_euler();
+            self.step_particles(sources);
+
+            let tick_start = self.t;
+            self.t += self.dt;
+            self.timeline.record(self.t, self.dt, self.integrator);
+            // the tick with the collision is recorded like the others, the bodies have moved already
+            if self.stopped {
+                break;
+            }
+
+            // resonant angles are sampled at the same interval that points are graphed
+            if self.resonances.is_due(self.t, self.draw_frequency as f32) {
+                self.sample_resonances();
+            }
+            if self.cluster.is_due(self.t, self.draw_frequency as f32) {
+                self.sample_cluster();
+            }
+
+            self.poincare.detect(&self.hierarchy, tick_start, self.dt);
+
+            let num_events =
+                self.events.detect(&self.hierarchy, tick_start, self.dt) + self.update_escapes();
+            if num_events > 0 && self.events.pause_on_event {
+                break;
+            }
+        }
+        self.analyze();
+
+        if log_enabled!(Level::Debug) {
+            debug!("");
+            for b in self.bodies_vec().iter() {
+                debug!("{}: abs: {:?}, rel: {:?}", b.name, b.absolute_pos, b.pos);
+            }
+        }
+    }
+
+    // Apply the velocity locks and group the bodies, which is done every frame until starting.
+    pub fn prepare(&mut self) {
+        // need to set velocities before so that barycenter grouping knows the bodies' velocities
+        // need to set velocities after to apply the circular velocities set by the grouping
+        // TODO refactor? calculate circular velocity while grouping?
+        self.set_velocities();
+        self.refresh_hierarchy();
+        self.set_velocities();
+    }
+
     pub fn start(&mut self) {
         self.started = true;
 
@@ -304,6 +539,204 @@ impl Orbital {
         }
 
         self.analysis = self.analysis.initialize(self);
+        self.resonances.clear();
+        self.sample_resonances();
+        self.chaos.clear();
+        self.cluster.clear();
+        self.sample_cluster();
+        self.escapes.clear();
+        self.escapes.update(&self.hierarchy);
+        self.timeline.start(self.t, &self.particles);
+    }
+
+    fn step_chaos(&mut self) {
+        if !self.chaos.enabled {
+            return;
+        }
+        let bodies: Vec<(Position, f32)> = self
+            .hierarchy
+            .node_weights()
+            .filter(|b| !b.is_barycenter)
+            .map(|b| (b.absolute_pos, b.mass))
+            .collect();
+        self.chaos.step(&bodies, self.dt);
+    }
+
+    // massive bodies that test particles are attracted to
+    fn particle_sources(&self) -> Vec<Source> {
+        self.hierarchy
+            .node_weights()
+            .filter(|b| !b.is_barycenter)
+            .map(|b| Source {
+                pos: b.absolute_pos,
+                mass: b.mass,
+                radius: b.radius,
+            })
+            .collect()
+    }
+
+    // after the bodies moved, with the sources from before they did
+    fn step_particles(&mut self, sources: Option<Vec<Source>>) {
+        let Some(sources) = sources else {
+            return;
+        };
+        let next_sources = self.particle_sources();
+        self.particles.step(
+            &sources,
+            &next_sources,
+            self.dt,
+            self.integrator,
+            self.parallel,
+        );
+    }
+
+    fn sample_cluster(&mut self) {
+        let stars: Vec<Star> = self
+            .hierarchy
+            .node_weights()
+            .filter(|b| !b.is_barycenter)
+            .map(|b| (b.absolute_pos, b.absolute_vel, b.mass))
+            .collect();
+        self.cluster.sample(&stars, self.softening, self.t);
+    }
+
+    // Log escapes and captures since the last tick, and remove escapers that are far enough away.
+    // Returns the number of events.
+    fn update_escapes(&mut self) -> usize {
+        let transitions = self.escapes.update(&self.hierarchy);
+        for transition in transitions.iter() {
+            let body = &self.hierarchy[NodeIndex::new(transition.body)];
+            let mut bodies = vec![body.copy()];
+            if !transition.system {
+                let default = Body::default();
+                let parent = parent_node_or_default(
+                    &self.hierarchy,
+                    NodeIndex::new(transition.body),
+                    &default,
+                );
+                bodies.push(parent.copy());
+            }
+            let kind = if transition.escaped {
+                EventKind::Escape {
+                    system: transition.system,
+                }
+            } else {
+                EventKind::Capture {
+                    system: transition.system,
+                }
+            };
+            self.events.log.push(Event {
+                t: self.t,
+                kind,
+                bodies,
+            });
+        }
+
+        let to_remove = self.escapes.to_remove();
+        for &i in to_remove.iter() {
+            self.remove_body(i);
+        }
+        if !to_remove.is_empty() {
+            self.escapes.update(&self.hierarchy);
+        }
+        transitions.len()
+    }
+
+    // Remove a body while running, e.g. an escaper that's too far away to matter any more.
+    // The hierarchy is rebuilt from the current state, keeping the trajectories.
+    fn remove_body(&mut self, index: usize) {
+        let mut bodies = self.original_bodies();
+        if index >= bodies.len() {
+            return;
+        }
+        let num_bodies = bodies.len();
+        let removed = bodies.remove(index);
+
+        self.rebuild_hierarchy(bodies);
+        self.removed.push((index, removed));
+
+        // barycenters are new after rebuilding, so references to them are dropped too
+        let dropped = self.remap_indices(IndexChange::Removed { index, num_bodies });
+        self.removed_conditions.push(dropped);
+        self.focused = None;
+        self.resonances.clear();
+    }
+
+    // Change the velocity of a body while running, e.g. in a branch to see what difference it makes.
+    // The barycenters depend on the velocity, so the hierarchy is rebuilt like when removing a body.
+    pub fn nudge(&mut self, index: usize, dv: Velocity) {
+        if self.timeline.is_rewound() {
+            self.discard_later_history();
+        }
+        let mut bodies = self.original_bodies();
+        let num_bodies = bodies.len();
+        let Some(body) = bodies.get_mut(index) else {
+            return;
+        };
+        body.absolute_vel = body.absolute_vel.add(dv);
+
+        self.rebuild_hierarchy(bodies);
+        self.remap_indices(IndexChange::Truncated(num_bodies));
+        self.focused = None;
+        self.resonances.clear();
+        self.stopped = false;
+        self.analyze();
+    }
+
+    // Build the hierarchy again from the current state of the bodies, keeping their trajectories.
+    fn rebuild_hierarchy(&mut self, bodies: Vec<Body>) {
+        let (mut hierarchy, root) = self.build_hierarchy(&bodies);
+        for (i, body) in bodies.into_iter().enumerate() {
+            hierarchy[NodeIndex::new(i)].trajectory = body.trajectory;
+        }
+        // the latest state is relative to the new parents, barycenters start recording from here
+        for body in hierarchy.node_weights_mut() {
+            let state = body.copy();
+            if body.is_barycenter {
+                body.trajectory = vec![state];
+            } else if let Some(latest) = body.trajectory.last_mut() {
+                *latest = state;
+            }
+        }
+        self.hierarchy = hierarchy;
+        self.root = root;
+        self.timeline.rebase(&self.particles);
+    }
+
+    // Copy the current state into a new branch, which then runs alongside this simulation.
+    pub fn fork(&mut self, name: &str) {
+        if !self.started {
+            return;
+        }
+        let mut sim = self.clone();
+        sim.branches.clear();
+        let branch = Branch::new(name, sim);
+        self.branches.push(branch);
+    }
+
+    pub fn remove_branch(&mut self, index: usize) {
+        if index < self.branches.len() {
+            self.branches.remove(index);
+        }
+    }
+
+    // how far the bodies in a branch are from the same bodies here
+    pub fn branch_divergence(&self, index: usize) -> Vec<Divergence> {
+        match self.branches.get(index) {
+            Some(branch) => divergence(&self.bodies_vec(), &branch.sim.bodies_vec()),
+            None => vec![],
+        }
+    }
+
+    // Lyapunov exponent and MEGNO, if enabled before running
+    pub fn chaos_indicators(&self) -> Option<ChaosIndicators> {
+        self.chaos.indicators()
+    }
+
+    fn sample_resonances(&mut self) {
+        let (index_groups, _) = self.sibling_groups();
+        self.resonances
+            .sample(&self.hierarchy, &index_groups, self.t);
     }
 
     // Return groups of sibling bodies in BFS order.
@@ -343,63 +776,99 @@ impl Orbital {
     fn hierarchical_update(&mut self) {
         let (index_groups, _) = self.sibling_groups();
 
-        let mut updates: HashMap<NodeIndex, (Position, Velocity, Acceleration)> = HashMap::new();
+        // A group's bodies are only attracted by each other, in positions relative to their parent,
+        // so every group's next states can be calculated before any are applied.
+        let updates =
+            parallel::map_indices(index_groups.len(), self.parallel, GROUPS_PER_THREAD, |i| {
+                self.group_updates(&index_groups[i])
+            });
 
         // TODO add velocity to root node when initializing, then include it in updates
-        for group in index_groups.iter() {
-            // calculate updates
-            for &child_idx in group.iter() {
-                let child = &self.hierarchy[child_idx];
-                let other_children: Vec<&Body> = group
-                    .iter()
-                    .filter(|&&i| i != child_idx)
-                    .map(|&nx| &self.hierarchy[nx])
-                    .collect();
-
-                let acceleration = self.calc_acceleration(child, other_children);
-
-                let (next_r, next_v) =
-                    symplectic_euler_calc(child.pos, child.v, acceleration, self.dt);
-                updates.insert(child_idx, (next_r, next_v, acceleration));
-            }
-
+        for (group, updates) in index_groups.iter().zip(updates) {
             // apply updates
             // if no parent, then it is the root node - consider 0,0 to be parent
             // (maybe add 0,0 to the tree?)
-            for &node_idx in group {
-                if let Some(update) = updates.get(&node_idx) {
-                    let default = &Body::default(); // 0,0 position
-                    let updated_parent = parent_node_or_default(&self.hierarchy, node_idx, default);
-
-                    // parent has already been updated because it's looping in BFS order
-                    let parent_abs_pos = updated_parent.absolute_pos;
-                    let parent_abs_vel = updated_parent.absolute_vel;
-                    let node = self
-                        .hierarchy
-                        .node_weight_mut(node_idx)
-                        .expect("invalid index");
-
-                    let (next_r, next_v, a) = update.clone();
-                    node.update(next_r, next_v, a, parent_abs_pos, parent_abs_vel);
-                } else {
-                    // root node - no update
-                }
+            for (&node_idx, (next_r, next_v, a)) in group.iter().zip(updates) {
+                let default = &Body::default(); // 0,0 position
+                let updated_parent = parent_node_or_default(&self.hierarchy, node_idx, default);
+
+                // parent has already been updated because it's looping in BFS order
+                let parent_abs_pos = updated_parent.absolute_pos;
+                let parent_abs_vel = updated_parent.absolute_vel;
+                let node = self
+                    .hierarchy
+                    .node_weight_mut(node_idx)
+                    .expect("invalid index");
+
+                node.update(next_r, next_v, a, parent_abs_pos, parent_abs_vel);
             }
         }
     }
 
-    fn calc_acceleration(&self, affected_body: &Body, sources: Vec<&Body>) -> Acceleration {
-        let total_a_for_body = sources
-            .iter()
-            .map(|source| {
-                let a_from_source =
-                    gravitational_acceleration(source.pos, affected_body.pos, source.mass);
+    // next relative position, velocity and acceleration of each body in a sibling group
+    fn group_updates(&self, group: &[NodeIndex]) -> Vec<(Position, Velocity, Acceleration)> {
+        let positions: Vec<Position> = group.iter().map(|&nx| self.hierarchy[nx].pos).collect();
+        let accelerations =
+            parallel::map_indices(group.len(), self.parallel, BODIES_PER_THREAD, |k| {
+                self.calc_acceleration(group, &positions, k)
+            });
 
-                a_from_source
-            })
-            .fold(Acceleration::default(), |acc, a| acc.add(a));
+        match self.integrator {
+            Integrator::SymplecticEuler => group
+                .iter()
+                .zip(accelerations)
+                .map(|(&child_idx, acceleration)| {
+                    let child = &self.hierarchy[child_idx];
+                    let (next_r, next_v) =
+                        symplectic_euler_calc(child.pos, child.v, acceleration, self.dt);
+                    (next_r, next_v, acceleration)
+                })
+                .collect(),
+            Integrator::VelocityVerlet => {
+                let next_positions: Vec<Position> = group
+                    .iter()
+                    .zip(&accelerations)
+                    .map(|(&nx, &a)| {
+                        let child = &self.hierarchy[nx];
+                        verlet_position(child.pos, child.v, a, self.dt)
+                    })
+                    .collect();
+                let next_accelerations =
+                    parallel::map_indices(group.len(), self.parallel, BODIES_PER_THREAD, |k| {
+                        self.calc_acceleration(group, &next_positions, k)
+                    });
+                group
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &child_idx)| {
+                        let child = &self.hierarchy[child_idx];
+                        let next_a = next_accelerations[k];
+                        let next_v = verlet_velocity(child.v, accelerations[k], next_a, self.dt);
+                        (next_positions[k], next_v, next_a)
+                    })
+                    .collect()
+            }
+        }
+    }
 
-        total_a_for_body
+    // acceleration of the k-th body of a sibling group from the others, at the given positions of the group
+    fn calc_acceleration(
+        &self,
+        group: &[NodeIndex],
+        positions: &[Position],
+        k: usize,
+    ) -> Acceleration {
+        group.iter().enumerate().filter(|&(j, _)| j != k).fold(
+            Acceleration::default(),
+            |acc, (j, &source)| {
+                acc.add(softened_gravitational_acceleration(
+                    positions[j],
+                    positions[k],
+                    self.hierarchy[source].mass,
+                    self.softening,
+                ))
+            },
+        )
     }
 
     // run function contains calculations necessary for the iteration process
@@ -413,8 +882,25 @@ impl Orbital {
         self.check_collisions();
     }
 
+    // stopped by a collision
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
     fn check_collisions(&mut self) -> bool {
-        let bodies = self.current_bodies();
+        match self.collided_pair() {
+            Some((b, b2)) => {
+                println!("collided: {}, {}", b.name, b2.name);
+                self.stopped = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // a pair of bodies that overlap
+    fn collided_pair(&self) -> Option<(&Body, &Body)> {
+        let bodies = self.bodies_vec();
 
         for (i, b) in bodies.iter().enumerate() {
             for b2 in bodies[i + 1..].iter() {
@@ -427,17 +913,15 @@ impl Orbital {
                 let is_collided = distance_between <= (b.radius + b2.radius);
 
                 if is_collided {
-                    self.stopped = true;
-                    println!("collided: {}, {}", b.name, b2.name);
-                    return true;
+                    return Some((b, b2));
                 }
             }
         }
-        false
+        None
     }
 
-    fn reset(&mut self) {
-        let initial_bodies: Vec<Body> = self
+    pub fn reset(&mut self) {
+        let mut initial_bodies: Vec<Body> = self
             .original_bodies()
             .iter()
             .map(|body| match body.trajectory.get(0) {
@@ -446,15 +930,383 @@ impl Orbital {
             })
             .collect();
 
+        // put removed bodies back in reverse order of removal, so every index is the same as before,
+        // and with them the conditions that were dropped when they were removed
+        let mut removed_conditions = std::mem::take(&mut self.removed_conditions);
+        for (index, body) in std::mem::take(&mut self.removed).into_iter().rev() {
+            let initial_body = body.trajectory.first().cloned().unwrap_or(body);
+            initial_bodies.insert(index, initial_body);
+            self.remap_indices(IndexChange::Inserted(index));
+            if let Some(dropped) = removed_conditions.pop() {
+                self.restore_conditions(dropped);
+            }
+        }
+
         self.bodies = initial_bodies;
         self.create_hierarchy();
 
         self.started = false;
         self.stopped = false;
         self.t = 0.;
+        self.events.log.clear();
+        self.resonances.clear();
+        self.poincare.points.clear();
+        self.chaos.clear();
+        self.cluster.clear();
+        self.escapes.clear();
+        self.timeline.clear();
+        self.realtime = RealTime::default();
+        self.branches.clear();
+        self.particles.reset();
+    }
+
+    // conditions dropped with a removed body, once it's back at the index it had
+    fn restore_conditions(&mut self, dropped: DroppedConditions) {
+        for condition in dropped.events {
+            self.events.add_condition(condition);
+        }
+        // unless another section was set up since
+        if let Some(section) = dropped.poincare {
+            if !self.poincare.enabled {
+                self.poincare = section;
+            }
+        }
+    }
+
+    // time warp that plays one press of num_ticks in 10 s
+    pub fn default_warp(&self) -> f32 {
+        self.num_ticks as f32 * self.dt / 10.
+    }
+
+    // start real-time playback, starting the simulation if needed
+    pub fn play(&mut self) {
+        if !self.started {
+            self.start();
+        }
+        if self.realtime.warp <= 0. {
+            self.realtime.warp = self.default_warp();
+        }
+        self.timeline.playback = Playback::Paused;
+        self.realtime.play();
+    }
+
+    // the play/pause button and key
+    pub fn toggle_play(&mut self) {
+        if self.realtime.playing {
+            self.realtime.pause();
+        } else {
+            self.play();
+        }
+    }
+
+    // Runs the ticks that are due after `elapsed` wall-clock seconds of real-time playback,
+    // within the frame budget, returns the number of ticks. Playback stops when the simulation does.
+    pub fn play_for(&mut self, elapsed: f32) -> usize {
+        if self.stopped {
+            self.realtime.pause();
+        }
+        let mut realtime = std::mem::take(&mut self.realtime);
+        let num_ticks = self.num_ticks;
+        let ticks = realtime.run_due(elapsed, self.dt, realtime::FRAME_BUDGET, |ticks| {
+            self.num_ticks = ticks as i32;
+            self.run();
+            !self.stopped
+        });
+        self.num_ticks = num_ticks;
+        self.realtime = realtime;
+        ticks
+    }
+
+    // Restore the state at a recorded tick, the later ticks are kept until running again.
+    pub fn seek(&mut self, tick: usize) {
+        if !self.started || self.timeline.is_empty() {
+            return;
+        }
+        self.timeline.set_current(tick);
+        let tick = self.timeline.current_tick();
+
+        for body in self.hierarchy.node_weights_mut() {
+            let index = self.timeline.trajectory_index(tick, body.is_barycenter);
+            if let Some(state) = body.trajectory.get(index).map(|b| b.copy()) {
+                body.restore(&state);
+            }
+        }
+        self.replay_particles(tick);
+        if let Some(t) = self.timeline.time_at(tick) {
+            self.t = t;
+        }
+        // stopped at the tick of a collision, running again from an earlier tick is fine
+        self.stopped = self.collided_pair().is_some();
+
+        self.escapes.clear();
+        self.escapes.update(&self.hierarchy);
+        self.analyze();
+
+        for branch in self.branches.iter_mut() {
+            branch.sim.seek(tick);
+        }
+    }
+
+    // step the particles through the recorded ticks, pulled by the recorded bodies
+    fn replay_particles(&mut self, tick: usize) {
+        if self.particles.is_empty() {
+            return;
+        }
+        let (mut particles, from) = self.timeline.replay_particles_from(tick, &self.particles);
+        let sources_at = |tick: usize| -> Vec<Source> {
+            self.hierarchy
+                .node_weights()
+                .filter(|b| !b.is_barycenter)
+                .filter_map(|b| b.trajectory.get(tick))
+                .map(|b| Source {
+                    pos: b.absolute_pos,
+                    mass: b.mass,
+                    radius: b.radius,
+                })
+                .collect()
+        };
+        let mut next_sources = sources_at(from);
+        for replayed in from..tick {
+            let sources = std::mem::replace(&mut next_sources, sources_at(replayed + 1));
+            let dt = self.timeline.step_at(replayed).unwrap_or(self.dt);
+            let integrator = self
+                .timeline
+                .integrator_at(replayed)
+                .unwrap_or(self.integrator);
+            particles.step(&sources, &next_sources, dt, integrator, self.parallel);
+        }
+        self.particles = particles;
+    }
+
+    // state of a node at a recorded tick, or its current state before anything is recorded
+    fn state_at<'a>(&self, body: &'a Body, tick: usize) -> Option<&'a Body> {
+        if self.timeline.is_empty() {
+            return Some(body);
+        }
+        body.trajectory
+            .get(self.timeline.trajectory_index(tick, body.is_barycenter))
+    }
+
+    // State of every body at recorded ticks, at least interval seconds apart.
+    // Bodies that were removed aren't included.
+    pub fn body_samples(&self, interval: f32, include_barycenters: bool) -> Vec<BodySample> {
+        let no_parent = Body {
+            name: String::new(),
+            ..Body::default()
+        };
+        let mut samples = vec![];
+        for tick in export::sampled_ticks(&self.timeline, interval) {
+            let t = self.timeline.time_at(tick).unwrap_or(self.t);
+            for nx in self.hierarchy.node_indices() {
+                let body = &self.hierarchy[nx];
+                if body.is_barycenter && !include_barycenters {
+                    continue;
+                }
+                let Some(state) = self.state_at(body, tick) else {
+                    continue;
+                };
+                let parent = parent_node_or_default(&self.hierarchy, nx, &no_parent);
+                samples.push(BodySample {
+                    t,
+                    tick,
+                    body: body.name.clone(),
+                    parent: parent.name.clone(),
+                    x: state.absolute_pos.x,
+                    y: state.absolute_pos.y,
+                    vx: state.absolute_vel.x,
+                    vy: state.absolute_vel.y,
+                    rel_x: state.pos.x,
+                    rel_y: state.pos.y,
+                    rel_vx: state.v.x,
+                    rel_vy: state.v.y,
+                    ax: state.computed_a.x,
+                    ay: state.computed_a.y,
+                });
+            }
+        }
+        samples
+    }
+
+    // Energy at the same ticks as body_samples
+    pub fn metrics_samples(&self, interval: f32) -> Vec<MetricsSample> {
+        let (index_groups, _) = self.sibling_groups();
+        export::sampled_ticks(&self.timeline, interval)
+            .into_iter()
+            .map(|tick| {
+                let body_groups: Vec<Vec<&Body>> = index_groups
+                    .iter()
+                    .map(|group| {
+                        group
+                            .iter()
+                            .filter_map(|nx| self.state_at(&self.hierarchy[*nx], tick))
+                            .collect()
+                    })
+                    .collect();
+                let (kinetic_e, gravitational_e, total_e) =
+                    Analysis::energy(&body_groups, self.softening);
+                MetricsSample {
+                    t: self.timeline.time_at(tick).unwrap_or(self.t),
+                    tick,
+                    kinetic_e,
+                    gravitational_e,
+                    total_e,
+                    energy_error: self.analysis.diff_percentage(total_e),
+                }
+            })
+            .collect()
+    }
+
+    pub fn export(&self, settings: &ExportSettings) -> Export {
+        let bodies = self.body_samples(settings.interval, settings.include_barycenters);
+        let metrics = self.metrics_samples(settings.interval);
+        Export {
+            bodies: export::format(&bodies, settings.format),
+            metrics: export::format(&metrics, settings.format),
+            format: settings.format,
+        }
+    }
+
+    // Continue from the tick being shown, forgetting everything that was recorded after it.
+    fn discard_later_history(&mut self) {
+        let tick = self.timeline.current_tick();
+        for body in self.hierarchy.node_weights_mut() {
+            let index = self.timeline.trajectory_index(tick, body.is_barycenter);
+            body.trajectory.truncate(index + 1);
+        }
+        self.timeline.truncate();
+
+        let t = self.t;
+        self.events.log.retain(|e| e.t <= t);
+        self.poincare.points.retain(|p| p.t <= t);
+        self.resonances.truncate(t);
+        self.cluster.truncate(t);
+        // the tangent vector isn't recorded, so chaos indicators start over from here
+        self.chaos.clear();
+    }
+
+    // trajectory up to the tick being shown
+    pub fn recorded_trajectory<'a>(&self, body: &'a Body) -> &'a [Body] {
+        if !self.timeline.is_rewound() {
+            return &body.trajectory;
+        }
+        let index = self
+            .timeline
+            .trajectory_index(self.timeline.current_tick(), body.is_barycenter);
+        &body.trajectory[..(index + 1).min(body.trajectory.len())]
+    }
+
+    // Everything needed to continue the simulation from its current state.
+    pub fn checkpoint(&self, include_trajectories: bool) -> Checkpoint {
+        let bodies = self
+            .hierarchy
+            .node_weights()
+            .map(|b| {
+                if include_trajectories {
+                    b.clone()
+                } else {
+                    b.copy()
+                }
+            })
+            .collect();
+        // in the order they were added, which is the order that siblings are updated in
+        let edges = self
+            .hierarchy
+            .edge_indices()
+            .filter_map(|e| self.hierarchy.edge_endpoints(e))
+            .map(|(parent, child)| (parent.index(), child.index()))
+            .collect();
+
+        Checkpoint {
+            version: checkpoint::VERSION,
+            dt: self.dt,
+            integrator: self.integrator,
+            t: self.t,
+            num_ticks: self.num_ticks,
+            distance_per_px: self.distance_per_px,
+            draw_frequency: self.draw_frequency,
+            softening: self.softening,
+            flat_hierarchy: self.flat_hierarchy,
+            started: self.started,
+            stopped: self.stopped,
+            initial_e: self.analysis.initial_e,
+            bodies,
+            edges,
+            root: self.root.index(),
+            particles: self.particles.clone(),
+            events: self.events.clone(),
+            timeline: include_trajectories.then(|| self.timeline.clone()),
+            removed: if include_trajectories {
+                self.removed.clone()
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    // Replace the simulation with a saved one, which continues exactly as the saved one would have.
+    pub fn load_checkpoint(&mut self, checkpoint: Checkpoint) {
+        let mut hierarchy = DiGraph::new();
+        for body in checkpoint.bodies {
+            hierarchy.add_node(body);
+        }
+        for (parent, child) in checkpoint.edges {
+            hierarchy.add_edge(NodeIndex::new(parent), NodeIndex::new(child), ());
+        }
+        let num_nodes = hierarchy.node_count();
+        self.hierarchy = hierarchy;
+        self.root = NodeIndex::new(checkpoint.root);
+        self.bodies = self
+            .current_bodies()
+            .into_iter()
+            .filter(|b| !b.is_barycenter)
+            .collect();
+
+        self.dt = checkpoint.dt;
+        self.integrator = checkpoint.integrator;
+        self.t = checkpoint.t;
+        self.num_ticks = checkpoint.num_ticks;
+        self.distance_per_px = checkpoint.distance_per_px;
+        self.draw_frequency = checkpoint.draw_frequency;
+        self.softening = checkpoint.softening;
+        self.flat_hierarchy = checkpoint.flat_hierarchy;
+        self.started = checkpoint.started;
+        self.stopped = checkpoint.stopped;
+        self.particles = checkpoint.particles;
+        self.events = checkpoint.events;
+        self.removed = checkpoint.removed;
+        // the dropped conditions aren't in checkpoints, only the bodies come back on reset
+        self.removed_conditions = vec![DroppedConditions::default(); self.removed.len()];
+
+        match checkpoint.timeline {
+            Some(timeline) => self.timeline = timeline,
+            None if self.started => {
+                for body in self.hierarchy.node_weights_mut() {
+                    body.trajectory = vec![body.copy()];
+                }
+                self.timeline.start(self.t, &self.particles);
+            }
+            None => self.timeline.clear(),
+        }
+
+        self.remap_indices(IndexChange::Truncated(num_nodes));
+        self.focused = None;
+        self.branches.clear();
+        self.resonances.clear();
+        self.poincare.points.clear();
+        self.chaos.clear();
+        self.cluster.enabled = self.flat_hierarchy;
+        self.cluster.clear();
+        self.escapes.clear();
+        self.escapes.update(&self.hierarchy);
+
+        self.analysis = Analysis {
+            initial_e: checkpoint.initial_e,
+            ..Analysis::default()
+        };
+        self.analyze();
     }
 
-    fn set_focus(&mut self, focused: Option<NodeIndex>) {
+    pub fn set_focus(&mut self, focused: Option<NodeIndex>) {
         if let Some(focused_idx) = focused {
             self.focused = Some(focused_idx);
         } else {
@@ -467,37 +1319,57 @@ impl Orbital {
     }
 }
 
-#[derive(Default)]
-struct UiState {
-    panel_width: f32,
+// How body indices changed when bodies were added or removed
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum IndexChange {
+    // a body was inserted at this index, the ones after it move up
+    Inserted(usize),
+    // one of the original bodies was removed, the barycenters after them (num_bodies and up) are new
+    Removed { index: usize, num_bodies: usize },
+    // only indices below this are still the same bodies
+    Truncated(usize),
 }
-impl UiState {
-    fn new() -> Self {
-        Self {
-            panel_width: 300.,
-            ..Default::default()
+
+impl IndexChange {
+    // the new index of a body, or None if it's gone
+    pub fn apply(&self, i: usize) -> Option<usize> {
+        match *self {
+            IndexChange::Inserted(index) => Some(if i >= index { i + 1 } else { i }),
+            IndexChange::Removed { index, num_bodies } => {
+                if i == index || i >= num_bodies {
+                    None
+                } else if i > index {
+                    Some(i - 1)
+                } else {
+                    Some(i)
+                }
+            }
+            IndexChange::Truncated(len) => (i < len).then_some(i),
         }
     }
 }
 
-#[derive(Default)]
+// conditions that referred to bodies that are gone, with the indices from before
+#[derive(Clone, Default)]
+struct DroppedConditions {
+    events: Vec<EventCondition>,
+    poincare: Option<PoincareSection>,
+}
+
+#[derive(Clone, Default)]
 pub struct Analysis {
     pub initial_e: f64,
-    kinetic_e: f64,
+    pub kinetic_e: f64,
     pub total_e: f64,
-    gravitational_e: f64,
-    diff_percentage: f64,
+    pub gravitational_e: f64,
+    pub diff_percentage: f64,
 }
 
 // contains calculations not necessary for the iteration process, only for displaying
 impl Analysis {
     fn analyze(&self, app: &Orbital) -> Analysis {
         let (kinetic_mj, grav_potential_mj, total) = self.current_e(app);
-        let diff_percentage = if self.initial_e != 0. {
-            (total / self.initial_e - 1.) * 100.
-        } else {
-            0.
-        };
+        let diff_percentage = self.diff_percentage(total);
 
         Analysis {
             kinetic_e: kinetic_mj,
@@ -508,9 +1380,22 @@ impl Analysis {
         }
     }
 
+    // change in total energy since the start, %
+    fn diff_percentage(&self, total: f64) -> f64 {
+        if self.initial_e != 0. {
+            (total / self.initial_e - 1.) * 100.
+        } else {
+            0.
+        }
+    }
+
     fn current_e(&self, app: &Orbital) -> (f64, f64, f64) {
         let (_, body_groups) = app.sibling_groups();
+        Self::energy(&body_groups, app.softening)
+    }
 
+    // kinetic, potential and total energy of the bodies in each sibling group, relative to their parent
+    fn energy(body_groups: &[Vec<&Body>], softening: f32) -> (f64, f64, f64) {
         let (total_kinetic, total_potential) = body_groups
             .iter()
             .map(|group_bodies| {
@@ -522,8 +1407,9 @@ impl Analysis {
 
                         let body_gravitational_mj =
                             group_bodies[i + 1..].iter().fold(0., |acc, b2| {
-                                let grav_potential_mj =
-                                    gravitational_potential_energy(b.mass, b2.mass, b.pos, b2.pos);
+                                let grav_potential_mj = softened_potential_energy(
+                                    b.mass, b2.mass, b.pos, b2.pos, softening,
+                                );
 
                                 acc + grav_potential_mj
                             });
@@ -0,0 +1,208 @@
+// Massless test particles, e.g. spacecraft or ring particles.
+// They feel the gravity of the bodies but don't exert any, so they aren't sources in the
+// acceleration calculation and aren't part of the hierarchy.
+// Stored as separate arrays (struct of arrays) without trajectories so that tens of thousands
+// can be updated in bulk.
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::core::{
+    parallel,
+    physics::{
+        gravitational_acceleration, symplectic_euler_calc, verlet_position, verlet_velocity,
+        Acceleration, Integrator, Position, Velocity,
+    },
+};
+
+// fewest particles worth handing to another thread
+const PARTICLES_PER_THREAD: usize = 512;
+
+// a massive body that particles are attracted to, or collide with
+#[derive(Clone, Copy, Debug)]
+pub struct Source {
+    pub pos: Position,
+    pub mass: f32,
+    pub radius: f32,
+}
+
+fn acceleration_from(sources: &[Source], pos: Position) -> Acceleration {
+    sources.iter().fold(Acceleration::default(), |acc, source| {
+        acc.add(gravitational_acceleration(source.pos, pos, source.mass))
+    })
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TestParticles {
+    pub x: Vec<f32>,
+    pub y: Vec<f32>,
+    pub vx: Vec<f32>,
+    pub vy: Vec<f32>,
+    pub color: Vec<(u8, u8, u8)>,
+    // false after colliding with a body or being removed
+    pub active: Vec<bool>,
+    // state when added, for resetting
+    initial: Vec<(Position, Velocity)>,
+}
+
+impl TestParticles {
+    pub fn add(&mut self, pos: Position, vel: Velocity, color: (u8, u8, u8)) {
+        self.x.push(pos.x);
+        self.y.push(pos.y);
+        self.vx.push(vel.x);
+        self.vy.push(vel.y);
+        self.color.push(color);
+        self.active.push(true);
+        self.initial.push((pos, vel));
+    }
+
+    pub fn extend(&mut self, other: &TestParticles) {
+        for i in 0..other.len() {
+            self.add(other.position(i), other.velocity(i), other.color[i]);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    pub fn num_active(&self) -> usize {
+        self.active.iter().filter(|&&a| a).count()
+    }
+
+    // every array has one entry per particle, which a file that was edited might not have
+    pub fn check(&self) -> Result<(), String> {
+        let lengths = [
+            self.y.len(),
+            self.vx.len(),
+            self.vy.len(),
+            self.color.len(),
+            self.active.len(),
+            self.initial.len(),
+        ];
+        if lengths.iter().any(|&len| len != self.len()) {
+            return Err(format!(
+                "particle arrays have different lengths: {} and {:?}",
+                self.len(),
+                lengths
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn position(&self, i: usize) -> Position {
+        Position::new(self.x[i], self.y[i])
+    }
+
+    pub fn velocity(&self, i: usize) -> Velocity {
+        Velocity::new(self.vx[i], self.vy[i])
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    // back to the state when each particle was added
+    pub fn reset(&mut self) {
+        let initial = std::mem::take(&mut self.initial);
+        let colors = std::mem::take(&mut self.color);
+        self.clear();
+        for ((pos, vel), color) in initial.into_iter().zip(colors) {
+            self.add(pos, vel, color);
+        }
+    }
+
+    // Advance every active particle by one tick, from the positions of the sources at the start of the tick
+    // to their positions at the end, which only velocity Verlet uses.
+    // Particles that end up inside a source (at the start of the tick) are deactivated.
+    pub fn step(
+        &mut self,
+        sources: &[Source],
+        next_sources: &[Source],
+        dt: f32,
+        integrator: Integrator,
+        parallel: bool,
+    ) {
+        // particles don't affect each other, so they're calculated separately and then stored
+        let next = parallel::map_indices(self.len(), parallel, PARTICLES_PER_THREAD, |i| {
+            if !self.active[i] {
+                return None;
+            }
+            let (pos, vel) = (self.position(i), self.velocity(i));
+            let acceleration = acceleration_from(sources, pos);
+
+            let (next_r, next_v) = match integrator {
+                Integrator::SymplecticEuler => symplectic_euler_calc(pos, vel, acceleration, dt),
+                Integrator::VelocityVerlet => {
+                    let next_r = verlet_position(pos, vel, acceleration, dt);
+                    let next_a = acceleration_from(next_sources, next_r);
+                    (next_r, verlet_velocity(vel, acceleration, next_a, dt))
+                }
+            };
+            let inside = sources
+                .iter()
+                .any(|source| next_r.abs_diff(source.pos) <= source.radius);
+            Some((next_r, next_v, inside))
+        });
+
+        for (i, next) in next.into_iter().enumerate() {
+            let Some((next_r, next_v, inside)) = next else {
+                continue;
+            };
+            self.x[i] = next_r.x;
+            self.y[i] = next_r.y;
+            self.vx[i] = next_v.x;
+            self.vy[i] = next_v.y;
+            if inside {
+                self.active[i] = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::core::physics::circular_velocity;
+
+    use super::*;
+
+    #[test]
+    fn circular_orbit_and_collision() {
+        let earth = Source {
+            pos: Position::new(0., 0.),
+            mass: 5.97e24,
+            radius: 6378.,
+        };
+        let mut particles = TestParticles::default();
+        let orbiting = Position::new(7000., 0.);
+        particles.add(
+            orbiting,
+            circular_velocity(earth.pos, earth.mass, orbiting),
+            (255, 255, 255),
+        );
+        particles.add(
+            Position::new(6400., 0.),
+            Velocity::default(),
+            (255, 255, 255),
+        );
+
+        let mut verlet = particles.clone();
+        for _ in 0..1000 {
+            particles.step(&[earth], &[earth], 1., Integrator::SymplecticEuler, false);
+            verlet.step(&[earth], &[earth], 1., Integrator::VelocityVerlet, false);
+        }
+
+        assert!((particles.position(0).mag() - 7000.).abs() < 10.);
+        assert_eq!(particles.active, vec![true, false]);
+        // second order, so much closer to the circle
+        assert!((verlet.position(0).mag() - 7000.).abs() < 1.);
+        assert_eq!(verlet.active, vec![true, false]);
+
+        particles.reset();
+        assert_eq!(particles.position(0), orbiting);
+        assert_eq!(particles.num_active(), 2);
+    }
+}
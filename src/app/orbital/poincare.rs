@@ -0,0 +1,196 @@
+// Poincaré surface of section.
+// Every time the body crosses the section line in the chosen direction, its state is recorded.
+// Plotting two coordinates of those states shows the structure of phase space:
+// a regular orbit lands on smooth curves, a chaotic orbit scatters points over an area.
+
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use crate::app::core::physics::{Axis, Position, Velocity};
+
+use super::{
+    body::Body,
+    events::{body_at, find_crossing},
+    frame::RotatingFrame,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SectionFrame {
+    Inertial,
+    // rotating with a pair of bodies, see RotatingFrame
+    Rotating { body1: usize, body2: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coordinate {
+    X,
+    Y,
+    Vx,
+    Vy,
+}
+
+impl Coordinate {
+    pub const ALL: [Coordinate; 4] = [Coordinate::X, Coordinate::Y, Coordinate::Vx, Coordinate::Vy];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Coordinate::X => "x (km)",
+            Coordinate::Y => "y (km)",
+            Coordinate::Vx => "vx (km/s)",
+            Coordinate::Vy => "vy (km/s)",
+        }
+    }
+}
+
+// state of the body in the section frame at the moment of crossing
+#[derive(Clone, Copy, Debug)]
+pub struct SectionPoint {
+    pub t: f32,
+    pub pos: Position,
+    pub vel: Velocity,
+}
+
+impl SectionPoint {
+    pub fn get(&self, coordinate: Coordinate) -> f32 {
+        match coordinate {
+            Coordinate::X => self.pos.x,
+            Coordinate::Y => self.pos.y,
+            Coordinate::Vx => self.vel.x,
+            Coordinate::Vy => self.vel.y,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PoincareSection {
+    pub enabled: bool,
+    pub body: usize,
+    pub frame: SectionFrame,
+    // the body crosses this axis, e.g. X is the line y = 0
+    pub axis: Axis,
+    // crossing in the positive direction of the other axis, e.g. with vy > 0 for the x axis
+    pub positive: bool,
+    // horizontal and vertical coordinates of the plot
+    pub plot: (Coordinate, Coordinate),
+    pub points: Vec<SectionPoint>,
+}
+
+impl Default for PoincareSection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            body: 1,
+            frame: SectionFrame::Inertial,
+            axis: Axis::X,
+            positive: true,
+            plot: (Coordinate::X, Coordinate::Vx),
+            points: vec![],
+        }
+    }
+}
+
+impl PoincareSection {
+    // Change every body index, for when a body is inserted or removed.
+    // The section is turned off if one of its bodies was removed (remap returns None),
+    // and returned as it was without its points.
+    pub fn remap_indices(&mut self, remap: impl Fn(usize) -> Option<usize>) -> Option<Self> {
+        let original = Self {
+            points: vec![],
+            ..self.clone()
+        };
+        let mut valid = true;
+        let mut apply = |i: &mut usize| match remap(*i) {
+            Some(new) => *i = new,
+            None => valid = false,
+        };
+        apply(&mut self.body);
+        if let SectionFrame::Rotating { body1, body2 } = &mut self.frame {
+            apply(body1);
+            apply(body2);
+        }
+        if valid {
+            return None;
+        }
+        *self = Self::default();
+        Some(original)
+    }
+
+    // distance from the section line, positive on the side that the crossing goes towards
+    fn value(&self, pos: Position) -> f32 {
+        match self.axis {
+            Axis::X => pos.y,
+            Axis::Y => pos.x,
+        }
+    }
+
+    // state of the body in the section frame, at a fraction of the way through the last tick
+    fn state_at(
+        &self,
+        hierarchy: &DiGraph<Body, ()>,
+        dt: f32,
+        fraction: f32,
+    ) -> Option<(Position, Velocity)> {
+        let body_at_fraction = |i: usize| {
+            let body = hierarchy.node_weight(NodeIndex::new(i))?;
+            body_at(body, dt, fraction)
+        };
+        let body = body_at_fraction(self.body)?;
+
+        match self.frame {
+            SectionFrame::Inertial => Some((body.absolute_pos, body.absolute_vel)),
+            SectionFrame::Rotating { body1, body2 } if body1 != body2 => {
+                let frame =
+                    RotatingFrame::new(&body_at_fraction(body1)?, &body_at_fraction(body2)?);
+                Some((
+                    frame.position(body.absolute_pos),
+                    frame.velocity(body.absolute_pos, body.absolute_vel),
+                ))
+            }
+            SectionFrame::Rotating { .. } => None,
+        }
+    }
+
+    // Check for a crossing during the last tick, which went from t_start to t_start + dt.
+    // Returns true if a point was recorded.
+    pub fn detect(&mut self, hierarchy: &DiGraph<Body, ()>, t_start: f32, dt: f32) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let (Some((start_pos, _)), Some((end_pos, _))) = (
+            self.state_at(hierarchy, dt, 0.),
+            self.state_at(hierarchy, dt, 1.),
+        ) else {
+            return false;
+        };
+
+        let sign = if self.positive { 1. } else { -1. };
+        let (start_value, end_value) = (sign * self.value(start_pos), sign * self.value(end_pos));
+        if !(start_value < 0. && end_value >= 0.) {
+            return false;
+        }
+
+        let value_at = |fraction: f32| {
+            self.state_at(hierarchy, dt, fraction)
+                .map(|(pos, _)| sign * self.value(pos))
+                .unwrap_or(0.)
+        };
+        let fraction = find_crossing(value_at, start_value);
+        let Some((pos, vel)) = self.state_at(hierarchy, dt, fraction) else {
+            return false;
+        };
+
+        self.points.push(SectionPoint {
+            t: t_start + fraction * dt,
+            pos,
+            vel,
+        });
+        true
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("t,x,y,vx,vy\n");
+        for p in self.points.iter() {
+            csv += &format!("{},{},{},{},{}\n", p.t, p.pos.x, p.pos.y, p.vel.x, p.vel.y);
+        }
+        csv
+    }
+}
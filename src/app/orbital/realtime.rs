@@ -0,0 +1,184 @@
+// Real-time playback: the simulation runs continuously at a time warp (simulated seconds per wall-clock second).
+// The wall-clock time between frames is added up and run in whole ticks of dt, with the remainder
+// carried to the next frame, so the simulation speed doesn't depend on the frame rate.
+// The ticks only get a few ms of each frame, so a high warp or slow ticks slow the playback down
+// instead of the window.
+
+use std::time::Duration;
+
+use web_time::Instant;
+
+// a slow frame (or the window being hidden) doesn't make up the time it missed
+const MAX_ELAPSED: f32 = 0.25;
+// ticks due in one frame at most
+const MAX_TICKS_PER_FRAME: usize = 20_000;
+// wall-clock time spent on ticks in one frame, leaving the rest of a 60 Hz frame for drawing
+pub const FRAME_BUDGET: Duration = Duration::from_millis(8);
+
+#[derive(Clone, Debug, Default)]
+pub struct RealTime {
+    pub playing: bool,
+    // simulated s per wall-clock s, 0 until chosen
+    pub warp: f32,
+    // simulated time that's due but not run yet, less than a tick
+    accumulator: f32,
+}
+
+impl RealTime {
+    pub fn play(&mut self) {
+        self.playing = true;
+        self.accumulator = 0.;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+        self.accumulator = 0.;
+    }
+
+    // Number of ticks of dt that are due after `elapsed` wall-clock seconds.
+    pub fn ticks_due(&mut self, elapsed: f32, dt: f32) -> usize {
+        if !self.playing || dt <= 0. {
+            return 0;
+        }
+        self.accumulator += elapsed.clamp(0., MAX_ELAPSED) * self.warp.max(0.);
+        let ticks = (self.accumulator / dt).floor();
+        if ticks >= MAX_TICKS_PER_FRAME as f32 {
+            // drop what can't be run this frame rather than build up a backlog
+            self.accumulator = 0.;
+            return MAX_TICKS_PER_FRAME;
+        }
+        self.accumulator -= ticks * dt;
+        ticks as usize
+    }
+
+    // Ticks that were due but not run are due again next frame,
+    // up to what one slow frame adds so a backlog can't build up.
+    fn carry(&mut self, ticks: usize, dt: f32) {
+        self.accumulator = (self.accumulator + ticks as f32 * dt).min(MAX_ELAPSED * self.warp);
+    }
+
+    // Runs the ticks due after `elapsed` wall-clock seconds with `run_ticks`, a chunk at a time,
+    // until they're done or `budget` has passed. `run_ticks` returns false once the simulation stops.
+    // Returns the number of ticks run, the rest are carried to the next frame.
+    pub fn run_due(
+        &mut self,
+        elapsed: f32,
+        dt: f32,
+        budget: Duration,
+        run_ticks: impl FnMut(usize) -> bool,
+    ) -> usize {
+        let started = Instant::now();
+        self.run_due_by(elapsed, dt, budget, run_ticks, || started.elapsed())
+    }
+
+    // run_due with the time spent so far from `spent`, so tests don't depend on the wall clock
+    fn run_due_by(
+        &mut self,
+        elapsed: f32,
+        dt: f32,
+        budget: Duration,
+        mut run_ticks: impl FnMut(usize) -> bool,
+        spent: impl Fn() -> Duration,
+    ) -> usize {
+        let due = self.ticks_due(elapsed, dt);
+        let mut done = 0;
+        // one tick to measure how long they take, then as many as fit in half the time left
+        let mut chunk = 1;
+        while done < due {
+            let ticks = chunk.min(due - done);
+            let running = run_ticks(ticks);
+            done += ticks;
+
+            let spent = spent();
+            if !running || spent >= budget {
+                break;
+            }
+            let per_tick = spent.as_nanos() / done as u128;
+            let fits = (budget - spent).as_nanos() / per_tick.max(1);
+            chunk = (fits / 2).try_into().unwrap_or(usize::MAX).max(1);
+        }
+        self.carry(due - done, dt);
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn runs_whole_ticks_independent_of_frame_rate() {
+        let run = |frames: usize, frame_time: f32| {
+            let mut realtime = RealTime {
+                warp: 100.,
+                ..Default::default()
+            };
+            realtime.play();
+            (0..frames)
+                .map(|_| realtime.ticks_due(frame_time, 3.))
+                .sum::<usize>()
+        };
+        // one wall-clock second at 100x is 100 s, 33 ticks of 3 s
+        assert_eq!(run(60, 1. / 60.), 33);
+        assert_eq!(run(144, 1. / 144.), 33);
+        assert_eq!(run(10, 0.1), 33);
+    }
+
+    #[test]
+    fn limits_slow_frames() {
+        let mut realtime = RealTime {
+            warp: 1.,
+            ..Default::default()
+        };
+        assert_eq!(realtime.ticks_due(1., 1. / 64.), 0);
+        realtime.play();
+        // a 10 s pause only counts as MAX_ELAPSED
+        assert_eq!(realtime.ticks_due(10., 1. / 64.), 16);
+
+        realtime.warp = 1e9;
+        assert_eq!(realtime.ticks_due(0.1, 1.), MAX_TICKS_PER_FRAME);
+        assert_eq!(realtime.ticks_due(0., 1.), 0);
+    }
+
+    #[test]
+    fn stops_at_frame_budget_when_ticks_are_slow() {
+        let mut realtime = RealTime {
+            warp: 1000.,
+            ..Default::default()
+        };
+        realtime.play();
+        // each tick takes 2 ms of a fake clock
+        let clock = Cell::new(Duration::ZERO);
+        let slow_ticks = |ticks: usize| {
+            clock.set(clock.get() + Duration::from_millis(2) * ticks as u32);
+            true
+        };
+        let budget = Duration::from_millis(10);
+
+        // 0.1 s at 1000x is 100 ticks, 200 ms of work
+        // chunks of 1, 2, 1 and 1 ticks, the last ones only fit a tick in half the time left
+        let done = realtime.run_due_by(0.1, 1., budget, slow_ticks, || clock.get());
+        assert_eq!(done, 5);
+        assert_eq!(clock.get(), budget);
+
+        // the ticks that didn't fit are run next frame
+        let next = realtime.run_due_by(0., 1., budget, |_| true, || Duration::ZERO);
+        assert_eq!(next, 95);
+
+        // stops when the simulation does
+        let mut ran = 0;
+        let done = realtime.run_due_by(
+            0.1,
+            1.,
+            budget,
+            |ticks| {
+                ran += ticks;
+                false
+            },
+            || Duration::ZERO,
+        );
+        assert_eq!((done, ran), (1, 1));
+    }
+}
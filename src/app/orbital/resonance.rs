@@ -0,0 +1,375 @@
+// Mean-motion resonances between bodies orbiting the same primary.
+// Two bodies are near resonance when the ratio of their periods is close to p:q for small integers.
+// The resonant angle combines their mean longitudes so that it changes slowly near resonance:
+// in a real resonance it librates (oscillates around a center), otherwise it circulates through 360 degrees.
+// A three body (Laplace) resonance is a combination of two neighbouring two body resonances
+// where the slow parts cancel out, e.g. Io - 3 Europa + 2 Ganymede.
+
+use std::{collections::VecDeque, f64::consts::TAU};
+
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use crate::app::core::physics::{
+    gravitational_parameter, normalize_angle, orbital_elements, wrap_angle, OrbitalElements,
+};
+
+use super::body::Body;
+
+// largest q to check in p:q
+const MAX_DENOMINATOR: u32 = 10;
+// minimum number of samples before deciding between libration and circulation
+const MIN_SAMPLES: usize = 20;
+// Samples kept per resonance. Libration is decided on the latest ones, so long runs don't keep
+// every sample, and a resonance that breaks up shows as circulating after a while.
+const MAX_SAMPLES: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Libration {
+    Undetermined,
+    // degrees
+    Librating { center: f64, amplitude: f64 },
+    Circulating,
+}
+
+#[derive(Clone, Debug)]
+pub struct Resonance {
+    // node indices, from the innermost body to the outermost
+    pub bodies: Vec<usize>,
+    pub names: Vec<String>,
+    // two body resonance: outer period / inner period ~ p / q
+    pub ratio: Option<(u32, u32)>,
+    pub period_ratio: f64,
+    // latest osculating elements of the bodies
+    pub elements: Vec<OrbitalElements>,
+    // resonant angle = sum of longitude_coefficients[i] * mean longitude of bodies[i]
+    //                  + periapsis_coefficient * longitude of periapsis of the inner body
+    longitude_coefficients: Vec<i32>,
+    periapsis_coefficient: i32,
+    // time and unwrapped resonant angle in radians, the latest MAX_SAMPLES
+    angles: VecDeque<(f32, f64)>,
+}
+
+impl Resonance {
+    // 2:1 between Io and Europa: 2 λ_Europa - λ_Io - ϖ_Io
+    fn two_body(inner: &Satellite, outer: &Satellite, p: u32, q: u32) -> Self {
+        Self {
+            bodies: vec![inner.index, outer.index],
+            names: vec![inner.name.clone(), outer.name.clone()],
+            ratio: Some((p, q)),
+            period_ratio: outer.elements.period / inner.elements.period,
+            elements: vec![inner.elements, outer.elements],
+            longitude_coefficients: vec![-(q as i32), p as i32],
+            periapsis_coefficient: -((p - q) as i32),
+            angles: VecDeque::new(),
+        }
+    }
+
+    // Combine inner:middle ~ p1:q1 and middle:outer ~ p2:q2 so the slow terms cancel.
+    // a (q1 λ1 - p1 λ2) + b (q2 λ2 - p2 λ3) with a (q1 - p1) + b (q2 - p2) = 0
+    fn three_body(satellites: [&Satellite; 3], (p1, q1): (u32, u32), (p2, q2): (u32, u32)) -> Self {
+        let (p1, q1, p2, q2) = (p1 as i32, q1 as i32, p2 as i32, q2 as i32);
+        let (a, b) = (p2 - q2, -(p1 - q1));
+        let coefficients = [a * q1, -a * p1 + b * q2, -b * p2];
+        let divisor = coefficients.iter().fold(0, |acc, &c| gcd(acc, c.abs()));
+
+        Self {
+            bodies: satellites.iter().map(|s| s.index).collect(),
+            names: satellites.iter().map(|s| s.name.clone()).collect(),
+            ratio: None,
+            period_ratio: 0.,
+            elements: satellites.iter().map(|s| s.elements).collect(),
+            longitude_coefficients: coefficients.iter().map(|c| c / divisor).collect(),
+            periapsis_coefficient: 0,
+            angles: VecDeque::new(),
+        }
+    }
+
+    fn is_same(&self, other: &Resonance) -> bool {
+        self.bodies == other.bodies && self.longitude_coefficients == other.longitude_coefficients
+    }
+
+    pub fn label(&self) -> String {
+        match self.ratio {
+            Some((p, q)) => format!("{}:{}", p, q),
+            None => {
+                let terms: Vec<String> = self
+                    .longitude_coefficients
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect();
+                format!("Laplace ({})", terms.join(", "))
+            }
+        }
+    }
+
+    fn angle(&self, elements: &[OrbitalElements]) -> f64 {
+        let longitudes = self
+            .longitude_coefficients
+            .iter()
+            .zip(elements)
+            .fold(0., |acc, (&c, e)| acc + c as f64 * e.mean_longitude);
+
+        longitudes + self.periapsis_coefficient as f64 * elements[0].longitude_of_periapsis
+    }
+
+    fn record(&mut self, t: f32, angle: f64) {
+        // unwrap so that the angle is continuous and circulation shows up as a growing range
+        let unwrapped = match self.angles.back() {
+            Some(&(_, prev)) => prev + wrap_angle(angle - prev),
+            None => wrap_angle(angle),
+        };
+        if self.angles.len() == MAX_SAMPLES {
+            self.angles.pop_front();
+        }
+        self.angles.push_back((t, unwrapped));
+    }
+
+    // current resonant angle in degrees
+    pub fn current_angle(&self) -> Option<f64> {
+        self.angles
+            .back()
+            .map(|&(_, angle)| normalize_angle(angle).to_degrees())
+    }
+
+    pub fn libration(&self) -> Libration {
+        if self.angles.len() < MIN_SAMPLES {
+            return Libration::Undetermined;
+        }
+        let (min, max) = self
+            .angles
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(min, max), &(_, a)| {
+                (min.min(a), max.max(a))
+            });
+
+        if max - min >= TAU {
+            Libration::Circulating
+        } else {
+            Libration::Librating {
+                center: normalize_angle((max + min) / 2.).to_degrees(),
+                amplitude: ((max - min) / 2.).to_degrees(),
+            }
+        }
+    }
+}
+
+// a body orbiting a more massive sibling
+struct Satellite {
+    index: usize,
+    name: String,
+    primary: usize,
+    elements: OrbitalElements,
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// The simplest p:q (smallest p + q) within the tolerance of the period ratio,
+// with p > q and order p - q up to max_order.
+fn find_ratio(period_ratio: f64, max_order: u32, tolerance: f64) -> Option<(u32, u32)> {
+    (1..=MAX_DENOMINATOR)
+        .flat_map(|q| (q + 1..=q + max_order).map(move |p| (p, q)))
+        .filter(|&(p, q)| {
+            let ratio = p as f64 / q as f64;
+            ((period_ratio - ratio) / ratio).abs() <= tolerance
+        })
+        .min_by_key(|&(p, q)| p + q)
+}
+
+// Bodies in each sibling group orbit the most massive body in the group.
+fn satellites(hierarchy: &DiGraph<Body, ()>, groups: &[Vec<NodeIndex>]) -> Vec<Satellite> {
+    let mut satellites = vec![];
+
+    for group in groups.iter() {
+        let Some(&primary_idx) = group
+            .iter()
+            .max_by(|&&a, &&b| hierarchy[a].mass.total_cmp(&hierarchy[b].mass))
+        else {
+            continue;
+        };
+        let primary = &hierarchy[primary_idx];
+
+        for &idx in group.iter().filter(|&&i| i != primary_idx) {
+            let body = &hierarchy[idx];
+            let mu = gravitational_parameter(primary.mass + body.mass);
+            let pos = body.absolute_pos.minus(primary.absolute_pos);
+            let vel = body.absolute_vel.minus(primary.absolute_vel);
+
+            if let Some(elements) = orbital_elements(mu, pos, vel) {
+                satellites.push(Satellite {
+                    index: idx.index(),
+                    name: body.name.clone(),
+                    primary: primary_idx.index(),
+                    elements,
+                });
+            }
+        }
+    }
+    satellites
+}
+
+#[derive(Clone)]
+pub struct Resonances {
+    // largest p - q to detect
+    pub max_order: u32,
+    // relative difference between the period ratio and p/q
+    pub tolerance: f64,
+    // every resonance detected so far, with the history of its resonant angle
+    pub tracked: Vec<Resonance>,
+    last_sample_t: Option<f32>,
+}
+
+impl Default for Resonances {
+    fn default() -> Self {
+        Self {
+            max_order: 2,
+            tolerance: 0.02,
+            tracked: vec![],
+            last_sample_t: None,
+        }
+    }
+}
+
+impl Resonances {
+    pub fn clear(&mut self) {
+        self.tracked.clear();
+        self.last_sample_t = None;
+    }
+
+    // drop the samples after t, when going back in time
+    pub fn truncate(&mut self, t: f32) {
+        for resonance in self.tracked.iter_mut() {
+            resonance.angles.retain(|&(sample_t, _)| sample_t <= t);
+        }
+        self.last_sample_t = self.last_sample_t.filter(|&last_t| last_t <= t);
+    }
+
+    pub fn is_due(&self, t: f32, interval: f32) -> bool {
+        match self.last_sample_t {
+            Some(last_t) => t - last_t >= interval,
+            None => true,
+        }
+    }
+
+    // Detect resonances from the current osculating periods,
+    // then record the resonant angle of every tracked resonance.
+    pub fn sample(&mut self, hierarchy: &DiGraph<Body, ()>, groups: &[Vec<NodeIndex>], t: f32) {
+        self.last_sample_t = Some(t);
+
+        let mut satellites = satellites(hierarchy, groups);
+        satellites.sort_by(|a, b| {
+            a.primary
+                .cmp(&b.primary)
+                .then(a.elements.period.total_cmp(&b.elements.period))
+        });
+
+        let mut detected = vec![];
+        let mut pair_ratios = vec![];
+        for (i, inner) in satellites.iter().enumerate() {
+            for outer in satellites[i + 1..].iter() {
+                if outer.primary != inner.primary {
+                    break;
+                }
+                let period_ratio = outer.elements.period / inner.elements.period;
+                if let Some((p, q)) = find_ratio(period_ratio, self.max_order, self.tolerance) {
+                    detected.push(Resonance::two_body(inner, outer, p, q));
+                    pair_ratios.push(((inner.index, outer.index), (p, q)));
+                }
+            }
+        }
+
+        // chains of neighbouring resonances
+        for ((inner, middle), ratio1) in pair_ratios.iter() {
+            for ((_, outer), ratio2) in pair_ratios.iter().filter(|((m, _), _)| m == middle) {
+                let find = |index: usize| satellites.iter().find(|s| s.index == index);
+                if let (Some(s1), Some(s2), Some(s3)) = (find(*inner), find(*middle), find(*outer))
+                {
+                    detected.push(Resonance::three_body([s1, s2, s3], *ratio1, *ratio2));
+                }
+            }
+        }
+
+        for resonance in detected {
+            match self.tracked.iter_mut().find(|r| r.is_same(&resonance)) {
+                Some(tracked) => tracked.period_ratio = resonance.period_ratio,
+                None => self.tracked.push(resonance),
+            }
+        }
+
+        for resonance in self.tracked.iter_mut() {
+            let elements: Option<Vec<OrbitalElements>> = resonance
+                .bodies
+                .iter()
+                .map(|&i| satellites.iter().find(|s| s.index == i).map(|s| s.elements))
+                .collect();
+
+            if let Some(elements) = elements {
+                let angle = resonance.angle(&elements);
+                resonance.record(t, angle);
+                resonance.elements = elements;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::core::physics::{Position, Velocity};
+
+    use super::*;
+
+    #[test]
+    fn simplest_ratio() {
+        assert_eq!(find_ratio(2.007, 2, 0.01), Some((2, 1)));
+        assert_eq!(find_ratio(1.49, 2, 0.01), Some((3, 2)));
+        assert_eq!(find_ratio(3.02, 1, 0.01), None);
+        assert_eq!(find_ratio(3.02, 2, 0.01), Some((3, 1)));
+    }
+
+    #[test]
+    fn laplace_coefficients() {
+        let satellite = |index: usize| Satellite {
+            index,
+            name: String::new(),
+            primary: 0,
+            elements: orbital_elements(1., Position::new(1., 0.), Velocity::new(0., 1.)).unwrap(),
+        };
+        let (io, europa, ganymede) = (satellite(1), satellite(2), satellite(3));
+
+        let laplace = Resonance::three_body([&io, &europa, &ganymede], (2, 1), (2, 1));
+
+        assert_eq!(laplace.longitude_coefficients, vec![1, -3, 2]);
+    }
+
+    #[test]
+    fn keeps_latest_samples() {
+        let satellite = |index: usize| Satellite {
+            index,
+            name: String::new(),
+            primary: 0,
+            elements: orbital_elements(1., Position::new(1., 0.), Velocity::new(0., 1.)).unwrap(),
+        };
+        let mut resonance = Resonance::two_body(&satellite(1), &satellite(2), 2, 1);
+
+        // circulating at first, then librating around 0 for the last MAX_SAMPLES
+        for i in 0..MAX_SAMPLES {
+            resonance.record(i as f32, i as f64 * 0.1);
+        }
+        assert_eq!(resonance.libration(), Libration::Circulating);
+        let start = resonance.angles.back().unwrap().1;
+        for i in 0..MAX_SAMPLES {
+            let t = (MAX_SAMPLES + i) as f32;
+            resonance.record(t, start + 0.5 * (i as f64 * 0.3).sin());
+        }
+        assert_eq!(resonance.angles.len(), MAX_SAMPLES);
+        assert!(matches!(
+            resonance.libration(),
+            Libration::Librating { amplitude, .. } if (amplitude - 0.5f64.to_degrees()).abs() < 1.
+        ));
+    }
+}
@@ -0,0 +1,622 @@
+// Scenario files: presets written in TOML so they can be changed without recompiling.
+// The presets that come with the app are embedded at build time, and more can be loaded from
+// a directory at runtime. A file there with the same name as an embedded one replaces it.
+//
+// name = "Three body"
+// dt = 1                    # s, default 1
+// ticks_per_press = 1000    # default 1000
+// draw_frequency = 100      # s between graphed points, default 100
+// distance_per_px = 300     # km, default 150
+// balance = "Jupiter"       # optional, sets this body's velocity so the total momentum is zero
+//
+// [[bodies]]
+// name = "1"                # unique, other fields refer to bodies by name
+// mass = 1e21               # kg
+// radius = 1000             # km, default 0
+// position = [-5000, -5000] # km
+// velocity = [0, 0]         # km/s, added to the orbit velocity if there is one
+// orbit = { around = "2" }  # circular velocity around another body, plus that body's velocity
+//                           # binary = true: both bodies circle their barycenter
+//                           # massless = true: ignore this body's own mass
+// lock = "circular"         # or "escape", keeps the velocity locked while setting up
+// lock_to = "2"             # body name, or index in the hierarchy for barycenters
+// fixed = false
+// color = [0, 255, 0]
+// expanded = false          # open in the side panel
+//
+// [[rings]]                 # test particles, see RingSettings
+// parent = "Saturn"
+// inner_radius = 92000
+// outer_radius = 137000
+// count = 5000
+//
+// Instead of bodies, a generated scenario: [galaxy_collision] or [cluster], see ClusterSettings.
+
+use std::{fmt, ops::Range};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::app::core::physics::{circ_velocity_bodies, circular_velocity, Position, Velocity};
+
+use super::{
+    body::{Body, Preset},
+    generators::{ring, ClusterSettings, RingSettings},
+    particles::TestParticles,
+};
+
+// (file name, contents) in the same order as the presets in the UI
+const EMBEDDED: [(&str, &str); 10] = [
+    (
+        "earth_orbit.toml",
+        include_str!("../../../scenarios/earth_orbit.toml"),
+    ),
+    (
+        "sun_earth_moon.toml",
+        include_str!("../../../scenarios/sun_earth_moon.toml"),
+    ),
+    (
+        "earth_moon.toml",
+        include_str!("../../../scenarios/earth_moon.toml"),
+    ),
+    (
+        "three_body.toml",
+        include_str!("../../../scenarios/three_body.toml"),
+    ),
+    (
+        "equal_binary.toml",
+        include_str!("../../../scenarios/equal_binary.toml"),
+    ),
+    (
+        "unequal_binary.toml",
+        include_str!("../../../scenarios/unequal_binary.toml"),
+    ),
+    (
+        "laplace_resonance.toml",
+        include_str!("../../../scenarios/laplace_resonance.toml"),
+    ),
+    (
+        "saturn_rings.toml",
+        include_str!("../../../scenarios/saturn_rings.toml"),
+    ),
+    (
+        "galaxy_collision.toml",
+        include_str!("../../../scenarios/galaxy_collision.toml"),
+    ),
+    (
+        "star_cluster.toml",
+        include_str!("../../../scenarios/star_cluster.toml"),
+    ),
+];
+
+// directory that scenarios are loaded from at runtime, relative to the working directory
+pub const SCENARIO_DIR: &str = "scenarios";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioError {
+    pub file: String,
+    pub line: Option<usize>,
+    // e.g. bodies[2].lock_to
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, ": {}", field)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    name: String,
+    dt: Option<Spanned<f32>>,
+    ticks_per_press: Option<Spanned<i32>>,
+    draw_frequency: Option<u32>,
+    distance_per_px: Option<Spanned<i32>>,
+    balance: Option<Spanned<String>>,
+    #[serde(default)]
+    bodies: Vec<Spanned<BodySpec>>,
+    #[serde(default)]
+    rings: Vec<Spanned<RingSpec>>,
+    galaxy_collision: Option<Spanned<GalaxyCollisionSpec>>,
+    cluster: Option<Spanned<ClusterSettings>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BodySpec {
+    name: String,
+    mass: f32,
+    #[serde(default)]
+    radius: f32,
+    #[serde(default)]
+    position: [f32; 2],
+    #[serde(default)]
+    velocity: [f32; 2],
+    orbit: Option<OrbitSpec>,
+    lock: Option<Lock>,
+    lock_to: Option<BodyRef>,
+    #[serde(default)]
+    fixed: bool,
+    color: Option<[u8; 3]>,
+    #[serde(default)]
+    expanded: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OrbitSpec {
+    around: String,
+    #[serde(default)]
+    binary: bool,
+    #[serde(default)]
+    massless: bool,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Lock {
+    Circular,
+    Escape,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BodyRef {
+    Name(String),
+    Index(usize),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RingSpec {
+    parent: String,
+    inner_radius: f32,
+    outer_radius: f32,
+    count: usize,
+    #[serde(default)]
+    dispersion: f32,
+    seed: Option<u64>,
+    color: Option<[u8; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GalaxyCollisionSpec {
+    impact_parameter: f32,  // km
+    relative_velocity: f32, // km/s
+    particles_per_galaxy: Option<usize>,
+}
+
+// errors for one file, with the line found from the position in the file
+struct Context<'a> {
+    file: &'a str,
+    source: &'a str,
+}
+
+impl Context<'_> {
+    fn error(
+        &self,
+        span: Option<Range<usize>>,
+        field: Option<String>,
+        message: String,
+    ) -> ScenarioError {
+        let line = span.map(|span| {
+            let start = span.start.min(self.source.len());
+            self.source[..start].matches('\n').count() + 1
+        });
+        ScenarioError {
+            file: self.file.to_string(),
+            line,
+            field,
+            message,
+        }
+    }
+
+    // span is the table the field is in, the error points at the field's line if it's written out
+    fn field_error(
+        &self,
+        span: Range<usize>,
+        field: String,
+        message: impl ToString,
+    ) -> ScenarioError {
+        let key = field.rsplit(['.', ']']).next().unwrap_or_default();
+        let table = self.source.get(span.clone()).unwrap_or_default();
+        let mut offset = 0;
+        let mut start = span.start;
+        for line in table.split_inclusive('\n') {
+            let rest = line.trim_start().strip_prefix(key);
+            if !key.is_empty() && rest.is_some_and(|r| r.trim_start().starts_with('=')) {
+                start = span.start + offset;
+                break;
+            }
+            offset += line.len();
+        }
+        self.error(Some(start..span.end), Some(field), message.to_string())
+    }
+}
+
+pub fn parse(file: &str, source: &str) -> Result<Preset, ScenarioError> {
+    let context = Context { file, source };
+    let scenario: ScenarioFile = toml::from_str(source)
+        .map_err(|e| context.error(e.span(), None, e.message().to_string()))?;
+    build(&context, scenario)
+}
+
+fn build(context: &Context, scenario: ScenarioFile) -> Result<Preset, ScenarioError> {
+    let generators = [
+        !scenario.bodies.is_empty(),
+        scenario.galaxy_collision.is_some(),
+        scenario.cluster.is_some(),
+    ];
+    match generators.iter().filter(|&&g| g).count() {
+        0 => {
+            return Err(context.error(
+                None,
+                None,
+                "needs [[bodies]], [galaxy_collision] or [cluster]".to_string(),
+            ))
+        }
+        1 => {}
+        _ => {
+            return Err(context.error(
+                None,
+                None,
+                "only one of [[bodies]], [galaxy_collision] or [cluster] can be used".to_string(),
+            ))
+        }
+    }
+
+    let mut preset = if let Some(spec) = scenario.galaxy_collision {
+        let span = spec.span();
+        let spec = spec.into_inner();
+        if spec.relative_velocity < 0. {
+            return Err(context.field_error(
+                span,
+                "galaxy_collision.relative_velocity".to_string(),
+                "can't be negative",
+            ));
+        }
+        Preset::galaxy_collision(
+            spec.impact_parameter,
+            spec.relative_velocity,
+            spec.particles_per_galaxy.unwrap_or(3000),
+        )
+    } else if let Some(settings) = scenario.cluster {
+        let span = settings.span();
+        let settings = settings.into_inner();
+        if settings.count < 2 {
+            return Err(context.field_error(
+                span,
+                "cluster.count".to_string(),
+                "needs at least 2 stars",
+            ));
+        }
+        Preset::plummer_cluster(&settings)
+    } else {
+        let (bodies, particles) = build_bodies(context, &scenario)?;
+        Preset {
+            bodies,
+            particles,
+            dt: 1.,
+            ticks_per_press: 1000,
+            draw_frequency: 100,
+            distance_per_px: 150,
+            ..Default::default()
+        }
+    };
+    preset.name = scenario.name;
+
+    if let Some(dt) = scenario.dt {
+        if *dt.get_ref() <= 0. {
+            return Err(context.field_error(dt.span(), "dt".to_string(), "must be positive"));
+        }
+        preset.dt = dt.into_inner();
+    }
+    if let Some(ticks) = scenario.ticks_per_press {
+        if *ticks.get_ref() <= 0 {
+            return Err(context.field_error(
+                ticks.span(),
+                "ticks_per_press".to_string(),
+                "must be positive",
+            ));
+        }
+        preset.ticks_per_press = ticks.into_inner();
+    }
+    if let Some(draw_frequency) = scenario.draw_frequency {
+        preset.draw_frequency = draw_frequency;
+    }
+    if let Some(distance_per_px) = scenario.distance_per_px {
+        if *distance_per_px.get_ref() <= 0 {
+            return Err(context.field_error(
+                distance_per_px.span(),
+                "distance_per_px".to_string(),
+                "must be positive",
+            ));
+        }
+        preset.distance_per_px = distance_per_px.into_inner();
+    }
+    Ok(preset)
+}
+
+fn build_bodies(
+    context: &Context,
+    scenario: &ScenarioFile,
+) -> Result<(Vec<Body>, TestParticles), ScenarioError> {
+    let specs = &scenario.bodies;
+    let find = |name: &str| specs.iter().position(|s| s.get_ref().name == name);
+    // barycenters are numbered after the bodies, there's at most one less of them than bodies
+    let max_index = (2 * specs.len()).saturating_sub(1);
+
+    let mut bodies: Vec<Body> = Vec::with_capacity(specs.len());
+    for (i, spanned) in specs.iter().enumerate() {
+        let spec = spanned.get_ref();
+        let span = spanned.span();
+        let field = |name: &str| format!("bodies[{}].{}", i, name);
+
+        if find(&spec.name) != Some(i) {
+            return Err(context.field_error(
+                span,
+                field("name"),
+                format!("\"{}\" is used more than once", spec.name),
+            ));
+        }
+        if spec.mass <= 0. || !spec.mass.is_finite() {
+            return Err(context.field_error(span, field("mass"), "must be positive"));
+        }
+        if spec.radius < 0. {
+            return Err(context.field_error(span, field("radius"), "can't be negative"));
+        }
+
+        let selected_vel_lock = match &spec.lock_to {
+            None if spec.lock.is_some() => {
+                return Err(context.field_error(
+                    span,
+                    field("lock_to"),
+                    "lock needs a body to lock to",
+                ))
+            }
+            None => 0,
+            Some(BodyRef::Name(name)) => match find(name) {
+                Some(index) if index != i => index,
+                Some(_) => {
+                    return Err(context.field_error(span, field("lock_to"), "can't lock to itself"))
+                }
+                None => {
+                    return Err(context.field_error(
+                        span,
+                        field("lock_to"),
+                        format!("no body named \"{}\"", name),
+                    ))
+                }
+            },
+            Some(BodyRef::Index(index)) if *index < max_index && *index != i => *index,
+            Some(BodyRef::Index(index)) => {
+                return Err(context.field_error(
+                    span,
+                    field("lock_to"),
+                    format!("no other body or barycenter at index {}", index),
+                ))
+            }
+        };
+
+        let position = Position::new(spec.position[0], spec.position[1]);
+        let mut velocity = Velocity::new(spec.velocity[0], spec.velocity[1]);
+        if let Some(orbit) = &spec.orbit {
+            let center = match find(&orbit.around) {
+                Some(index) if index != i => index,
+                Some(_) => {
+                    return Err(context.field_error(
+                        span,
+                        field("orbit.around"),
+                        "can't orbit itself",
+                    ))
+                }
+                None => {
+                    return Err(context.field_error(
+                        span,
+                        field("orbit.around"),
+                        format!("no body named \"{}\"", orbit.around),
+                    ))
+                }
+            };
+            let center_spec = specs[center].get_ref();
+            let center_pos = Position::new(center_spec.position[0], center_spec.position[1]);
+            if center_pos == position {
+                return Err(context.field_error(
+                    span,
+                    field("orbit.around"),
+                    "is at the same position",
+                ));
+            }
+            let orbit_velocity = if orbit.binary {
+                let this = Body {
+                    mass: spec.mass,
+                    absolute_pos: position,
+                    ..Body::default()
+                };
+                let other = Body {
+                    mass: center_spec.mass,
+                    absolute_pos: center_pos,
+                    ..Body::default()
+                };
+                circ_velocity_bodies(&this, &other).0
+            } else {
+                let mass = if orbit.massless {
+                    center_spec.mass
+                } else {
+                    center_spec.mass + spec.mass
+                };
+                // the center's velocity so far, bodies earlier in the file are already done
+                let center_velocity = match bodies.get(center) {
+                    Some(b) => b.absolute_vel,
+                    None => Velocity::new(center_spec.velocity[0], center_spec.velocity[1]),
+                };
+                circular_velocity(center_pos, mass, position).add(center_velocity)
+            };
+            velocity = orbit_velocity.add(velocity);
+        }
+
+        bodies.push(Body {
+            name: spec.name.clone(),
+            mass: spec.mass,
+            radius: spec.radius,
+            absolute_pos: position,
+            absolute_vel: velocity,
+            is_fixed: spec.fixed,
+            lock_to_circular_velocity: matches!(spec.lock, Some(Lock::Circular)),
+            lock_to_escape_velocity: matches!(spec.lock, Some(Lock::Escape)),
+            selected_vel_lock,
+            color: spec
+                .color
+                .map(|[r, g, b]| (r, g, b))
+                .unwrap_or(Body::default().color),
+            default_expanded: spec.expanded,
+            ..Body::default()
+        });
+    }
+
+    if let Some(balance) = &scenario.balance {
+        let Some(index) = find(balance.get_ref()) else {
+            return Err(context.field_error(
+                balance.span(),
+                "balance".to_string(),
+                format!("no body named \"{}\"", balance.get_ref()),
+            ));
+        };
+        let momentum = bodies
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .fold(Velocity::default(), |acc, (_, b)| {
+                acc.add(b.absolute_vel.scale(b.mass))
+            });
+        bodies[index].absolute_vel = momentum.scale(-1. / bodies[index].mass);
+    }
+
+    let mut particles = TestParticles::default();
+    for (i, spanned) in scenario.rings.iter().enumerate() {
+        let spec = spanned.get_ref();
+        let span = spanned.span();
+        let field = |name: &str| format!("rings[{}].{}", i, name);
+        let Some(parent) = find(&spec.parent) else {
+            return Err(context.field_error(
+                span,
+                field("parent"),
+                format!("no body named \"{}\"", spec.parent),
+            ));
+        };
+        if spec.inner_radius < 0. || spec.inner_radius >= spec.outer_radius {
+            return Err(context.field_error(
+                span,
+                field("outer_radius"),
+                "must be larger than inner_radius, which can't be negative",
+            ));
+        }
+        let default = RingSettings::default();
+        let settings = RingSettings {
+            inner_radius: spec.inner_radius,
+            outer_radius: spec.outer_radius,
+            count: spec.count,
+            dispersion: spec.dispersion,
+            seed: spec.seed.unwrap_or(default.seed),
+            color: spec
+                .color
+                .map(|[r, g, b]| (r, g, b))
+                .unwrap_or(default.color),
+        };
+        particles.extend(&ring(&bodies[parent], &settings));
+    }
+
+    Ok((bodies, particles))
+}
+
+// presets that are built into the app
+pub fn embedded() -> Vec<Preset> {
+    EMBEDDED
+        .iter()
+        .map(|(file, source)| {
+            parse(file, source).unwrap_or_else(|e| panic!("embedded scenario: {}", e))
+        })
+        .collect()
+}
+
+// Embedded presets, replaced or followed by the scenarios in the directory.
+// Files with errors are skipped and their errors returned.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_dir(dir: &str) -> (Vec<Preset>, Vec<ScenarioError>) {
+    let mut presets = embedded();
+    let mut errors = vec![];
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (presets, errors);
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let file = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let result = std::fs::read_to_string(&path)
+            .map_err(|e| ScenarioError {
+                file: file.clone(),
+                line: None,
+                field: None,
+                message: e.to_string(),
+            })
+            .and_then(|source| parse(&file, &source));
+        match result {
+            Ok(preset) => match EMBEDDED.iter().position(|(name, _)| *name == file) {
+                Some(i) => presets[i] = preset,
+                None => presets.push(preset),
+            },
+            Err(e) => errors.push(e),
+        }
+    }
+    (presets, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_scenarios_parse() {
+        assert_eq!(embedded().len(), EMBEDDED.len());
+    }
+
+    #[test]
+    fn errors_have_line_and_field() {
+        let source = "name = \"Test\"\n\n[[bodies]]\nname = \"A\"\nmass = 1e20\n\n[[bodies]]\nname = \"B\"\nmass = 1e20\nlock = \"circular\"\nlock_to = \"C\"\n";
+        let error = parse("test.toml", source).err().unwrap();
+        assert_eq!(error.line, Some(11));
+        assert_eq!(error.field.as_deref(), Some("bodies[1].lock_to"));
+        assert_eq!(
+            error.to_string(),
+            "test.toml:11: bodies[1].lock_to: no body named \"C\""
+        );
+
+        let error = parse(
+            "test.toml",
+            "name = \"Test\"\n[[bodies]]\nname = \"A\"\nmas = 1\n",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error.line, Some(4));
+        assert!(error.message.contains("mas"), "{}", error.message);
+    }
+}
@@ -0,0 +1,71 @@
+// Images of the simulation drawn on the CPU: PNG snapshots, and frames for animations.
+// Frames are drawn by going back through the recorded ticks with the timeline,
+// so they're made after running and all use the same zoom, fitted to the whole run.
+
+use crate::app::core::{physics::Position, raster::Raster};
+
+use super::{export, view::View, Orbital};
+
+// space around the bodies and trajectories in a fitted image, px
+const MARGIN: f32 = 40.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameSettings {
+    pub width: u32,
+    pub height: u32,
+    // simulated time between frames, s
+    pub interval: f32,
+}
+
+impl Orbital {
+    // the current state drawn so that an area in canvas units (top left, bottom right) fills the image
+    pub fn raster(
+        &self,
+        view: &View,
+        width: u32,
+        height: u32,
+        area: (Position, Position),
+    ) -> Raster {
+        let mut raster = Raster::fit(width, height, area, 0.);
+        self.draw(&mut raster, view);
+        raster
+    }
+
+    // A PNG image of the bodies and their trajectories so far, zoomed to fit them.
+    pub fn png(&self, view: &View, width: u32, height: u32) -> Result<Vec<u8>, String> {
+        let mut raster = Raster::fit(width, height, self.drawn_area(view), MARGIN);
+        self.draw(&mut raster, view);
+        raster.png()
+    }
+
+    // Draws the recorded ticks at least settings.interval apart, oldest first, and passes each frame on.
+    // Returns the number of frames. The simulation is back at the tick it was showing afterwards.
+    pub fn frames(
+        &mut self,
+        view: &View,
+        settings: &FrameSettings,
+        mut on_frame: impl FnMut(&Raster) -> Result<(), String>,
+    ) -> Result<usize, String> {
+        let mut raster = Raster::fit(
+            settings.width,
+            settings.height,
+            self.drawn_area(view),
+            MARGIN,
+        );
+        let current = self.timeline.current_tick();
+        let ticks = export::sampled_ticks(&self.timeline, settings.interval);
+
+        let mut result = Ok(());
+        for tick in ticks.iter() {
+            self.seek(*tick);
+            raster.clear();
+            self.draw(&mut raster, view);
+            result = on_frame(&raster);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.seek(current);
+        result.map(|_| ticks.len())
+    }
+}
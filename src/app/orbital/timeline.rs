@@ -0,0 +1,300 @@
+// Going back to any recorded tick.
+// Every body already records its state each tick in its trajectory, so restoring a tick only needs
+// the time it was recorded at. The trajectories are kept while scrubbing back and forth,
+// and the ticks after the restored one are only discarded once the simulation runs again from there.
+// Test particles don't have trajectories, they're replayed from a copy using the recorded bodies.
+// While a run happens away from the window, the recorded ticks stay with the window and the run
+// only has the latest one, see background.rs.
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::core::physics::Integrator;
+
+use super::{body::Body, particles::TestParticles};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Playback {
+    #[default]
+    Paused,
+    Forward,
+    Backward,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Timeline {
+    // simulation time and dt of every tick, by tick number
+    times: Vec<f32>,
+    steps: Vec<f32>,
+    // integrator of every step, for replaying the particles the same way
+    #[serde(default)]
+    integrators: Vec<Integrator>,
+    // the hierarchy is rebuilt when a body is removed, ticks before that can't be restored any more
+    first_tick: usize,
+    // tick being shown, None for the latest one
+    viewing: Option<usize>,
+    // test particles at the first tick, and the tick that the current particles are at
+    initial_particles: TestParticles,
+    particle_tick: usize,
+    pub playback: Playback,
+    pub speed: usize, // ticks per frame while playing
+    // the earlier ticks were split off with take_ticks, first_tick only counts ticks after that
+    #[serde(default)]
+    detached: bool,
+}
+
+// Ticks split off the start of a timeline, to be put back in front of the ticks recorded after them.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Ticks {
+    times: Vec<f32>,
+    steps: Vec<f32>,
+    integrators: Vec<Integrator>,
+    // the first tick that can be restored and the particles at it, if it's among these ticks
+    first: Option<(usize, TestParticles)>,
+}
+
+impl Ticks {
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    // add the ticks recorded after these
+    pub fn append(&mut self, mut later: Ticks) {
+        if let Some((tick, particles)) = later.first {
+            self.first = Some((self.len() + tick, particles));
+        }
+        self.times.append(&mut later.times);
+        self.steps.append(&mut later.steps);
+        self.integrators.append(&mut later.integrators);
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            times: vec![],
+            steps: vec![],
+            integrators: vec![],
+            first_tick: 0,
+            viewing: None,
+            initial_particles: TestParticles::default(),
+            particle_tick: 0,
+            playback: Playback::Paused,
+            speed: 10,
+            detached: false,
+        }
+    }
+}
+
+impl Timeline {
+    pub fn clear(&mut self) {
+        *self = Self {
+            speed: self.speed,
+            ..Self::default()
+        };
+    }
+
+    pub fn start(&mut self, t: f32, particles: &TestParticles) {
+        self.clear();
+        self.times.push(t);
+        self.initial_particles = particles.clone();
+    }
+
+    pub fn record(&mut self, t: f32, dt: f32, integrator: Integrator) {
+        self.times.push(t);
+        self.steps.push(dt);
+        self.integrators.push(integrator);
+        self.particle_tick = self.last_tick();
+    }
+
+    // the hierarchy was rebuilt at the latest tick
+    pub fn rebase(&mut self, particles: &TestParticles) {
+        self.first_tick = self.last_tick();
+        self.initial_particles = particles.clone();
+        self.particle_tick = self.first_tick;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    // The ticks agree with each other and with the trajectories of the bodies they were recorded with,
+    // which a file that was edited might not.
+    pub fn check(&self, bodies: &[Body]) -> Result<(), String> {
+        let num_steps = self.last_tick();
+        // files from before the integrator could be chosen don't have them
+        let integrators_match = self.integrators.is_empty() || self.integrators.len() == num_steps;
+        if self.steps.len() != num_steps || !integrators_match {
+            return Err(format!(
+                "{} ticks but {} steps and {} integrators",
+                self.times.len(),
+                self.steps.len(),
+                self.integrators.len()
+            ));
+        }
+        if self.first_tick > self.last_tick() || self.particle_tick > self.last_tick() {
+            return Err(format!(
+                "first tick {} and particle tick {} but the last tick is {}",
+                self.first_tick,
+                self.particle_tick,
+                self.last_tick()
+            ));
+        }
+        if let Some(tick) = self.viewing {
+            if tick < self.first_tick || tick >= self.last_tick() {
+                return Err(format!(
+                    "viewing tick {} outside {}..{}",
+                    tick,
+                    self.first_tick,
+                    self.last_tick()
+                ));
+            }
+        }
+        self.initial_particles.check()?;
+        if self.is_empty() {
+            return Ok(());
+        }
+        for (i, body) in bodies.iter().enumerate() {
+            let expected = self.trajectory_index(self.last_tick(), body.is_barycenter) + 1;
+            if body.trajectory.len() != expected {
+                return Err(format!(
+                    "body {} has {} states but {} were recorded",
+                    i,
+                    body.trajectory.len(),
+                    expected
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn first_tick(&self) -> usize {
+        self.first_tick
+    }
+
+    pub fn last_tick(&self) -> usize {
+        self.times.len().saturating_sub(1)
+    }
+
+    pub fn current_tick(&self) -> usize {
+        self.viewing.unwrap_or(self.last_tick())
+    }
+
+    // showing an earlier tick than the latest one
+    pub fn is_rewound(&self) -> bool {
+        self.viewing.is_some()
+    }
+
+    pub fn time_at(&self, tick: usize) -> Option<f32> {
+        self.times.get(tick).copied()
+    }
+
+    // dt used to go from this tick to the next
+    pub fn step_at(&self, tick: usize) -> Option<f32> {
+        self.steps.get(tick).copied()
+    }
+
+    pub fn integrator_at(&self, tick: usize) -> Option<Integrator> {
+        self.integrators.get(tick).copied()
+    }
+
+    // Index of the state at a tick in a trajectory.
+    // Barycenters start recording when the hierarchy is built, bodies keep their whole trajectory.
+    pub fn trajectory_index(&self, tick: usize, is_barycenter: bool) -> usize {
+        if is_barycenter {
+            tick.saturating_sub(self.first_tick)
+        } else {
+            tick
+        }
+    }
+
+    pub fn set_current(&mut self, tick: usize) {
+        let tick = tick.clamp(self.first_tick, self.last_tick());
+        self.viewing = (tick < self.last_tick()).then_some(tick);
+    }
+
+    // Particles at the start of the replay to reach a tick, and the tick they're at.
+    // Going forward continues from the current particles, going back starts again from the copy.
+    pub fn replay_particles_from(
+        &mut self,
+        tick: usize,
+        current: &TestParticles,
+    ) -> (TestParticles, usize) {
+        let from = if tick >= self.particle_tick {
+            (current.clone(), self.particle_tick)
+        } else {
+            (self.initial_particles.clone(), self.first_tick)
+        };
+        self.particle_tick = tick;
+        from
+    }
+
+    // Next tick while playing, None once it reaches either end
+    pub fn next_tick(&self) -> Option<usize> {
+        let current = self.current_tick();
+        match self.playback {
+            Playback::Paused => None,
+            Playback::Forward if current < self.last_tick() => {
+                Some((current + self.speed).min(self.last_tick()))
+            }
+            Playback::Backward if current > self.first_tick => {
+                Some(current.saturating_sub(self.speed).max(self.first_tick))
+            }
+            _ => None,
+        }
+    }
+
+    // Split off every tick but the latest one, which is all that's needed to keep running.
+    pub fn take_ticks(&mut self) -> Ticks {
+        let latest = self.last_tick();
+        let first = (!self.detached || self.first_tick > 0)
+            .then(|| (self.first_tick, std::mem::take(&mut self.initial_particles)));
+        let steps = std::mem::take(&mut self.steps);
+        let integrators = std::mem::take(&mut self.integrators);
+        let mut times = std::mem::take(&mut self.times);
+        self.times = times.split_off(latest.min(times.len()));
+
+        self.first_tick = 0;
+        self.particle_tick = 0;
+        self.detached = true;
+        Ticks {
+            times,
+            steps,
+            integrators,
+            first,
+        }
+    }
+
+    // Put back the ticks from before the ones recorded since take_ticks.
+    pub fn prepend_ticks(&mut self, mut earlier: Ticks) {
+        let offset = earlier.len();
+        if self.first_tick > 0 {
+            self.first_tick += offset;
+        } else if let Some((tick, particles)) = earlier.first {
+            self.first_tick = tick;
+            self.initial_particles = particles;
+        }
+        earlier.times.append(&mut self.times);
+        earlier.steps.append(&mut self.steps);
+        earlier.integrators.append(&mut self.integrators);
+        self.times = earlier.times;
+        self.steps = earlier.steps;
+        self.integrators = earlier.integrators;
+        // runs keep the particles at the latest tick
+        self.particle_tick = self.last_tick();
+        self.detached = false;
+    }
+
+    // forget the ticks after the current one, before running again from there
+    pub fn truncate(&mut self) {
+        let tick = self.current_tick();
+        self.times.truncate(tick + 1);
+        self.steps.truncate(tick);
+        self.integrators.truncate(tick);
+        self.viewing = None;
+        self.playback = Playback::Paused;
+    }
+}
@@ -0,0 +1,283 @@
+// Earth satellites from NORAD two-line element sets (TLEs), like the ones from CelesTrak.
+// A set is two 69 character lines, optionally after a line with the satellite's name.
+//
+// The mean elements are treated as a plain Keplerian orbit around Earth, without the
+// drag and oblateness terms that SGP4 adds, which is close enough over a few orbits.
+// Our plane is the equator: each orbit is rotated into it around its line of nodes,
+// so the inclination is dropped and angles along the orbit are measured from the vernal equinox
+// (longitude of periapsis = RAAN + argument of perigee).
+// The satellites are moved along their orbits to the latest epoch in the file so they line up in time.
+
+use std::{f64::consts::TAU, fmt};
+
+use crate::app::core::physics::{
+    gravitational_parameter, normalize_angle, state_from_elements, OrbitalElements,
+};
+
+use super::body::Body;
+
+const DAY_S: f64 = 86_400.;
+// kg, the satellites barely affect each other or Earth
+const SATELLITE_MASS: f32 = 1000.;
+const COLORS: [(u8, u8, u8); 6] = [
+    (255, 80, 80),
+    (255, 200, 0),
+    (80, 220, 120),
+    (0, 200, 255),
+    (200, 120, 255),
+    (255, 140, 200),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tle {
+    pub name: String,
+    pub catalog_number: u32,
+    // days since 2000-01-01 00:00 UTC
+    pub epoch: f64,
+    // degrees
+    pub inclination: f64,
+    pub raan: f64,
+    pub eccentricity: f64,
+    pub argument_of_perigee: f64,
+    pub mean_anomaly: f64,
+    pub mean_motion: f64, // revolutions per day
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TleError {
+    // line number in the file
+    Format(usize, String),
+    Checksum(usize),
+    Empty,
+}
+
+impl fmt::Display for TleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TleError::Format(line, e) => write!(f, "line {}: {}", line, e),
+            TleError::Checksum(line) => write!(f, "line {}: checksum doesn't match", line),
+            TleError::Empty => write!(f, "no element sets found"),
+        }
+    }
+}
+
+// the last digit of a line: the sum of its digits, with minus signs counting as 1
+fn checksum(line: &str) -> u32 {
+    line.chars()
+        .take(68)
+        .map(|c| match c {
+            '-' => 1,
+            c => c.to_digit(10).unwrap_or(0),
+        })
+        .sum::<u32>()
+        % 10
+}
+
+// columns are numbered from 1 like in the format's description, both ends included
+fn field<T: std::str::FromStr>(
+    line: &str,
+    columns: (usize, usize),
+    name: &str,
+    line_number: usize,
+) -> Result<T, TleError> {
+    line.get(columns.0 - 1..columns.1)
+        .map(str::trim)
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(|| TleError::Format(line_number, format!("can't read the {}", name)))
+}
+
+// epoch like 08264.51782528: two digit year, then the day of the year starting at 1
+fn days_since_2000(year: u32, day_of_year: f64) -> f64 {
+    // years 57-99 are 1957-1999
+    let year = if year < 57 { 2000 + year } else { 1900 + year } as i32;
+    let is_leap = |y: i32| (y % 4 == 0 && y % 100 != 0) || y % 400 == 0;
+    let days_before_year: i32 = if year >= 2000 {
+        (2000..year)
+            .map(|y| if is_leap(y) { 366 } else { 365 })
+            .sum()
+    } else {
+        -(year..2000)
+            .map(|y| if is_leap(y) { 366 } else { 365 })
+            .sum::<i32>()
+    };
+    days_before_year as f64 + day_of_year - 1.
+}
+
+// one element set, line numbers are for errors
+fn parse_set(
+    name: Option<&str>,
+    (line1, n1): (&str, usize),
+    (line2, n2): (&str, usize),
+) -> Result<Tle, TleError> {
+    for (line, n, first) in [(line1, n1, '1'), (line2, n2, '2')] {
+        if !line.starts_with(first) {
+            return Err(TleError::Format(
+                n,
+                format!("expected line {} of an element set", first),
+            ));
+        }
+        // by bytes like the other columns, a pasted non-ASCII character makes it miss
+        let Some(check_digit) = line.get(68..69) else {
+            return Err(TleError::Format(
+                n,
+                format!("expected line {} of an element set", first),
+            ));
+        };
+        if check_digit.parse::<u32>().ok() != Some(checksum(line)) {
+            return Err(TleError::Checksum(n));
+        }
+    }
+
+    let catalog_number = field(line1, (3, 7), "catalog number", n1)?;
+    if field::<u32>(line2, (3, 7), "catalog number", n2)? != catalog_number {
+        return Err(TleError::Format(
+            n2,
+            "catalog number doesn't match the first line".to_string(),
+        ));
+    }
+    let year = field(line1, (19, 20), "epoch year", n1)?;
+    let day = field(line1, (21, 32), "epoch day", n1)?;
+    // the eccentricity has an implied leading decimal point
+    let eccentricity: f64 = field::<f64>(line2, (27, 33), "eccentricity", n2)? * 1e-7;
+    let mean_motion: f64 = field(line2, (53, 63), "mean motion", n2)?;
+    if mean_motion <= 0. {
+        return Err(TleError::Format(n2, "mean motion must be positive".into()));
+    }
+
+    Ok(Tle {
+        name: name
+            .map(|n| n.trim_start_matches("0 ").trim().to_string())
+            .unwrap_or_else(|| format!("NORAD {}", catalog_number)),
+        catalog_number,
+        epoch: days_since_2000(year, day),
+        inclination: field(line2, (9, 16), "inclination", n2)?,
+        raan: field(line2, (18, 25), "RAAN", n2)?,
+        eccentricity,
+        argument_of_perigee: field(line2, (35, 42), "argument of perigee", n2)?,
+        mean_anomaly: field(line2, (44, 51), "mean anomaly", n2)?,
+        mean_motion,
+    })
+}
+
+pub fn parse(text: &str) -> Result<Vec<Tle>, TleError> {
+    let lines: Vec<(&str, usize)> = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (line.trim_end(), i + 1))
+        .filter(|(line, _)| !line.is_empty())
+        .collect();
+
+    let mut sets = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        // a name line is anything before a line 1
+        let name = (!lines[i].0.starts_with("1 ")).then_some(lines[i].0);
+        let start = i + name.is_some() as usize;
+        let (Some(&line1), Some(&line2)) = (lines.get(start), lines.get(start + 1)) else {
+            return Err(TleError::Format(
+                lines[i].1,
+                "incomplete element set".to_string(),
+            ));
+        };
+        sets.push(parse_set(name, line1, line2)?);
+        i = start + 2;
+    }
+
+    if sets.is_empty() {
+        return Err(TleError::Empty);
+    }
+    Ok(sets)
+}
+
+impl Tle {
+    // Keplerian elements in the equatorial plane, moved forward to a later time (days since 2000)
+    pub fn elements_at(&self, mu: f64, epoch: f64) -> OrbitalElements {
+        let mean_motion = self.mean_motion * TAU / DAY_S; // rad/s
+        let semi_major_axis = (mu / mean_motion.powi(2)).cbrt();
+        let longitude_of_periapsis = (self.raan + self.argument_of_perigee).to_radians();
+        let mean_anomaly =
+            self.mean_anomaly.to_radians() + mean_motion * (epoch - self.epoch) * DAY_S;
+
+        OrbitalElements {
+            semi_major_axis,
+            eccentricity: self.eccentricity,
+            period: TAU / mean_motion,
+            longitude_of_periapsis: normalize_angle(longitude_of_periapsis),
+            mean_longitude: normalize_angle(longitude_of_periapsis + mean_anomaly),
+        }
+    }
+
+    // retrograde orbits (inclination over 90°) go around the same ellipse clockwise
+    fn is_retrograde(&self) -> bool {
+        self.inclination > 90.
+    }
+}
+
+// Earth at the origin with the satellites around it, all at the latest epoch
+pub fn bodies(sets: &[Tle]) -> Vec<Body> {
+    let earth = Body::earth();
+    let mu = gravitational_parameter(earth.mass + SATELLITE_MASS);
+    let epoch = sets.iter().map(|s| s.epoch).fold(f64::MIN, f64::max);
+
+    let satellites = sets.iter().enumerate().map(|(i, set)| {
+        let (pos, mut vel) = state_from_elements(mu, &set.elements_at(mu, epoch));
+        if set.is_retrograde() {
+            vel = vel.scale(-1.);
+        }
+        Body {
+            name: set.name.clone(),
+            mass: SATELLITE_MASS,
+            absolute_pos: pos,
+            absolute_vel: vel,
+            color: COLORS[i % COLORS.len()],
+            ..Body::default()
+        }
+    });
+
+    std::iter::once(earth).chain(satellites).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISS: &str = "ISS (ZARYA)
+1 25544U 98067A   08264.51782528 -.00002182  00000-0 -11606-4 0  2927
+2 25544  51.6416 247.4627 0006703 130.5360 325.0288 15.72125391563537";
+
+    #[test]
+    fn reads_element_set() {
+        let sets = parse(ISS).unwrap();
+        let iss = &sets[0];
+        assert_eq!(iss.name, "ISS (ZARYA)");
+        assert_eq!(iss.catalog_number, 25544);
+        assert_eq!(iss.inclination, 51.6416);
+        assert_eq!(iss.raan, 247.4627);
+        assert!((iss.eccentricity - 0.0006703).abs() < 1e-12);
+        assert_eq!(iss.mean_motion, 15.72125391);
+        // 2008 day 264: 8 years with 2 leap days, then 263.5 days
+        assert!((iss.epoch - (8. * 365. + 2. + 263.51782528)).abs() < 1e-9);
+
+        // without the name line
+        let unnamed = parse(ISS.split_once('\n').unwrap().1).unwrap();
+        assert_eq!(unnamed[0].name, "NORAD 25544");
+        assert_eq!(unnamed[0].epoch, iss.epoch);
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let changed = ISS.replace("51.6416", "51.6417");
+        assert_eq!(parse(&changed), Err(TleError::Checksum(3)));
+        assert!(matches!(
+            parse("ISS (ZARYA)\n1 25544U"),
+            Err(TleError::Format(1, _))
+        ));
+    }
+
+    #[test]
+    fn rejects_non_ascii_line() {
+        // the degree sign is two bytes, so the checksum column falls inside it
+        let changed = ISS.replace("15.72125391563537", "15.7212539156353°");
+        assert!(matches!(parse(&changed), Err(TleError::Format(3, _))));
+    }
+}
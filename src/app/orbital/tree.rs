@@ -237,7 +237,7 @@ fn build_one_level(nodes: &Vec<Node>) -> Vec<Vec<NodeIndex>> {
 }
 
 // Leaf node = index corresponds to original vector index
-pub fn build_hierarchy(bodies: &Vec<Body>) -> (DiGraph<Body, ()>, NodeIndex) {
+pub fn build_hierarchy(bodies: &[Body]) -> (DiGraph<Body, ()>, NodeIndex) {
     let initial_nodes: Vec<Node> = bodies.iter().map(|b| Node::new(b.copy())).collect();
 
     let mut overall_graph = DiGraph::<Node, ()>::new();
@@ -326,6 +326,25 @@ pub fn build_hierarchy(bodies: &Vec<Body>) -> (DiGraph<Body, ()>, NodeIndex) {
     (localized, root_index)
 }
 
+// All bodies as siblings under one root, so every body feels every other body directly.
+// Used for star clusters, where close pairs form and break up all the time,
+// so the grouping made at the start wouldn't stay valid.
+pub fn build_flat_hierarchy(bodies: &[Body]) -> (DiGraph<Body, ()>, NodeIndex) {
+    let mut graph = DiGraph::<Node, ()>::new();
+    let leaves: Vec<NodeIndex> = bodies
+        .iter()
+        .map(|b| graph.add_node(Node::new(b.copy())))
+        .collect();
+    let root = graph.add_node(Node::Group {
+        children: bodies.iter().map(|b| Node::new(b.copy())).collect(),
+    });
+    for leaf in leaves {
+        graph.add_edge(root, leaf, ());
+    }
+
+    (map_to_localized(map_to_bodies(graph)), root)
+}
+
 // TODO combine two mapping steps
 fn map_to_bodies(graph: DiGraph<Node, ()>) -> DiGraph<Body, ()> {
     let body_graph: DiGraph<Body, ()> = graph.map(
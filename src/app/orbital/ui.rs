@@ -1,63 +1,123 @@
+#[cfg(target_arch = "wasm32")]
+use std::sync::{Arc, Mutex};
 use std::usize::MAX;
 
 use egui::RichText;
 use petgraph::graph::NodeIndex;
 
-use crate::ui::widgets::{CustomSlider, XYInput};
+use crate::{
+    app::core::physics::{Axis, Integrator, Position, Velocity},
+    helpers::files,
+    ui::widgets::{CustomSlider, XYInput},
+};
 
-use super::Orbital;
+use super::{
+    body::Preset,
+    checkpoint::Checkpoint,
+    events::{EventCondition, Events},
+    export::{ExportFormat, ExportSettings},
+    frame::JacobiSubject,
+    generators::RingSettings,
+    poincare::{Coordinate, PoincareSection, SectionFrame},
+    resonance::{Libration, Resonances},
+    timeline::Playback,
+    view::branch_style,
+    OrbitalApp,
+};
 
-pub fn ui(app: &mut Orbital, ctx: &egui::Context) {
+pub fn ui(app: &mut OrbitalApp, ctx: &egui::Context) {
     let (kinetic, potential, diff_percent) = (
-        app.analysis.kinetic_e,
-        app.analysis.gravitational_e,
-        app.analysis.diff_percentage,
+        app.sim.analysis.kinetic_e,
+        app.sim.analysis.gravitational_e,
+        app.sim.analysis.diff_percentage,
     );
-    let presets: Vec<String> = app.presets.iter().map(|p| p.name.clone()).collect();
+    let presets: Vec<String> = app.sim.presets().iter().map(|p| p.name.clone()).collect();
 
     let panel = egui::SidePanel::left("main-ui-panel")
         .exact_width(app.ui_state.panel_width)
         .resizable(false);
 
     panel.show(ctx, |ui| {
+        // the simulation can't be changed while a run is in progress, it'd be overwritten
+        let running = run_progress(ui, app);
+        if running {
+            ui.disable();
+        }
+
         egui::CollapsingHeader::new(RichText::new("Select preset simulation").heading())
             .default_open(true)
             .show(ui, |ui| {
                 for (i, preset) in presets.iter().enumerate() {
                     if ui.button(preset).clicked() {
-                        app.reset();
-                        app.load_preset(i);
+                        app.sim.reset();
+                        app.sim.load_preset(i);
                     }
                 }
+                galaxy_collision_form(ui, app);
+                cluster_form(ui, app);
+                horizons_form(ui, app);
+                tle_form(ui, app);
+                scenario_files_section(ui, app);
             });
 
         ui.add(egui::Separator::default().spacing(10.));
 
         ui.label(RichText::new("General").heading());
-        ui.add(CustomSlider::new(&mut app.dt, 0.01..=10.0).label("dt:"));
-        ui.add(CustomSlider::new(&mut app.num_ticks, 100..=100000).label("ticks per press:"));
+        ui.add(CustomSlider::new(&mut app.sim.dt, 0.01..=10.0).label("dt:"));
+        ui.horizontal(|ui| {
+            for integrator in Integrator::ALL {
+                ui.radio_value(&mut app.sim.integrator, integrator, integrator.name());
+            }
+        });
+        ui.add(CustomSlider::new(&mut app.sim.num_ticks, 100..=100000).label("ticks per press:"));
+        realtime_controls(ui, app);
+
+        let bodies_list = app.sim.bodies_list();
+        frame_section(ui, app, &bodies_list);
+        timeline_section(ui, app);
+        branches_section(ui, app, &bodies_list);
+        checkpoint_section(ui, app);
+        export_section(ui, app);
+        #[cfg(feature = "raster")]
+        image_section(ui, app);
 
         ui.add(egui::Separator::default().spacing(10.));
 
         ui.input(|i| {
+            if running {
+                return;
+            }
             if i.key_pressed(egui::Key::R) {
-                app.reset();
+                app.sim.reset();
             }
-            if !app.started && i.key_pressed(egui::Key::Enter) {
-                app.start();
+            if !app.sim.started && i.key_pressed(egui::Key::Enter) {
+                app.sim.start();
             }
         });
+        // not while typing, e.g. a space in a name
+        if !running && !ctx.wants_keyboard_input() {
+            ui.input(|i| {
+                if i.key_pressed(egui::Key::Space) {
+                    app.sim.toggle_play();
+                }
+                if i.key_pressed(egui::Key::Plus) || i.key_pressed(egui::Key::Equals) {
+                    app.sim.realtime.warp *= 2.;
+                }
+                if i.key_pressed(egui::Key::Minus) {
+                    app.sim.realtime.warp /= 2.;
+                }
+            });
+        }
 
-        let bodies_list = app.bodies_list();
-        let started = app.started;
-        let current_focus = app.focused;
+        let started = app.sim.started;
+        let current_focus = app.sim.focused;
         let mut focus_click: usize = MAX;
 
         egui::ScrollArea::vertical().show(ui, |ui| {
             ui.label(RichText::new("Bodies").heading());
             ui.add_space(6.);
 
-            for (i, body) in app.bodies_vec_mut().iter_mut().enumerate() {
+            for (i, body) in app.sim.bodies_vec_mut().iter_mut().enumerate() {
                 let x_range = -10000.0..=10000.;
                 let y_range = -10000.0..=10000.;
 
@@ -172,20 +232,27 @@ pub fn ui(app: &mut Orbital, ctx: &egui::Context) {
                 ui.add_space(10.);
             }
 
+            particles_section(ui, app, &bodies_list);
+            lagrange_section(ui, app, &bodies_list);
+            ui.add_space(10.);
+
             if ui.button("Start").clicked() {
-                app.start();
+                app.sim.start();
             }
 
             ui.add_space(10.);
             ui.label(RichText::new("Analysis").heading());
-            let t = app.t;
+            let t = app.sim.t;
             let days = t / (60 * 60 * 24) as f32;
             ui.monospace(format!("t: {:.4e} s, {:.2} d", t, days));
+            if app.sim.is_stopped() {
+                ui.colored_label(egui::Color32::LIGHT_RED, "Stopped: two bodies collided");
+            }
             ui.monospace("Energy (MJ)");
             ui.monospace(format!("Kinetic:      {:+.4e}", kinetic));
             ui.monospace(format!("Potential:    {:+.4e}", potential));
-            ui.monospace(format!("Total:        {:+.4e}", kinetic + potential));
-            ui.monospace(format!("Initial:      {:+.4e}", app.analysis.initial_e));
+            ui.monospace(format!("Total:        {:+.4e}", app.sim.analysis.total_e));
+            ui.monospace(format!("Initial:      {:+.4e}", app.sim.analysis.initial_e));
             ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
                 ui.monospace(format!("Energy Drift: {:.2e}%", diff_percent));
                 ui.monospace("❓")
@@ -202,6 +269,19 @@ pub fn ui(app: &mut Orbital, ctx: &egui::Context) {
                     "Percentage difference divided by time elapsed. The absolute value should trend downwards if the simulation is accurate.",
                 );
             });
+            chaos_section(ui, app);
+            cluster_section(ui, app);
+            escapes_section(ui, app);
+            resonances_section(ui, &mut app.sim.resonances);
+            ui.add_space(10.);
+
+            events_section(
+                ui,
+                &mut app.sim.events,
+                &mut app.ui_state.event_form,
+                &bodies_list,
+            );
+            poincare_section(ui, &mut app.sim.poincare, &bodies_list);
             ui.add_space(10.);
         });
 
@@ -211,16 +291,1261 @@ pub fn ui(app: &mut Orbital, ctx: &egui::Context) {
                 Some(focused) if focused.index() == focus_click => None,
                 _ => Some(NodeIndex::new(focus_click)),
             };
-            app.set_focus(new_focus);
+            app.sim.set_focus(new_focus);
+        }
+    });
+}
+
+fn chaos_section(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    ui.add_space(5.);
+    ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+        ui.checkbox(&mut app.sim.chaos.enabled, "Lyapunov / MEGNO");
+        ui.monospace("❓")
+            .on_hover_cursor(egui::CursorIcon::Default)
+            .on_hover_text(
+                "Propagates a small offset of all bodies to measure how quickly nearby orbits diverge. MEGNO approaches 2 for regular orbits and keeps growing for chaotic ones.",
+            );
+    });
+    if let Some(indicators) = app.sim.chaos_indicators() {
+        let days = 60. * 60. * 24.;
+        ui.monospace(format!(
+            "Lyapunov:     {:.4e} /s",
+            indicators.lyapunov_exponent
+        ));
+        if indicators.lyapunov_exponent > 0. {
+            ui.monospace(format!(
+                "Lyap. time:   {:.4e} d",
+                1. / indicators.lyapunov_exponent / days
+            ));
         }
+        ui.monospace(format!("MEGNO:        {:.4}", indicators.megno));
+        ui.monospace(format!("Mean MEGNO:   {:.4}", indicators.mean_megno));
+        ui.monospace(format!("Measured for: {:.2} d", indicators.t / days));
+    }
+}
+
+fn cluster_section(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    ui.add_space(5.);
+    ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+        ui.checkbox(&mut app.sim.cluster.enabled, "Cluster statistics");
+        ui.monospace("❓")
+            .on_hover_cursor(egui::CursorIcon::Default)
+            .on_hover_text(
+                "Virial ratio is 0.5 in equilibrium. The core (10% mass) radius shrinking while the half-mass radius grows is core collapse. Escapers have positive energy and are outside twice the half-mass radius.",
+            );
+    });
+    let (Some(first), Some(latest)) = (app.sim.cluster.samples.first(), app.sim.cluster.latest())
+    else {
+        return;
+    };
+    ui.monospace(format!("Softening:    {:.4e} km", app.sim.softening));
+    ui.monospace(format!("Virial ratio: {:.4}", latest.virial_ratio));
+    ui.monospace(format!("Half-mass r:  {:.4e} km", latest.half_mass_radius));
+    ui.monospace(format!(
+        "Core r:       {:.4e} km ({:.2}x initial)",
+        latest.core_radius,
+        latest.core_radius / first.core_radius
+    ));
+    if let Some(deepest) = app.sim.cluster.deepest_core() {
+        ui.monospace(format!(
+            "Smallest core: {:.2}x initial at t = {:.4e} s",
+            deepest.core_radius / first.core_radius,
+            deepest.t
+        ));
+    }
+    ui.monospace(format!("Escapers:     {}", latest.escapers));
+    ui.add(
+        egui::ProgressBar::new(latest.escapers as f32 / app.sim.bodies_vec().len().max(1) as f32)
+            .desired_height(8.)
+            .desired_width(ui.available_width() / 2.),
+    );
+}
+
+fn escapes_section(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    ui.add_space(5.);
+    ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+        ui.checkbox(&mut app.sim.escapes.enabled, "Escapes / captures");
+        ui.monospace("❓")
+            .on_hover_cursor(egui::CursorIcon::Default)
+            .on_hover_text(
+                "Classifies each body by the sign of its orbital energy relative to its parent and to the whole system. Escaped means unbound, moving away and outside the tidal radius of the parent. Escapes and captures are added to the event log.",
+            );
     });
+    if !app.sim.escapes.enabled {
+        return;
+    }
+    let escapes = &mut app.sim.escapes;
+    ui.checkbox(&mut escapes.remove_escapers, "Remove escapers");
+    ui.add_enabled(
+        escapes.remove_escapers,
+        egui::Slider::new(&mut escapes.removal_distance, 1e3..=1e12)
+            .logarithmic(true)
+            .text("beyond (km)"),
+    );
+
+    let names: Vec<String> = app
+        .sim
+        .bodies_vec()
+        .iter()
+        .map(|b| b.name.clone())
+        .collect();
+    for (name, classification) in names.iter().zip(app.sim.escapes.classifications.iter()) {
+        let Some(c) = classification else {
+            continue;
+        };
+        let parent = match c.parent {
+            Some((binding, _)) => format!(", parent: {}", binding.label()),
+            None => "".to_string(),
+        };
+        ui.monospace(format!("{}: system: {}{}", name, c.system.label(), parent))
+            .on_hover_text(format!(
+                "Specific energy relative to the system: {:.4e} km²/s²",
+                c.system_energy
+            ));
+    }
+}
+
+fn resonances_section(ui: &mut egui::Ui, resonances: &mut Resonances) {
+    ui.add_space(5.);
+    ui.monospace("Resonances");
+    ui.add(egui::Slider::new(&mut resonances.max_order, 1..=5).text("Max order (p - q)"));
+    let mut tolerance_percent = resonances.tolerance * 100.;
+    ui.add(
+        egui::Slider::new(&mut tolerance_percent, 0.1..=5.)
+            .text("Tolerance")
+            .suffix("%"),
+    );
+    resonances.tolerance = tolerance_percent / 100.;
+
+    if resonances.tracked.is_empty() {
+        ui.monospace("None detected");
+    }
+    for resonance in resonances.tracked.iter() {
+        let mut heading = format!("{} {}", resonance.names.join(" : "), resonance.label());
+        if resonance.ratio.is_some() {
+            heading += &format!(" ({:.4})", resonance.period_ratio);
+        }
+        ui.monospace(heading);
+
+        for (name, elements) in resonance.names.iter().zip(resonance.elements.iter()) {
+            ui.monospace(format!(
+                "  {}: a {:.4e} km, e {:.4}",
+                name, elements.semi_major_axis, elements.eccentricity
+            ));
+        }
+        if let Some(angle) = resonance.current_angle() {
+            ui.monospace(format!("  Angle: {:.1}°", angle));
+        }
+        let state = match resonance.libration() {
+            Libration::Undetermined => "not enough data".to_string(),
+            Libration::Librating { center, amplitude } => {
+                format!("librating {:.1}° ± {:.1}°", center, amplitude)
+            }
+            Libration::Circulating => "circulating".to_string(),
+        };
+        ui.monospace(format!("  {}", state));
+    }
+}
+
+fn frame_section(ui: &mut egui::Ui, app: &mut OrbitalApp, bodies_list: &[String]) {
+    egui::CollapsingHeader::new("Reference frame")
+        .default_open(false)
+        .show(ui, |ui| {
+            let num_particles = app.sim.particles.len();
+            let frame = &mut app.ui_state.view.frame;
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut frame.corotating, false, "Inertial");
+                ui.radio_value(&mut frame.corotating, true, "Co-rotating");
+            });
+
+            ui.add_enabled_ui(frame.corotating, |ui| {
+                egui::ComboBox::from_label("Rotate with").show_index(
+                    ui,
+                    &mut frame.body1,
+                    bodies_list.len(),
+                    |i| bodies_list[i].clone(),
+                );
+                egui::ComboBox::from_label("and").show_index(
+                    ui,
+                    &mut frame.body2,
+                    bodies_list.len(),
+                    |i| bodies_list[i].clone(),
+                );
+                ui.checkbox(&mut frame.show_zero_velocity, "zero-velocity curve");
+                ui.horizontal(|ui| {
+                    ui.label("of");
+                    let is_particle = matches!(frame.jacobi_of, JacobiSubject::Particle(_));
+                    if ui.radio(is_particle, "test particle").clicked() {
+                        frame.jacobi_of = JacobiSubject::Particle(0);
+                    }
+                    if ui.radio(!is_particle, "body").clicked() {
+                        frame.jacobi_of = JacobiSubject::Body(0);
+                    }
+                });
+                match &mut frame.jacobi_of {
+                    JacobiSubject::Particle(i) => {
+                        let last = num_particles.saturating_sub(1);
+                        ui.add(egui::DragValue::new(i).range(0..=last).prefix("particle "));
+                    }
+                    JacobiSubject::Body(i) => {
+                        egui::ComboBox::from_label("body").show_index(
+                            ui,
+                            i,
+                            bodies_list.len(),
+                            |i| bodies_list[i].clone(),
+                        );
+                    }
+                }
+            });
+
+            let frame = &app.ui_state.view.frame;
+            if let Some(jacobi) = app
+                .sim
+                .jacobi_constant(frame.body1, frame.body2, frame.jacobi_of)
+            {
+                ui.monospace(format!("Jacobi constant: {:+.6e} km^2/s^2", jacobi));
+            }
+        });
+}
+
+// Progress of a run of num_ticks with a button to cancel it, returns true while it's running.
+fn run_progress(ui: &mut egui::Ui, app: &mut OrbitalApp) -> bool {
+    let (done, total) = match (&app.background, &app.foreground) {
+        (Some(run), _) => run.progress(),
+        (None, Some(run)) => run.progress(),
+        (None, None) => return false,
+    };
+    ui.horizontal(|ui| {
+        if ui.button("Cancel").clicked() {
+            if let Some(run) = app.background.take() {
+                run.cancel();
+            }
+            app.foreground = None;
+        }
+        ui.add(
+            egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                .text(format!("{} / {} ticks", done, total)),
+        );
+    });
+    if let Some(run) = &app.foreground {
+        text_sized(ui, &format!("Running in the window: {}", run.reason), 12.);
+    }
+    ui.separator();
+    app.is_running()
+}
+
+// continuous playback at a time warp, instead of running num_ticks per press
+fn realtime_controls(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    if app.sim.realtime.warp <= 0. {
+        app.sim.realtime.warp = app.sim.default_warp();
+    }
+    ui.horizontal(|ui| {
+        let label = if app.sim.realtime.playing {
+            "\u{23F8} pause"
+        } else {
+            "\u{25B6} play"
+        };
+        if ui.button(label).clicked() {
+            app.sim.toggle_play();
+        }
+        ui.add(
+            egui::Slider::new(&mut app.sim.realtime.warp, 0.01..=1e10)
+                .logarithmic(true)
+                .text("s per s"),
+        );
+    });
+    text_sized(ui, "Space: play/pause, + and -: faster and slower", 12.);
+}
+
+fn timeline_section(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    egui::CollapsingHeader::new("Timeline")
+        .default_open(false)
+        .show(ui, |ui| {
+            if !app.sim.started || app.sim.timeline.is_empty() {
+                ui.label("Start the simulation to record a timeline.");
+                return;
+            }
+            let timeline = &app.sim.timeline;
+            let (first, last) = (timeline.first_tick(), timeline.last_tick());
+            let mut tick = timeline.current_tick();
+            let response = ui.add(CustomSlider::new(&mut tick, first..=last).label("tick:"));
+            if response.changed() {
+                app.sim.timeline.playback = Playback::Paused;
+                app.sim.seek(tick);
+            }
+            ui.label(format!("t: {:.1} s", app.sim.t));
+
+            ui.horizontal(|ui| {
+                let playback = &mut app.sim.timeline.playback;
+                ui.selectable_value(playback, Playback::Backward, "\u{25C0} back");
+                ui.selectable_value(playback, Playback::Paused, "\u{23F8} pause");
+                ui.selectable_value(playback, Playback::Forward, "\u{25B6} forward");
+            });
+            // replaying and running on at the same time would fight over the current tick
+            if app.sim.timeline.playback != Playback::Paused {
+                app.sim.realtime.pause();
+            }
+            ui.add(
+                CustomSlider::new(&mut app.sim.timeline.speed, 1..=1000).label("ticks per frame:"),
+            );
+
+            if app.sim.timeline.is_rewound() {
+                text_sized(
+                    ui,
+                    &format!(
+                        "Press {} to continue from here, the later ticks are discarded.",
+                        '\u{2192}'
+                    ),
+                    12.,
+                );
+            }
+            if first > 0 {
+                text_sized(
+                    ui,
+                    "Ticks before a body was removed can't be restored.",
+                    12.,
+                );
+            }
+        });
+}
+
+#[derive(Clone)]
+pub struct CheckpointForm {
+    // file name on native, name of the downloaded file on the web
+    pub path: String,
+    pub include_trajectories: bool,
+    pub message: Option<String>,
+    // contents of an uploaded file, once the browser has read it
+    #[cfg(target_arch = "wasm32")]
+    pub upload: Arc<Mutex<Option<String>>>,
+}
+
+impl Default for CheckpointForm {
+    fn default() -> Self {
+        Self {
+            path: "checkpoint.json".to_string(),
+            include_trajectories: true,
+            message: None,
+            #[cfg(target_arch = "wasm32")]
+            upload: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+fn load_checkpoint_json(app: &mut OrbitalApp, json: &str) -> String {
+    match Checkpoint::from_json(json) {
+        Ok(checkpoint) => {
+            app.sim.load_checkpoint(checkpoint);
+            format!("Loaded at t = {:.1} s", app.sim.t)
+        }
+        Err(e) => e.to_string(),
+    }
+}
+
+fn checkpoint_section(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let uploaded = app
+            .ui_state
+            .checkpoint
+            .upload
+            .lock()
+            .ok()
+            .and_then(|mut u| u.take());
+        if let Some(json) = uploaded {
+            app.ui_state.checkpoint.message = Some(load_checkpoint_json(app, &json));
+        }
+    }
+
+    egui::CollapsingHeader::new("Save / load")
+        .default_open(false)
+        .show(ui, |ui| {
+            let form = &mut app.ui_state.checkpoint;
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut form.path);
+            });
+            ui.checkbox(&mut form.include_trajectories, "include trajectories");
+
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    let form = &app.ui_state.checkpoint;
+                    let json = app.sim.checkpoint(form.include_trajectories).to_json();
+                    let message = match files::save_text(&form.path, &json) {
+                        Ok(()) => format!("Saved at t = {:.1} s", app.sim.t),
+                        Err(e) => e,
+                    };
+                    app.ui_state.checkpoint.message = Some(message);
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Load").clicked() {
+                    let message = match files::read_text(&app.ui_state.checkpoint.path) {
+                        Ok(json) => load_checkpoint_json(app, &json),
+                        Err(e) => e,
+                    };
+                    app.ui_state.checkpoint.message = Some(message);
+                }
+                #[cfg(target_arch = "wasm32")]
+                if ui.button("Upload").clicked() {
+                    let upload = app.ui_state.checkpoint.upload.clone();
+                    let ctx = ui.ctx().clone();
+                    if let Err(e) = files::open_text(upload, move || ctx.request_repaint()) {
+                        app.ui_state.checkpoint.message = Some(e);
+                    }
+                }
+            });
+
+            if let Some(message) = &app.ui_state.checkpoint.message {
+                text_sized(ui, message, 12.);
+            }
+        });
+}
+
+#[derive(Clone)]
+pub struct ExportForm {
+    // files are named <name>_bodies and <name>_metrics
+    pub name: String,
+    pub settings: ExportSettings,
+    pub message: Option<String>,
+}
+
+impl Default for ExportForm {
+    fn default() -> Self {
+        Self {
+            name: "export".to_string(),
+            settings: ExportSettings::default(),
+            message: None,
+        }
+    }
+}
+
+fn export_section(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    egui::CollapsingHeader::new("Export data")
+        .default_open(false)
+        .show(ui, |ui| {
+            let form = &mut app.ui_state.export;
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut form.name);
+            });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut form.settings.format, ExportFormat::Csv, "CSV");
+                ui.radio_value(
+                    &mut form.settings.format,
+                    ExportFormat::JsonLines,
+                    "JSON Lines",
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("every");
+                ui.add(
+                    egui::DragValue::new(&mut form.settings.interval)
+                        .range(0.0..=f32::MAX)
+                        .speed(10.),
+                );
+                ui.label("s (0: every tick)");
+            });
+            ui.checkbox(
+                &mut form.settings.include_barycenters,
+                "include barycenters",
+            );
+
+            if ui.button("Export").clicked() {
+                let form = &app.ui_state.export;
+                let message = match app.sim.export(&form.settings).save(&form.name) {
+                    Ok((bodies_file, metrics_file)) => {
+                        format!("Saved {} and {}", bodies_file, metrics_file)
+                    }
+                    Err(e) => e,
+                };
+                app.ui_state.export.message = Some(message);
+            }
+
+            if let Some(message) = &app.ui_state.export.message {
+                text_sized(ui, message, 12.);
+            }
+        });
+}
+
+#[cfg(feature = "raster")]
+#[derive(Clone)]
+pub struct ImageForm {
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+    pub message: Option<String>,
+}
+
+#[cfg(feature = "raster")]
+impl Default for ImageForm {
+    fn default() -> Self {
+        Self {
+            file_name: "grav.png".to_string(),
+            width: 1920,
+            height: 1080,
+            message: None,
+        }
+    }
+}
+
+// a PNG of what's on screen, at any size
+#[cfg(feature = "raster")]
+fn image_section(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    egui::CollapsingHeader::new("Save image")
+        .default_open(false)
+        .show(ui, |ui| {
+            let form = &mut app.ui_state.image;
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut form.file_name);
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut form.width).range(1..=8192));
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut form.height).range(1..=8192));
+                ui.label("px");
+            });
+
+            if ui.button("Save PNG").clicked() {
+                let form = &app.ui_state.image;
+                let area = app.ui_state.visible_area.get();
+                let raster = app
+                    .sim
+                    .raster(&app.ui_state.view, form.width, form.height, area);
+                let message = match raster
+                    .png()
+                    .and_then(|png| files::save_bytes(&form.file_name, &png))
+                {
+                    Ok(()) => format!("Saved {}", form.file_name),
+                    Err(e) => e,
+                };
+                app.ui_state.image.message = Some(message);
+            }
+
+            if let Some(message) = &app.ui_state.image.message {
+                text_sized(ui, message, 12.);
+            }
+        });
+}
+
+#[derive(Clone)]
+pub struct BranchForm {
+    pub name: String,
+    pub branch: usize,
+    pub body: usize,
+    pub dv_x: f32, // km/s
+    pub dv_y: f32,
+}
+
+impl Default for BranchForm {
+    fn default() -> Self {
+        Self {
+            name: "Branch 1".to_string(),
+            branch: 0,
+            body: 0,
+            dv_x: 0.,
+            dv_y: 0.,
+        }
+    }
+}
+
+fn branches_section(ui: &mut egui::Ui, app: &mut OrbitalApp, bodies_list: &[String]) {
+    egui::CollapsingHeader::new("Branches")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut app.ui_state.branch.name);
+                if ui
+                    .add_enabled(app.sim.started, egui::Button::new("Fork"))
+                    .clicked()
+                {
+                    let name = app.ui_state.branch.name.clone();
+                    app.sim.fork(&name);
+                    app.ui_state.branch.branch = app.sim.branches.len() - 1;
+                    app.ui_state.branch.name = format!("Branch {}", app.sim.branches.len() + 1);
+                }
+            });
+            if app.sim.branches.is_empty() {
+                ui.label(
+                    "Fork the running simulation to try a change without losing the original.",
+                );
+                return;
+            }
+
+            let names: Vec<String> = app.sim.branches.iter().map(|b| b.name.clone()).collect();
+            let form = &mut app.ui_state.branch;
+            form.branch = form.branch.min(names.len() - 1);
+            egui::ComboBox::from_label("Branch").show_index(
+                ui,
+                &mut form.branch,
+                names.len(),
+                |i| format!("{} ({:?})", names[i], branch_style(i)),
+            );
+            egui::ComboBox::from_label("Nudge").show_index(
+                ui,
+                &mut form.body,
+                bodies_list.len(),
+                |i| bodies_list[i].clone(),
+            );
+            text_sized(ui, "Velocity change (km/s)", 14.);
+            ui.add(XYInput::new(
+                &mut form.dv_x,
+                &mut form.dv_y,
+                -1.0..=1.0,
+                -1.0..=1.0,
+            ));
+
+            let form = &app.ui_state.branch;
+            let (branch, body, dv) = (form.branch, form.body, Velocity::new(form.dv_x, form.dv_y));
+            ui.horizontal(|ui| {
+                if ui.button("Apply to branch").clicked() {
+                    app.sim.branches[branch].sim.nudge(body, dv);
+                }
+                if ui.button("Remove branch").clicked() {
+                    app.sim.remove_branch(branch);
+                }
+            });
+
+            for (i, name) in names.iter().enumerate() {
+                ui.monospace(format!("{} divergence:", name));
+                for d in app.sim.branch_divergence(i) {
+                    ui.monospace(format!(
+                        "  {}: {:.4e} km, {:.4e} km/s",
+                        d.name, d.distance, d.velocity
+                    ));
+                }
+            }
+        });
+}
+
+// encounter for the galaxy collision scenario
+#[derive(Clone)]
+pub struct GalaxyForm {
+    pub impact_parameter: f32,  // km
+    pub relative_velocity: f32, // km/s
+}
+
+impl Default for GalaxyForm {
+    fn default() -> Self {
+        Self {
+            impact_parameter: 7.5e7,
+            relative_velocity: 1.1,
+        }
+    }
+}
+
+// loading bodies from a text file, like the Horizons and TLE imports
+#[derive(Clone, Default)]
+pub struct ImportForm {
+    pub path: String,
+    pub message: Option<String>,
+    // contents of an uploaded file, once the browser has read it
+    #[cfg(target_arch = "wasm32")]
+    pub upload: Arc<Mutex<Option<String>>>,
+}
+
+impl ImportForm {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+// form: the import's form in the UI state, import: reads the file and returns a message
+fn import_form(
+    ui: &mut egui::Ui,
+    app: &mut OrbitalApp,
+    title: &str,
+    description: &str,
+    form: fn(&mut OrbitalApp) -> &mut ImportForm,
+    import: fn(&mut OrbitalApp, &str) -> String,
+) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let uploaded = form(app).upload.lock().ok().and_then(|mut u| u.take());
+        if let Some(text) = uploaded {
+            form(app).message = Some(import(app, &text));
+        }
+    }
+
+    egui::CollapsingHeader::new(title)
+        .default_open(false)
+        .show(ui, |ui| {
+            text_sized(ui, description, 12.);
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut form(app).path);
+                if ui.button("Import").clicked() {
+                    let message = match files::read_text(&form(app).path) {
+                        Ok(text) => import(app, &text),
+                        Err(e) => e,
+                    };
+                    form(app).message = Some(message);
+                }
+            });
+            #[cfg(target_arch = "wasm32")]
+            if ui.button("Upload").clicked() {
+                let upload = form(app).upload.clone();
+                let ctx = ui.ctx().clone();
+                if let Err(e) = files::open_text(upload, move || ctx.request_repaint()) {
+                    form(app).message = Some(e);
+                }
+            }
+
+            if let Some(message) = &form(app).message {
+                text_sized(ui, message, 12.);
+            }
+        });
+}
+
+fn horizons_form(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    import_form(
+        ui,
+        app,
+        "Import from JPL Horizons",
+        "Vector tables with the ecliptic reference plane, one per body, all with the same center and start time.",
+        |app| &mut app.ui_state.horizons,
+        |app, text| match app.sim.import_horizons(text) {
+            Ok(num_bodies) => format!("Imported {} bodies", num_bodies),
+            Err(e) => e.to_string(),
+        },
+    );
+}
+
+fn tle_form(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    import_form(
+        ui,
+        app,
+        "Import satellites (TLE)",
+        "Two-line element sets of Earth satellites, flattened into the equatorial plane.",
+        |app| &mut app.ui_state.tle,
+        |app, text| match app.sim.import_tle(text) {
+            Ok(num_satellites) => format!("Imported {} satellites", num_satellites),
+            Err(e) => e.to_string(),
+        },
+    );
+}
+
+// errors from the scenario files, and reading them again after editing
+fn scenario_files_section(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    for error in &app.sim.scenario_errors {
+        ui.colored_label(egui::Color32::LIGHT_RED, error.to_string());
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if ui
+        .button("Reload scenario files")
+        .on_hover_text(format!("from ./{}", super::scenario::SCENARIO_DIR))
+        .clicked()
+    {
+        app.sim.reload_scenarios();
+    }
+}
+
+fn galaxy_collision_form(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    egui::CollapsingHeader::new("Galaxy collision settings")
+        .default_open(false)
+        .show(ui, |ui| {
+            let form = &mut app.ui_state.galaxy;
+            ui.add(
+                CustomSlider::new(&mut form.impact_parameter, 0.0..=3e8)
+                    .label("Impact parameter (km):"),
+            );
+            ui.add(
+                CustomSlider::new(&mut form.relative_velocity, 0.0..=3.)
+                    .label("Relative velocity (km/s):"),
+            );
+            ui.monospace(format!(
+                "parabolic: {:.3} km/s",
+                Preset::galaxy_parabolic_velocity()
+            ))
+            .on_hover_text(
+                "Relative velocity at the starting separation that makes the cores' orbit exactly unbound. Slower gives a bound orbit and eventually a merger.",
+            );
+            if ui.button("Load").clicked() {
+                let (b, v) = (form.impact_parameter, form.relative_velocity);
+                app.sim.reset();
+                app.sim.load_galaxy_collision(b, v);
+            }
+        });
+}
+
+fn cluster_form(ui: &mut egui::Ui, app: &mut OrbitalApp) {
+    egui::CollapsingHeader::new("Star cluster settings")
+        .default_open(false)
+        .show(ui, |ui| {
+            let settings = &mut app.ui_state.cluster;
+            ui.add(egui::Slider::new(&mut settings.count, 2..=500).text("Stars"));
+            ui.add(
+                egui::Slider::new(&mut settings.softening, 0.0..=0.2)
+                    .text("Softening (Hénon units)"),
+            );
+            ui.add(egui::DragValue::new(&mut settings.seed).prefix("Seed: "));
+            if ui.button("Load").clicked() {
+                let settings = settings.clone();
+                app.sim.reset();
+                app.sim.load_cluster(&settings);
+            }
+        });
+}
+
+// ring of test particles to add around a body
+#[derive(Clone, Default)]
+pub struct RingForm {
+    pub parent: usize,
+    pub settings: RingSettings,
+}
+
+fn particles_section(ui: &mut egui::Ui, app: &mut OrbitalApp, bodies_list: &[String]) {
+    egui::CollapsingHeader::new(RichText::new("Test particles").heading())
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+                ui.monospace(format!(
+                    "{} particles, {} active",
+                    app.sim.particles.len(),
+                    app.sim.particles.num_active()
+                ));
+                ui.monospace("❓")
+                    .on_hover_cursor(egui::CursorIcon::Default)
+                    .on_hover_text(
+                        "Massless particles that are attracted to the bodies but don't attract anything. Particles that hit a body are removed.",
+                    );
+            });
+            ui.add_enabled_ui(!app.sim.started, |ui| {
+                if ui.button("Remove all").clicked() {
+                    app.sim.particles.clear();
+                }
+
+                ui.add_space(5.);
+                text_sized(ui, "Add ring to body", 14.);
+                let form = &mut app.ui_state.ring;
+                egui::ComboBox::from_label("Parent").show_index(
+                    ui,
+                    &mut form.parent,
+                    bodies_list.len(),
+                    |i| bodies_list[i].clone(),
+                );
+                let settings = &mut form.settings;
+                ui.add(
+                    CustomSlider::new(&mut settings.inner_radius, 0.0..=1e6).label("Inner r (km):"),
+                );
+                ui.add(
+                    CustomSlider::new(&mut settings.outer_radius, 0.0..=1e6).label("Outer r (km):"),
+                );
+                ui.add(egui::Slider::new(&mut settings.count, 1..=20000).text("Count"));
+                ui.add(egui::Slider::new(&mut settings.dispersion, 0.0..=0.2).text("Dispersion"));
+                ui.add(egui::DragValue::new(&mut settings.seed).prefix("Seed: "));
+                settings.outer_radius = settings.outer_radius.max(settings.inner_radius);
+
+                if ui.button("Add ring").clicked() {
+                    let (parent, settings) = (form.parent, form.settings.clone());
+                    app.sim.add_ring(parent, &settings);
+                }
+            });
+        });
+}
+
+fn lagrange_section(ui: &mut egui::Ui, app: &mut OrbitalApp, bodies_list: &[String]) {
+    egui::CollapsingHeader::new(RichText::new("Lagrange points").heading())
+        .default_open(false)
+        .show(ui, |ui| {
+            let selection = &mut app.ui_state.view.lagrange;
+            ui.checkbox(&mut selection.show, "show");
+            egui::ComboBox::from_label("Primary").show_index(
+                ui,
+                &mut selection.body1,
+                bodies_list.len(),
+                |i| bodies_list[i].clone(),
+            );
+            egui::ComboBox::from_label("Secondary").show_index(
+                ui,
+                &mut selection.body2,
+                bodies_list.len(),
+                |i| bodies_list[i].clone(),
+            );
+            let (body1, body2) = (selection.body1, selection.body2);
+
+            ui.add_enabled_ui(!app.sim.started, |ui| {
+                text_sized(ui, "Add test particle at", 12.);
+                ui.horizontal(|ui| {
+                    for point in 0..5 {
+                        if ui.button(format!("L{}", point + 1)).clicked() {
+                            app.sim.add_lagrange_particle(body1, body2, point);
+                        }
+                    }
+                });
+            });
+        });
+}
+
+const EVENT_TYPES: [&str; 4] = ["Apsis", "Closest approach", "Line crossing", "Time"];
+
+// inputs for a new event condition
+#[derive(Clone, Default)]
+pub struct EventForm {
+    kind: usize,
+    body1: usize,
+    body2: usize,
+    line_point: (f32, f32),
+    line_angle: f32, // degrees from the x axis
+    t: f32,
+}
+impl EventForm {
+    fn condition(&self) -> EventCondition {
+        match self.kind {
+            0 => EventCondition::Apsis { body: self.body1 },
+            1 => EventCondition::ClosestApproach {
+                body1: self.body1,
+                body2: self.body2,
+            },
+            2 => {
+                let angle = self.line_angle.to_radians();
+                EventCondition::LineCrossing {
+                    body: self.body1,
+                    point: Position::new(self.line_point.0, self.line_point.1),
+                    direction: Position::new(angle.cos(), angle.sin()),
+                }
+            }
+            _ => EventCondition::Time { t: self.t },
+        }
+    }
+}
+
+fn condition_label(condition: &EventCondition, bodies_list: &[String]) -> String {
+    let name = |i: usize| bodies_list.get(i).cloned().unwrap_or_default();
+    match *condition {
+        EventCondition::Apsis { body } => format!("Apsis: {}", name(body)),
+        EventCondition::ClosestApproach { body1, body2 } => {
+            format!("Closest approach: {} - {}", name(body1), name(body2))
+        }
+        EventCondition::LineCrossing {
+            body,
+            point,
+            direction,
+        } => format!(
+            "Line crossing: {} ({:.3e}, {:.3e}) {:.0}°",
+            name(body),
+            point.x,
+            point.y,
+            direction.y.atan2(direction.x).to_degrees()
+        ),
+        EventCondition::Time { t } => format!("Time: {:.4e} s", t),
+    }
+}
+
+fn events_section(
+    ui: &mut egui::Ui,
+    events: &mut Events,
+    form: &mut EventForm,
+    bodies_list: &[String],
+) {
+    egui::CollapsingHeader::new(RichText::new("Events").heading())
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.checkbox(&mut events.pause_on_event, "pause on event");
+
+            egui::ComboBox::from_label("Type").show_index(
+                ui,
+                &mut form.kind,
+                EVENT_TYPES.len(),
+                |i| EVENT_TYPES[i].to_string(),
+            );
+            if form.kind != 3 {
+                egui::ComboBox::from_label("Body").show_index(
+                    ui,
+                    &mut form.body1,
+                    bodies_list.len(),
+                    |i| bodies_list[i].clone(),
+                );
+            }
+            if form.kind == 1 {
+                egui::ComboBox::from_label("Other body").show_index(
+                    ui,
+                    &mut form.body2,
+                    bodies_list.len(),
+                    |i| bodies_list[i].clone(),
+                );
+            }
+            if form.kind == 2 {
+                text_sized(ui, "Point on line (km)", 12.);
+                ui.add(XYInput::new(
+                    &mut form.line_point.0,
+                    &mut form.line_point.1,
+                    -10000.0..=10000.,
+                    -10000.0..=10000.,
+                ));
+                ui.add(CustomSlider::new(&mut form.line_angle, 0.0..=180.).label("angle:"));
+            }
+            if form.kind == 3 {
+                ui.add(CustomSlider::new(&mut form.t, 0.0..=1e7).label("t (s):"));
+            }
+            if ui.button("Add condition").clicked() {
+                events.add_condition(form.condition());
+            }
+
+            let mut remove = None;
+            for (i, condition) in events.conditions.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.monospace(condition_label(condition, bodies_list));
+                });
+            }
+            if let Some(i) = remove {
+                events.remove_condition(i);
+            }
+        });
+}
+
+pub fn events_window(app: &mut OrbitalApp, ctx: &egui::Context) {
+    if app.sim.events.conditions.is_empty() && app.sim.events.log.is_empty() {
+        return;
+    }
+
+    egui::Window::new("Event log")
+        .enabled(!app.is_running())
+        .anchor(egui::Align2::RIGHT_TOP, [-10., 10.])
+        .default_width(360.)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} events", app.sim.events.log.len()));
+                if ui.button("Clear").clicked() {
+                    app.sim.events.log.clear();
+                }
+            });
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(400.)
+                .show(ui, |ui| {
+                    for event in app.sim.events.log.iter().rev() {
+                        text_sized(
+                            ui,
+                            &format!("t: {:.6e} s  {}", event.t, event.description()),
+                            12.,
+                        );
+                        if let Some(distance) = event.distance() {
+                            text_sized(ui, &format!("    d: {:.6e} km", distance), 12.);
+                        }
+                        for b in event.bodies.iter() {
+                            text_sized(
+                                ui,
+                                &format!(
+                                    "    {}: r ({:+.4e}, {:+.4e}) v ({:+.4e}, {:+.4e})",
+                                    b.name,
+                                    b.absolute_pos.x,
+                                    b.absolute_pos.y,
+                                    b.absolute_vel.x,
+                                    b.absolute_vel.y
+                                ),
+                                12.,
+                            );
+                        }
+                    }
+                });
+        });
+}
+
+fn poincare_section(ui: &mut egui::Ui, section: &mut PoincareSection, bodies_list: &[String]) {
+    egui::CollapsingHeader::new(RichText::new("Poincaré section").heading())
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.checkbox(&mut section.enabled, "record crossings");
+            egui::ComboBox::from_label("Body").show_index(
+                ui,
+                &mut section.body,
+                bodies_list.len(),
+                |i| bodies_list[i].clone(),
+            );
+
+            let mut rotating = matches!(section.frame, SectionFrame::Rotating { .. });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut rotating, false, "Inertial");
+                ui.radio_value(&mut rotating, true, "Co-rotating");
+            });
+            match (&mut section.frame, rotating) {
+                (SectionFrame::Rotating { body1, body2 }, true) => {
+                    egui::ComboBox::from_label("Rotate with").show_index(
+                        ui,
+                        body1,
+                        bodies_list.len(),
+                        |i| bodies_list[i].clone(),
+                    );
+                    egui::ComboBox::from_label("and").show_index(
+                        ui,
+                        body2,
+                        bodies_list.len(),
+                        |i| bodies_list[i].clone(),
+                    );
+                }
+                (SectionFrame::Inertial, true) => {
+                    section.frame = SectionFrame::Rotating { body1: 0, body2: 1 };
+                }
+                (_, false) => section.frame = SectionFrame::Inertial,
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Crossing");
+                ui.radio_value(&mut section.axis, Axis::X, "y = 0");
+                ui.radio_value(&mut section.axis, Axis::Y, "x = 0");
+            });
+            let other_velocity = match section.axis {
+                Axis::X => "vy",
+                Axis::Y => "vx",
+            };
+            ui.horizontal(|ui| {
+                ui.radio_value(
+                    &mut section.positive,
+                    true,
+                    format!("{} > 0", other_velocity),
+                );
+                ui.radio_value(
+                    &mut section.positive,
+                    false,
+                    format!("{} < 0", other_velocity),
+                );
+            });
+
+            let labels = Coordinate::ALL.map(|c| c.label());
+            for (label, coordinate) in [
+                ("Horizontal", &mut section.plot.0),
+                ("Vertical", &mut section.plot.1),
+            ] {
+                let mut selected = Coordinate::ALL
+                    .iter()
+                    .position(|c| c == coordinate)
+                    .unwrap_or(0);
+                egui::ComboBox::from_label(label).show_index(
+                    ui,
+                    &mut selected,
+                    labels.len(),
+                    |i| labels[i].to_string(),
+                );
+                *coordinate = Coordinate::ALL[selected];
+            }
+            ui.monospace(format!("{} points", section.points.len()));
+        });
+}
+
+#[derive(Clone)]
+pub struct PoincareForm {
+    // file name on native, name of the downloaded file on the web
+    pub file_name: String,
+    pub message: Option<String>,
+}
+
+impl Default for PoincareForm {
+    fn default() -> Self {
+        Self {
+            file_name: "poincare_section.csv".to_string(),
+            message: None,
+        }
+    }
+}
+
+pub fn poincare_window(app: &mut OrbitalApp, ctx: &egui::Context) {
+    let idle = !app.is_running();
+    let form = &mut app.ui_state.poincare;
+    let section = &mut app.sim.poincare;
+    if !section.enabled && section.points.is_empty() {
+        return;
+    }
+
+    egui::Window::new("Poincaré section")
+        .enabled(idle)
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10., -10.])
+        .default_width(320.)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} points", section.points.len()));
+                if ui.button("Clear").clicked() {
+                    section.points.clear();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut form.file_name);
+                if ui.button("Save CSV").clicked() {
+                    form.message =
+                        Some(match files::save_text(&form.file_name, &section.to_csv()) {
+                            Ok(()) => format!(
+                                "Saved {} points to {}",
+                                section.points.len(),
+                                form.file_name
+                            ),
+                            Err(e) => e,
+                        });
+                }
+            });
+            if let Some(message) = &form.message {
+                text_sized(ui, message, 12.);
+            }
+            ui.separator();
+            scatter_plot(ui, section);
+        });
+}
+
+fn scatter_plot(ui: &mut egui::Ui, section: &PoincareSection) {
+    let (horizontal, vertical) = section.plot;
+    let size = egui::vec2(300., 300.);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(
+        rect,
+        0.,
+        egui::Stroke::new(1., egui::Color32::GRAY),
+        egui::StrokeKind::Inside,
+    );
+
+    let values: Vec<(f32, f32)> = section
+        .points
+        .iter()
+        .map(|p| (p.get(horizontal), p.get(vertical)))
+        .collect();
+    let range = |values: &mut dyn Iterator<Item = f32>| {
+        let (min, max) = values.fold((f32::MAX, f32::MIN), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+        // pad so that points aren't on the border, and a single value doesn't divide by 0
+        let padding = ((max - min) * 0.05).max(max.abs() * 1e-6).max(1e-9);
+        (min - padding, max + padding)
+    };
+    let (x_min, x_max) = range(&mut values.iter().map(|v| v.0));
+    let (y_min, y_max) = range(&mut values.iter().map(|v| v.1));
+
+    for &(x, y) in values.iter() {
+        let screen_x = rect.left() + (x - x_min) / (x_max - x_min) * rect.width();
+        let screen_y = rect.bottom() - (y - y_min) / (y_max - y_min) * rect.height();
+        painter.circle_filled(
+            egui::pos2(screen_x, screen_y),
+            1.5,
+            egui::Color32::LIGHT_BLUE,
+        );
+    }
+
+    if !values.is_empty() {
+        text_sized(
+            ui,
+            &format!("{}: {:.4e} to {:.4e}", horizontal.label(), x_min, x_max),
+            12.,
+        );
+        text_sized(
+            ui,
+            &format!("{}: {:.4e} to {:.4e}", vertical.label(), y_min, y_max),
+            12.,
+        );
+    }
 }
 
 fn text_sized(ui: &mut egui::Ui, text: &str, size: f32) {
     ui.monospace(RichText::new(text).size(size));
 }
 
-pub fn controls_panel(app: &mut Orbital, ctx: &egui::Context) {
+pub fn controls_panel(app: &mut OrbitalApp, ctx: &egui::Context) {
     let available_rect = ctx.screen_rect();
     let x = available_rect.left() + app.ui_state.panel_width;
     let y = available_rect.top();
@@ -0,0 +1,319 @@
+// Drawing the simulation to any DrawTarget: the window's canvas, or an SVG figure.
+// What's shown besides the bodies and trajectories (the reference frame, Lagrange points)
+// is chosen in the View, which the UI edits.
+
+use std::collections::HashMap;
+
+use petgraph::graph::NodeIndex;
+
+use crate::app::core::{
+    contour::zero_contour,
+    draw::{
+        draw_body, draw_line_thru_positions, draw_marker, draw_points, draw_segments,
+        draw_styled_line_thru_positions, draw_text, draw_tick_marks, DrawTarget, LineStyle,
+    },
+    physics::{effective_potential, Position},
+    svg::Svg,
+};
+
+use super::{
+    body::Body,
+    branch::Branch,
+    frame::{graphed_positions, trajectory_in_frame, JacobiSubject, RotatingFrame},
+    Orbital,
+};
+
+// reference frame for the view
+#[derive(Clone, Default)]
+pub struct FrameSelection {
+    pub corotating: bool,
+    pub body1: usize,
+    pub body2: usize,
+    pub show_zero_velocity: bool,
+    pub jacobi_of: JacobiSubject,
+}
+
+// pair of bodies to show the Lagrange points for
+#[derive(Clone, Default)]
+pub struct LagrangeSelection {
+    pub show: bool,
+    pub body1: usize,
+    pub body2: usize,
+}
+
+#[derive(Clone)]
+pub struct View {
+    pub frame: FrameSelection,
+    pub lagrange: LagrangeSelection,
+    // px on the left of the target that are covered, e.g. by the side panel, so the axis labels start after them
+    pub covered_left: f32,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            frame: FrameSelection {
+                body2: 1,
+                ..Default::default()
+            },
+            lagrange: LagrangeSelection {
+                body2: 1,
+                ..Default::default()
+            },
+            covered_left: 0.,
+        }
+    }
+}
+
+// Branch trajectories are drawn over the main ones in the body colors, so they alternate styles instead.
+pub fn branch_style(index: usize) -> LineStyle {
+    if index.is_multiple_of(2) {
+        LineStyle::Dashed
+    } else {
+        LineStyle::Dotted
+    }
+}
+
+impl Orbital {
+    pub fn draw<D: DrawTarget>(&self, canvas: &mut D, view: &View) {
+        let (x_distance_range, y_distance_range) = self.distance_range(canvas, view);
+        draw_tick_marks(
+            canvas,
+            x_distance_range,
+            y_distance_range,
+            self.distance_per_px,
+        );
+
+        let ticks_per_graph_point = self.ticks_per_graph_point();
+        let frame_pair = self.frame_pair(view);
+
+        for b in self.bodies_vec().iter() {
+            // only up to the tick being shown
+            let recorded = self.recorded_trajectory(b);
+            let display_body = Body {
+                absolute_pos: self.display_pos(b.absolute_pos, view),
+                ..b.copy()
+            };
+            draw_body(canvas, &display_body, self.distance_per_px);
+
+            let positions = match frame_pair {
+                Some((body1, body2)) => {
+                    trajectory_in_frame(recorded, body1, body2, ticks_per_graph_point)
+                }
+                None => graphed_positions(recorded, ticks_per_graph_point),
+            };
+            draw_line_thru_positions(canvas, &positions, self.distance_per_px, b.color);
+
+            if !b.is_barycenter {
+                draw_text(
+                    canvas,
+                    b.name.clone(),
+                    &display_body.absolute_pos,
+                    self.distance_per_px,
+                );
+            }
+        }
+
+        for (i, branch) in self.branches.iter().enumerate() {
+            self.draw_branch(canvas, branch, branch_style(i), view);
+        }
+
+        self.draw_particles(canvas, view);
+
+        let lagrange = &view.lagrange;
+        if lagrange.show {
+            if let Some(points) = self.lagrange_points(lagrange.body1, lagrange.body2) {
+                for (i, point) in points.iter().enumerate() {
+                    let display_point = self.display_pos(*point, view);
+                    draw_marker(canvas, &display_point, self.distance_per_px, (255, 0, 255));
+                    draw_text(
+                        canvas,
+                        format!("L{}", i + 1),
+                        &display_point,
+                        self.distance_per_px,
+                    );
+                }
+            }
+        }
+
+        if view.frame.show_zero_velocity {
+            let segments = self.zero_velocity_curve(x_distance_range, y_distance_range, view);
+            draw_segments(canvas, &segments, self.distance_per_px, (0, 200, 255));
+        }
+    }
+
+    // An SVG image of the bodies and their trajectories so far, zoomed to fit them.
+    pub fn svg(&self, view: &View, width: f32, height: f32) -> String {
+        let mut svg = Svg::fit(width, height, self.drawn_area(view), 40.);
+        self.draw(&mut svg, view);
+        svg.finish()
+    }
+
+    fn ticks_per_graph_point(&self) -> usize {
+        (self.draw_frequency as f32 / self.dt).ceil() as usize
+    }
+
+    // canvas units (top left, bottom right) that all the bodies and trajectories are in
+    pub(super) fn drawn_area(&self, view: &View) -> (Position, Position) {
+        let ticks_per_graph_point = self.ticks_per_graph_point();
+        let frame_pair = self.frame_pair(view);
+        let mut positions = vec![];
+        for b in self.bodies_vec().iter().filter(|b| !b.is_barycenter) {
+            let recorded = self.recorded_trajectory(b);
+            match frame_pair {
+                Some((body1, body2)) => positions.extend(trajectory_in_frame(
+                    recorded,
+                    body1,
+                    body2,
+                    ticks_per_graph_point,
+                )),
+                None => positions.extend(recorded.iter().map(|b| b.absolute_pos)),
+            }
+            positions.push(self.display_pos(b.absolute_pos, view));
+        }
+
+        let (mut min, mut max) = (
+            Position::new(f32::MAX, f32::MAX),
+            Position::new(f32::MIN, f32::MIN),
+        );
+        for pos in positions {
+            // y is down on the canvas
+            let (x, y) = (pos.x / self.distance_per_px, -pos.y / self.distance_per_px);
+            min = Position::new(min.x.min(x), min.y.min(y));
+            max = Position::new(max.x.max(x), max.y.max(y));
+        }
+        if min.x > max.x {
+            return (Position::new(-1., -1.), Position::new(1., 1.));
+        }
+        (min, max)
+    }
+
+    // x and y distances (km) that are on screen
+    fn distance_range<D: DrawTarget>(&self, canvas: &D, view: &View) -> ((f32, f32), (f32, f32)) {
+        let (top_left, bottom_right) = canvas.visible_area();
+        let min_x_px = top_left.x + view.covered_left / canvas.scale();
+        let x_range = (
+            min_x_px * self.distance_per_px,
+            bottom_right.x * self.distance_per_px,
+        );
+        // y is up in distances and down on the canvas
+        let y_range = (
+            -bottom_right.y * self.distance_per_px,
+            -top_left.y * self.distance_per_px,
+        );
+        (x_range, y_range)
+    }
+
+    // the pair of bodies that the view rotates with, if the co-rotating frame is selected
+    fn frame_pair(&self, view: &View) -> Option<(&Body, &Body)> {
+        let frame = &view.frame;
+        if !frame.corotating || frame.body1 == frame.body2 {
+            return None;
+        }
+        let body1 = self.hierarchy.node_weight(NodeIndex::new(frame.body1))?;
+        let body2 = self.hierarchy.node_weight(NodeIndex::new(frame.body2))?;
+        Some((body1, body2))
+    }
+
+    // the co-rotating frame selected for the view
+    fn view_frame(&self, view: &View) -> Option<RotatingFrame> {
+        let frame = &view.frame;
+        if !frame.corotating {
+            return None;
+        }
+        self.rotating_frame(frame.body1, frame.body2)
+    }
+
+    // convert a current absolute position to the position in the selected reference frame
+    pub fn display_pos(&self, pos: Position, view: &View) -> Position {
+        match self.view_frame(view) {
+            Some(frame) => frame.position(pos),
+            None => pos,
+        }
+    }
+
+    // bodies and trajectories of a branch drawn over this simulation, in the same reference frame
+    fn draw_branch<D: DrawTarget>(
+        &self,
+        canvas: &mut D,
+        branch: &Branch,
+        style: LineStyle,
+        view: &View,
+    ) {
+        let ticks_per_graph_point = self.ticks_per_graph_point();
+        let sim = &branch.sim;
+        let frame_pair = sim.frame_pair(view);
+        for b in sim.bodies_vec().iter().filter(|b| !b.is_barycenter) {
+            let recorded = sim.recorded_trajectory(b);
+            let positions = match frame_pair {
+                Some((body1, body2)) => {
+                    trajectory_in_frame(recorded, body1, body2, ticks_per_graph_point)
+                }
+                None => graphed_positions(recorded, ticks_per_graph_point),
+            };
+            draw_styled_line_thru_positions(
+                canvas,
+                &positions,
+                self.distance_per_px,
+                b.color,
+                style,
+            );
+
+            let display_body = Body {
+                absolute_pos: sim.display_pos(b.absolute_pos, view),
+                ..b.copy()
+            };
+            draw_body(canvas, &display_body, self.distance_per_px);
+        }
+    }
+
+    fn draw_particles<D: DrawTarget>(&self, canvas: &mut D, view: &View) {
+        let view_frame = self.view_frame(view);
+        let mut by_color: HashMap<(u8, u8, u8), Vec<Position>> = HashMap::new();
+
+        for i in (0..self.particles.len()).filter(|&i| self.particles.active[i]) {
+            let pos = self.particles.position(i);
+            let display_pos = match view_frame {
+                Some(frame) => frame.position(pos),
+                None => pos,
+            };
+            by_color
+                .entry(self.particles.color[i])
+                .or_default()
+                .push(display_pos);
+        }
+        for (color, positions) in by_color.iter() {
+            draw_points(canvas, positions, self.distance_per_px, *color);
+        }
+    }
+
+    // Zero-velocity curve of the selected particle or body within the visible area, in the co-rotating frame.
+    // The particle can't cross this curve because its velocity would have to be imaginary.
+    fn zero_velocity_curve(
+        &self,
+        x_range: (f32, f32),
+        y_range: (f32, f32),
+        view: &View,
+    ) -> Vec<(Position, Position)> {
+        let selection = &view.frame;
+        let (Some(frame), Some(jacobi)) = (
+            self.view_frame(view),
+            self.jacobi_constant(selection.body1, selection.body2, selection.jacobi_of),
+        ) else {
+            return vec![];
+        };
+        let (m1, m2) = frame.masses();
+        let (pos1, pos2) = frame.body_positions();
+
+        zero_contour(x_range, y_range, 200, 125, |x, y| {
+            effective_potential(
+                m1,
+                pos1,
+                m2,
+                pos2,
+                frame.angular_velocity(),
+                Position::new(x, y),
+            ) - jacobi
+        })
+    }
+}
@@ -5,7 +5,7 @@ use super::{
         eq_tolerance, midpoint,
         physics::{Acceleration, Position, Velocity},
     },
-    App,
+    App, Simulation,
 };
 
 pub struct ConstAcceleration {
@@ -16,7 +16,7 @@ pub struct ConstAcceleration {
     a: Acceleration,
     t_per_tick: f32,
 }
-impl App for ConstAcceleration {
+impl Simulation for ConstAcceleration {
     fn run(&mut self) {
         if self.started {
             while self.hist.len() < 500 && self.current_pos().y >= 0. {
@@ -30,7 +30,9 @@ impl App for ConstAcceleration {
             self.analyze();
         }
     }
+}
 
+impl App for ConstAcceleration {
     fn draw(&self, canvas: &mut Canvas<WGPURenderer>) {
         let mut dots = Path::new();
         let history = &self.hist;
@@ -0,0 +1,429 @@
+// Runs a simulation without a window, for scripts and CI machines without a GPU.
+// Prints a summary at the end, and can write the same exports as the UI.
+//
+// cargo run --bin grav-cli -- --preset "Three body" --duration 100000 --max-drift 0.1
+
+use std::{process::ExitCode, time::Instant};
+
+use grav::app::{
+    core::physics::Integrator,
+    orbital::{
+        export::{ExportFormat, ExportSettings},
+        scenario,
+        view::View,
+        Orbital,
+    },
+    Simulation,
+};
+#[cfg(feature = "raster")]
+use grav::app::{core::raster::Gif, orbital::snapshot::FrameSettings};
+
+const USAGE: &str = "Usage: grav-cli [options]
+
+What to run (the first preset by default):
+  --preset <name or number>   one of the presets, see --list
+  --scenario <file.toml>      a scenario file
+  --horizons <file>           JPL Horizons vector tables
+  --tle <file>                two-line element sets of Earth satellites
+  --list                      list the presets and exit
+
+How to run:
+  --duration <s>              simulated time, one press of the preset by default
+  --dt <s>                    time step, the preset's by default
+  --integrator <name>         euler (symplectic Euler, default) or verlet (velocity Verlet)
+  --escapes                   classify bound and escaping bodies, and log escapes and captures
+  --serial                    calculate on one thread, the results are the same (parallel feature)
+
+Output:
+  --export <name>             write <name>_bodies and <name>_metrics
+  --format <csv|jsonl>        export format, csv by default
+  --interval <s>              simulated time between exported samples, every tick by default
+  --svg <file.svg>            draw the bodies and trajectories as an SVG figure
+  --png <file.png>            draw them as a PNG image
+  --frames <name>             draw a frame every --frame-interval as <name>_0000.png, <name>_0001.png, ...
+  --gif <file.gif>            draw the frames as an animated GIF
+  --frame-interval <s>        simulated time between frames, 100 frames over the run by default
+  --fps <n>                   frames per second of the GIF, 25 by default
+  --size <width>x<height>     size of the images in px, 1200x900 by default
+  --max-drift <percent>       exit with an error if the energy drifts more than this
+  --quiet                     only print errors";
+
+// most ticks per run, so a long duration doesn't build up one huge batch
+const TICKS_PER_RUN: i32 = 10_000;
+
+#[derive(Default)]
+struct Options {
+    preset: Option<String>,
+    scenario: Option<String>,
+    horizons: Option<String>,
+    tle: Option<String>,
+    list: bool,
+    duration: Option<f32>,
+    dt: Option<f32>,
+    integrator: Integrator,
+    escapes: bool,
+    serial: bool,
+    export: Option<String>,
+    export_settings: ExportSettings,
+    svg: Option<String>,
+    png: Option<String>,
+    frames: Option<String>,
+    gif: Option<String>,
+    frame_interval: Option<f32>,
+    fps: f32,
+    size: (f32, f32),
+    max_drift: Option<f64>,
+    quiet: bool,
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{}: {} isn't a number", flag, value))
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        size: (1200., 900.),
+        fps: 25.,
+        ..Default::default()
+    };
+    let mut args = args.peekable();
+
+    while let Some(flag) = args.next() {
+        // flags without a value
+        match flag.as_str() {
+            "--list" => {
+                options.list = true;
+                continue;
+            }
+            "--escapes" => {
+                options.escapes = true;
+                continue;
+            }
+            "--serial" => {
+                options.serial = true;
+                continue;
+            }
+            "--quiet" => {
+                options.quiet = true;
+                continue;
+            }
+            "--help" | "-h" => return Err(String::new()),
+            _ => {}
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--preset" => options.preset = Some(value),
+            "--scenario" => options.scenario = Some(value),
+            "--horizons" => options.horizons = Some(value),
+            "--tle" => options.tle = Some(value),
+            "--duration" => options.duration = Some(parse_number(&flag, &value)?),
+            "--dt" => options.dt = Some(parse_number(&flag, &value)?),
+            "--integrator" => {
+                options.integrator = match value.as_str() {
+                    "euler" => Integrator::SymplecticEuler,
+                    "verlet" => Integrator::VelocityVerlet,
+                    _ => return Err(format!("unknown integrator {}", value)),
+                }
+            }
+            "--export" => options.export = Some(value),
+            "--format" => {
+                options.export_settings.format = match value.as_str() {
+                    "csv" => ExportFormat::Csv,
+                    "jsonl" => ExportFormat::JsonLines,
+                    _ => return Err(format!("unknown format {}", value)),
+                }
+            }
+            "--interval" => options.export_settings.interval = parse_number(&flag, &value)?,
+            "--svg" => options.svg = Some(value),
+            "--png" => options.png = Some(value),
+            "--frames" => options.frames = Some(value),
+            "--gif" => options.gif = Some(value),
+            "--frame-interval" => options.frame_interval = Some(parse_number(&flag, &value)?),
+            "--fps" => options.fps = parse_number(&flag, &value)?,
+            "--size" => {
+                let (width, height) = value
+                    .split_once('x')
+                    .ok_or_else(|| format!("--size: {} isn't <width>x<height>", value))?;
+                options.size = (parse_number(&flag, width)?, parse_number(&flag, height)?);
+            }
+            "--max-drift" => options.max_drift = Some(parse_number(&flag, &value)?),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+
+    let sources = [
+        &options.preset,
+        &options.scenario,
+        &options.horizons,
+        &options.tle,
+    ];
+    if sources.iter().filter(|s| s.is_some()).count() > 1 {
+        return Err(
+            "only one of --preset, --scenario, --horizons and --tle can be used".to_string(),
+        );
+    }
+    if options.dt.is_some_and(|dt| dt <= 0.) || options.duration.is_some_and(|d| d < 0.) {
+        return Err("--dt must be positive and --duration can't be negative".to_string());
+    }
+    if options.size.0 < 1. || options.size.1 < 1. || options.fps <= 0. {
+        return Err("--size and --fps must be positive".to_string());
+    }
+    Ok(options)
+}
+
+fn read(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+// sets up the bodies from the options, returns the name of what's loaded
+fn load(app: &mut Orbital, options: &Options) -> Result<String, String> {
+    if let Some(path) = &options.scenario {
+        let preset = scenario::parse(path, &read(path)?).map_err(|e| e.to_string())?;
+        app.load_scenario(&preset);
+        return Ok(preset.name);
+    }
+    if let Some(path) = &options.horizons {
+        app.import_horizons(&read(path)?)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        return Ok(path.clone());
+    }
+    if let Some(path) = &options.tle {
+        app.import_tle(&read(path)?)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        return Ok(path.clone());
+    }
+
+    let presets = app.presets();
+    let index = match &options.preset {
+        None => 0,
+        // numbered from 1 like --list
+        Some(preset) => match preset.parse::<usize>() {
+            Ok(number) if (1..=presets.len()).contains(&number) => number - 1,
+            _ => presets
+                .iter()
+                .position(|p| p.name.eq_ignore_ascii_case(preset))
+                .ok_or_else(|| format!("no preset {}, see --list", preset))?,
+        },
+    };
+    let name = presets[index].name.clone();
+    app.load_preset(index);
+    Ok(name)
+}
+
+fn run(options: &Options) -> Result<ExitCode, String> {
+    let mut app = Orbital::new();
+    for error in &app.scenario_errors {
+        eprintln!("skipped scenario {}", error);
+    }
+    if options.list {
+        for (i, preset) in app.presets().iter().enumerate() {
+            println!("{:>3}  {}", i + 1, preset.name);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let name = load(&mut app, options)?;
+    if let Some(dt) = options.dt {
+        app.dt = dt;
+    }
+    app.integrator = options.integrator;
+    app.escapes.enabled = options.escapes;
+    app.parallel = !options.serial;
+    let duration = options.duration.unwrap_or(app.num_ticks as f32 * app.dt);
+
+    app.prepare();
+    app.start();
+    let num_bodies = app.bodies_vec().iter().filter(|b| !b.is_barycenter).count();
+    if !options.quiet {
+        println!(
+            "{}: {} bodies, dt = {} s, {}",
+            name,
+            num_bodies,
+            app.dt,
+            app.integrator.name()
+        );
+    }
+
+    let started = Instant::now();
+    let end_t = app.t + duration;
+    let mut ticks = 0;
+    while app.t < end_t && !app.is_stopped() {
+        let remaining = ((end_t - app.t) / app.dt).ceil() as i32;
+        app.num_ticks = remaining.clamp(1, TICKS_PER_RUN);
+        let t = app.t;
+        app.run();
+        ticks += ((app.t - t) / app.dt).round() as i64;
+        if app.t == t {
+            break;
+        }
+    }
+    let elapsed = started.elapsed();
+
+    let drift = app.analysis.diff_percentage;
+    if !options.quiet {
+        print_summary(&app, ticks, elapsed.as_secs_f32());
+    }
+
+    if let Some(export_name) = &options.export {
+        let export = app.export(&options.export_settings);
+        let (bodies_file, metrics_file) = export.save(export_name)?;
+        if !options.quiet {
+            println!("\nwrote {} and {}", bodies_file, metrics_file);
+        }
+    }
+
+    if let Some(path) = &options.svg {
+        let (width, height) = options.size;
+        std::fs::write(path, app.svg(&View::default(), width, height))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        if !options.quiet {
+            println!("\nwrote {}", path);
+        }
+    }
+
+    write_images(&mut app, options, duration)?;
+
+    match options.max_drift {
+        Some(max_drift) if drift.abs() > max_drift => {
+            eprintln!(
+                "energy drift {:.6} % is more than --max-drift {} %",
+                drift, max_drift
+            );
+            Ok(ExitCode::from(2))
+        }
+        _ => Ok(ExitCode::SUCCESS),
+    }
+}
+
+// PNG snapshot and animation frames, drawn after running
+#[cfg(feature = "raster")]
+fn write_images(app: &mut Orbital, options: &Options, duration: f32) -> Result<(), String> {
+    let (width, height) = (options.size.0 as u32, options.size.1 as u32);
+    let write = |path: &str, bytes: &[u8]| {
+        std::fs::write(path, bytes).map_err(|e| format!("{}: {}", path, e))
+    };
+
+    if let Some(path) = &options.png {
+        write(path, &app.png(&View::default(), width, height)?)?;
+        if !options.quiet {
+            println!("\nwrote {}", path);
+        }
+    }
+
+    if options.frames.is_none() && options.gif.is_none() {
+        return Ok(());
+    }
+    let settings = FrameSettings {
+        width,
+        height,
+        interval: options.frame_interval.unwrap_or(duration / 100.),
+    };
+    let mut gif = match &options.gif {
+        Some(path) => {
+            let file = std::fs::File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+            Some(Gif::new(std::io::BufWriter::new(file), width, height)?)
+        }
+        None => None,
+    };
+    // hundredths of a second
+    let delay = (100. / options.fps).round().max(1.) as u16;
+    let mut index = 0;
+    let num_frames = app.frames(&View::default(), &settings, |raster| {
+        if let Some(name) = &options.frames {
+            write(&format!("{}_{:04}.png", name, index), &raster.png()?)?;
+        }
+        if let Some(gif) = gif.as_mut() {
+            gif.add_frame(raster, delay)?;
+        }
+        index += 1;
+        Ok(())
+    })?;
+    // finishes writing the GIF
+    drop(gif);
+
+    if !options.quiet {
+        for path in [&options.frames, &options.gif].into_iter().flatten() {
+            println!("\nwrote {} frames to {}", num_frames, path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "raster"))]
+fn write_images(_app: &mut Orbital, options: &Options, _duration: f32) -> Result<(), String> {
+    if options.png.is_some() || options.frames.is_some() || options.gif.is_some() {
+        return Err("--png, --frames and --gif need the raster feature".to_string());
+    }
+    Ok(())
+}
+
+fn print_summary(app: &Orbital, ticks: i64, seconds: f32) {
+    println!(
+        "ran {} ticks to t = {} s in {:.2} s{}",
+        ticks,
+        app.t,
+        seconds,
+        if app.is_stopped() {
+            ", stopped by a collision"
+        } else {
+            ""
+        }
+    );
+
+    let analysis = &app.analysis;
+    println!(
+        "\nenergy (MJ): initial {:e}, final {:e} (kinetic {:e}, potential {:e})",
+        analysis.initial_e, analysis.total_e, analysis.kinetic_e, analysis.gravitational_e
+    );
+    println!("energy drift: {:.6} %", analysis.diff_percentage);
+
+    println!("\nfinal states (km, km/s):");
+    println!(
+        "{:<24} {:>14} {:>14} {:>12} {:>12}",
+        "body", "x", "y", "vx", "vy"
+    );
+    for body in app.bodies_vec().iter().filter(|b| !b.is_barycenter) {
+        println!(
+            "{:<24} {:>14.6e} {:>14.6e} {:>12.6e} {:>12.6e}",
+            body.name,
+            body.absolute_pos.x,
+            body.absolute_pos.y,
+            body.absolute_vel.x,
+            body.absolute_vel.y
+        );
+    }
+
+    if !app.events.log.is_empty() {
+        println!("\nevents:");
+        for event in &app.events.log {
+            println!("{:>14.1} s  {}", event.t, event.description());
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    env_logger::builder().format_timestamp(None).init();
+
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&options) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use femtovg::{renderer::WGPURenderer, Canvas, Color, Paint, Path};
 use std::sync::Arc;
+use web_time::Instant;
 use winit::{
     application::ApplicationHandler,
     event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
@@ -10,7 +11,7 @@ use winit::{
 
 use crate::{
     app::{
-        core::draw::{get_scale, scaled_width},
+        core::{canvas::get_scale, draw::scaled_width},
         App,
     },
     helpers::wgpu::WgpuWindowSurface,
@@ -23,6 +24,8 @@ pub struct AppHandler<A: App> {
     dragging: bool,
     close_requested: bool,
     next_tick: bool,
+    // when the last frame that animated was drawn
+    last_animated: Option<Instant>,
     app: A,
     window: Arc<Window>,
     canvas: Canvas<WGPURenderer>,
@@ -47,6 +50,7 @@ impl<A: App> AppHandler<A> {
             dragging: false,
             close_requested: false,
             next_tick: false,
+            last_animated: None,
             app,
         }
     }
@@ -220,6 +224,18 @@ impl<A: App> ApplicationHandler for AppHandler<A> {
 
                 // both
                 surface_texture.present();
+
+                // the next frame shows the app after it moved on by itself, e.g. playback
+                let now = Instant::now();
+                let elapsed = self
+                    .last_animated
+                    .map_or(0., |last| now.duration_since(last).as_secs_f32());
+                if self.app.animate(elapsed) {
+                    self.last_animated = Some(now);
+                    window.request_redraw();
+                } else {
+                    self.last_animated = None;
+                }
             }
             WindowEvent::CloseRequested => {
                 event_loop.exit();
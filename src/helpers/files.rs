@@ -0,0 +1,115 @@
+// Saving and opening text files, and saving images.
+// Natively these are files on disk. In the browser there's no file system, so saving downloads
+// the file and opening asks for a file to upload, which is read asynchronously.
+
+#[cfg(target_arch = "wasm32")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_text(path: &str, contents: &str) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_bytes(path: &str, contents: &[u8]) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_text(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(value: wasm_bindgen::JsValue) -> String {
+    format!("{:?}", value)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn document() -> Result<web_sys::Document, String> {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("no document".to_string())
+}
+
+// downloads the contents as a file with this name
+#[cfg(target_arch = "wasm32")]
+pub fn save_text(file_name: &str, contents: &str) -> Result<(), String> {
+    let parts = js_sys::Array::of1(&wasm_bindgen::JsValue::from_str(contents));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("text/plain");
+    let blob =
+        web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).map_err(js_error)?;
+    download(file_name, &blob)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_bytes(file_name: &str, contents: &[u8]) -> Result<(), String> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(js_error)?;
+    download(file_name, &blob)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn download(file_name: &str, blob: &web_sys::Blob) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+
+    let url = web_sys::Url::create_object_url_with_blob(blob).map_err(js_error)?;
+
+    let anchor: web_sys::HtmlAnchorElement = document()?
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(|_| "not an anchor element".to_string())?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    web_sys::Url::revoke_object_url(&url).map_err(js_error)
+}
+
+// Opens the browser's file picker. The contents are put into `slot` once the file has been read,
+// then `repaint` is called so the next frame picks them up without waiting for input.
+#[cfg(target_arch = "wasm32")]
+pub fn open_text(
+    slot: Arc<Mutex<Option<String>>>,
+    repaint: impl Fn() + Clone + 'static,
+) -> Result<(), String> {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let input: web_sys::HtmlInputElement = document()?
+        .create_element("input")
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(|_| "not an input element".to_string())?;
+    input.set_type("file");
+
+    let picker = input.clone();
+    let on_change = Closure::<dyn FnMut()>::new(move || {
+        let Some(file) = picker.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        let Ok(reader) = web_sys::FileReader::new() else {
+            return;
+        };
+        let result = reader.clone();
+        let slot = slot.clone();
+        let repaint = repaint.clone();
+        let on_load = Closure::<dyn FnMut()>::new(move || {
+            if let Some(text) = result.result().ok().and_then(|r| r.as_string()) {
+                if let Ok(mut slot) = slot.lock() {
+                    *slot = Some(text);
+                }
+                repaint();
+            }
+        });
+        reader.set_onload(Some(on_load.as_ref().unchecked_ref()));
+        // the callbacks have to live until the browser calls them
+        on_load.forget();
+        let _ = reader.read_as_text(&file);
+    });
+    input.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+    on_change.forget();
+    input.click();
+    Ok(())
+}
@@ -1,10 +1,16 @@
+#[cfg(feature = "gui")]
 use femtovg::{Canvas, Renderer};
+#[cfg(feature = "gui")]
 use resource::resource;
 
+#[cfg(feature = "gui")]
 use crate::app::App;
 
+pub mod files;
+#[cfg(feature = "gui")]
 pub mod wgpu;
 
+#[cfg(feature = "gui")]
 pub fn start<A: App + 'static>(
     app: A,
     #[cfg(not(target_arch = "wasm32"))] width: u32,
@@ -17,6 +23,7 @@ pub fn start<A: App + 'static>(
     wasm_bindgen_futures::spawn_local(wgpu::start_wgpu(app));
 }
 
+#[cfg(feature = "gui")]
 pub fn init_canvas<T: Renderer>(canvas: &mut Canvas<T>) {
     canvas
         .add_font_mem(&resource!("assets/Roboto-Regular.ttf"))
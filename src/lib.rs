@@ -1,7 +1,13 @@
 pub mod app;
+#[cfg(feature = "gui")]
 pub mod handler;
 pub mod helpers;
+#[cfg(feature = "gui")]
 pub mod ui;
 
 pub use app::orbital::Orbital;
+#[cfg(feature = "gui")]
+pub use app::orbital::OrbitalApp;
+#[cfg(feature = "gui")]
 pub use app::App;
+pub use app::Simulation;
@@ -1,15 +1,18 @@
-mod app;
-mod handler;
-mod helpers;
-mod ui;
+use grav::helpers;
 
 fn main() {
+    // the same module is loaded in the Web Worker that runs the simulation, which has no window
+    #[cfg(target_arch = "wasm32")]
+    if web_sys::window().is_none() {
+        return;
+    }
+
     env_logger::builder().format_timestamp(None).init();
 
     let app = {
         #[cfg(feature = "simple")]
-        app::orbital::Orbital::new()
-        // app::simple::ConstAcceleration::new()
+        grav::OrbitalApp::new()
+        // grav::app::simple::ConstAcceleration::new()
     };
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -17,3 +20,10 @@ fn main() {
     #[cfg(target_arch = "wasm32")]
     helpers::start(app);
 }
+
+// called by web/worker.js
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+pub fn run_in_worker(checkpoint: &str, ticks: usize) {
+    grav::app::orbital::background::run_in_worker(checkpoint, ticks);
+}
@@ -1,9 +1,25 @@
+use std::f32::consts::PI;
+
 use grav::{
     app::{
-        core::physics::Position,
-        orbital::{body::Body, Orbital},
+        core::physics::{
+            circular_velocity, co_orbital_velocity, Integrator, Position, Velocity, G_KM,
+        },
+        orbital::{
+            binding::Binding,
+            body::Body,
+            checkpoint::Checkpoint,
+            events::{EventCondition, EventKind},
+            export::{ExportFormat, ExportSettings},
+            frame::JacobiSubject,
+            generators::{GalaxySettings, RingSettings},
+            poincare::SectionFrame,
+            resonance::Libration,
+            view::View,
+            Orbital,
+        },
     },
-    App,
+    Simulation,
 };
 
 /// Test that Earth completes approximately one orbit around the Sun
@@ -122,6 +138,767 @@ fn test_all_presets_runnable() {
     }
 }
 
+/// Test that apsides of an elliptical orbit are detected alternately,
+/// and that a time event is found at the exact time within a tick
+#[test]
+fn test_events_detected() {
+    // unequal binary has a slightly elliptical orbit
+    let mut app = load_preset(5);
+    app.events.add_condition(EventCondition::Apsis { body: 0 });
+    let target_t = 12345.6;
+    app.events
+        .add_condition(EventCondition::Time { t: target_t });
+    app.start();
+
+    for _ in 0..100 {
+        app.run();
+    }
+
+    let apsides: Vec<_> = app
+        .events
+        .log
+        .iter()
+        .filter(|e| matches!(e.kind, EventKind::Periapsis | EventKind::Apoapsis))
+        .collect();
+    assert!(apsides.len() >= 4, "found {} apsides", apsides.len());
+
+    for pair in apsides.windows(2) {
+        assert_ne!(pair[0].kind, pair[1].kind, "apsides should alternate");
+        assert!(pair[0].t < pair[1].t);
+    }
+
+    let periapsis = apsides
+        .iter()
+        .find(|e| e.kind == EventKind::Periapsis)
+        .unwrap();
+    let apoapsis = apsides
+        .iter()
+        .find(|e| e.kind == EventKind::Apoapsis)
+        .unwrap();
+    assert!(periapsis.distance().unwrap() < apoapsis.distance().unwrap());
+
+    let time_events: Vec<_> = app
+        .events
+        .log
+        .iter()
+        .filter(|e| e.kind == EventKind::Time)
+        .collect();
+    assert_eq!(time_events.len(), 1);
+    assert!((time_events[0].t - target_t).abs() < 0.01);
+}
+
+/// Test that the simulation stops at an event when pause is enabled
+#[test]
+fn test_pause_on_event() {
+    let mut app = load_preset(5);
+    app.events.add_condition(EventCondition::Time { t: 500. });
+    app.events.pause_on_event = true;
+    app.start();
+
+    app.run();
+
+    assert_eq!(app.events.log.len(), 1);
+    assert!(app.t >= 500. && app.t < 500. + app.dt);
+}
+
+/// Test that a particle placed at the Earth-Moon L4 point stays close to L4 for a month
+#[test]
+fn test_l4_particle_stays_near_l4() {
+    let mut app = load_preset(2);
+    app.add_lagrange_particle(0, 1, 3);
+    app.set_velocities();
+    app.refresh_hierarchy();
+    app.set_velocities();
+    app.start();
+
+    app.dt = 10.;
+    app.num_ticks = 10000;
+    let month = 27.3 * 24. * 60. * 60.;
+    while app.t < month {
+        app.run();
+    }
+
+    // only a test particle, Earth and Moon are the only bodies
+    assert_eq!(
+        app.bodies_vec().iter().filter(|b| !b.is_barycenter).count(),
+        2
+    );
+    let particle_pos = app.particles.position(0);
+    let bodies = get_bodies_snapshot(&app);
+    let earth_pos = find_body_position(bodies.clone(), "Earth");
+    let moon_pos = find_body_position(bodies, "Moon");
+
+    let l4 = app.lagrange_points(0, 1).unwrap()[3];
+    let separation = earth_pos.abs_diff(moon_pos);
+    let drift = particle_pos.abs_diff(l4);
+    println!("drift from L4: {:.0} km", drift);
+
+    assert!(
+        drift < separation * 0.1,
+        "particle drifted {:.0} km from L4",
+        drift
+    );
+}
+
+/// Test that in the frame rotating with Earth and Moon, both stay in place
+/// and the Jacobi constant of a test particle is conserved
+#[test]
+fn test_corotating_frame() {
+    let mut app = load_preset(2);
+    app.add_lagrange_particle(0, 1, 4);
+    app.set_velocities();
+    app.refresh_hierarchy();
+    app.set_velocities();
+    app.start();
+
+    let initial_frame = app.rotating_frame(0, 1).unwrap();
+    let initial_moon =
+        initial_frame.position(find_body_position(get_bodies_snapshot(&app), "Moon"));
+    let initial_jacobi = app
+        .jacobi_constant(0, 1, JacobiSubject::Particle(0))
+        .unwrap();
+    assert_eq!(app.jacobi_constant(0, 1, JacobiSubject::Particle(1)), None);
+
+    app.dt = 10.;
+    app.num_ticks = 10000;
+    for _ in 0..12 {
+        app.run();
+    }
+
+    let frame = app.rotating_frame(0, 1).unwrap();
+    let moon = frame.position(find_body_position(get_bodies_snapshot(&app), "Moon"));
+    let jacobi = app
+        .jacobi_constant(0, 1, JacobiSubject::Particle(0))
+        .unwrap();
+
+    assert!(moon.abs_diff(initial_moon) < initial_moon.mag() * 0.01);
+    assert!(moon.y.abs() < 1.);
+    assert!(
+        ((jacobi - initial_jacobi) / initial_jacobi).abs() < 0.01,
+        "Jacobi constant changed from {} to {}",
+        initial_jacobi,
+        jacobi
+    );
+}
+
+#[test]
+fn test_laplace_resonance() {
+    let mut app = load_preset(6);
+    app.start();
+
+    // about 60 days
+    for _ in 0..26 {
+        app.run();
+    }
+
+    let tracked = &app.resonances.tracked;
+    for r in tracked.iter() {
+        println!(
+            "{} {} {:?} {:?}",
+            r.names.join(" : "),
+            r.label(),
+            r.current_angle(),
+            r.libration()
+        );
+    }
+
+    let find = |names: &[&str]| {
+        tracked
+            .iter()
+            .find(|r| r.names == names)
+            .unwrap_or_else(|| panic!("{:?} not detected", names))
+    };
+    assert_eq!(find(&["Io", "Europa"]).ratio, Some((2, 1)));
+    assert_eq!(find(&["Europa", "Ganymede"]).ratio, Some((2, 1)));
+
+    let laplace = find(&["Io", "Europa", "Ganymede"]);
+    assert_eq!(laplace.label(), "Laplace (1, -3, 2)");
+    match laplace.libration() {
+        Libration::Librating { center, .. } => assert!((center - 180.).abs() < 20.),
+        state => panic!("Laplace angle not librating: {:?}", state),
+    }
+}
+
+/// Test that the Moon crosses the section once per orbit, on the section line,
+/// and that the points are exported as CSV
+#[test]
+fn test_poincare_section() {
+    let mut app = load_preset(2);
+    app.poincare.enabled = true;
+    app.poincare.body = 1;
+    app.poincare.frame = SectionFrame::Inertial;
+    app.start();
+
+    let moon_distance = find_body_position(get_bodies_snapshot(&app), "Moon").mag();
+
+    // about 2 months
+    app.dt = 60.;
+    app.num_ticks = 1440;
+    for _ in 0..60 {
+        app.run();
+    }
+
+    let points = &app.poincare.points;
+    assert_eq!(points.len(), 2);
+    for p in points.iter() {
+        assert!(p.pos.y.abs() < 1., "not on the section line: {:?}", p.pos);
+        assert!(p.vel.y > 0.);
+        assert!((p.pos.x.abs() - moon_distance).abs() < moon_distance * 0.02);
+    }
+    // T = 2 pi sqrt(r^3 / G (M + m))
+    let expected_period =
+        2. * PI * (moon_distance.powi(3) / (G_KM * 1e-3 * (5.97e24 + 7.34e22))).sqrt();
+    let period = points[1].t - points[0].t;
+    assert!(
+        (period / expected_period - 1.).abs() < 0.01,
+        "period: {} s, expected {} s",
+        period,
+        expected_period
+    );
+
+    let csv = app.poincare.to_csv();
+    assert!(csv.starts_with("t,x,y,vx,vy\n"));
+    assert_eq!(csv.lines().count(), 3);
+}
+
+/// Test that MEGNO separates a particle at the unstable L1 point from a particle at the stable L4 point
+#[test]
+fn test_chaos_indicators() {
+    let indicators = |point: usize| {
+        let mut app = load_preset(2);
+        // a light body rather than a test particle, the variational equations only cover bodies
+        let (earth, moon) = (&app.bodies_vec()[0], &app.bodies_vec()[1]);
+        let pos = app.lagrange_points(0, 1).unwrap()[point];
+        let vel = co_orbital_velocity(
+            earth.mass,
+            earth.absolute_pos,
+            earth.absolute_vel,
+            moon.mass,
+            moon.absolute_pos,
+            moon.absolute_vel,
+            pos,
+        );
+        app.add_body(Body {
+            name: format!("L{}", point + 1),
+            mass: 1.,
+            absolute_pos: pos,
+            absolute_vel: vel,
+            ..Body::default()
+        });
+        app.set_velocities();
+        app.refresh_hierarchy();
+        app.set_velocities();
+        app.chaos.enabled = true;
+        app.start();
+
+        // 20 days
+        app.dt = 10.;
+        app.num_ticks = 8640;
+        for _ in 0..20 {
+            app.run();
+        }
+        app.chaos_indicators().unwrap()
+    };
+
+    let l1 = indicators(0);
+    let l4 = indicators(3);
+    println!("L1: {:?}\nL4: {:?}", l1, l4);
+
+    assert!(l1.mean_megno > 4.);
+    assert!(l4.mean_megno < 3.);
+    assert!(l1.lyapunov_exponent > 3. * l4.lyapunov_exponent);
+}
+
+/// Test that test particles orbit the Earth without affecting the Moon
+#[test]
+fn test_particles_exert_no_gravity() {
+    let run = |num_particles: usize| {
+        let mut app = load_preset(2);
+        let earth = find_body_position(get_bodies_snapshot(&app), "Earth");
+        let earth_vel = get_bodies_snapshot(&app)
+            .iter()
+            .find(|b| b.name == "Earth")
+            .unwrap()
+            .absolute_vel;
+        for i in 0..num_particles {
+            let angle = i as f32 * 0.01;
+            let r = 10000. + i as f32;
+            let pos = earth.add(Position::new(r * angle.cos(), r * angle.sin()));
+            let vel = circular_velocity(earth, 5.97e24, pos).add(earth_vel);
+            app.particles.add(pos, vel, (255, 255, 255));
+        }
+        app.start();
+
+        app.dt = 1.;
+        app.num_ticks = 1000;
+        app.run();
+        app
+    };
+
+    let without = run(0);
+    let with = run(10000);
+
+    let moon = |app: &Orbital| find_body_position(get_bodies_snapshot(app), "Moon");
+    assert_eq!(moon(&without), moon(&with));
+
+    let earth = find_body_position(get_bodies_snapshot(&with), "Earth");
+    assert_eq!(with.particles.num_active(), 10000);
+    for i in 0..with.particles.len() {
+        let r = with.particles.position(i).abs_diff(earth);
+        assert!(
+            (r - (10000. + i as f32)).abs() < 10.,
+            "particle {} at {} km",
+            i,
+            r
+        );
+    }
+}
+
+/// Test that a ring added to the Earth keeps orbiting within its radii,
+/// and that the Saturn preset comes with its ring
+#[test]
+fn test_ring_generator() {
+    let mut app = load_preset(0);
+    let settings = RingSettings {
+        inner_radius: 10000.,
+        outer_radius: 12000.,
+        count: 200,
+        dispersion: 0.,
+        seed: 3,
+        ..RingSettings::default()
+    };
+    app.add_ring(0, &settings);
+    assert_eq!(app.particles.len(), 200);
+
+    app.start();
+    app.dt = 5.;
+    app.num_ticks = 1000;
+    for _ in 0..5 {
+        app.run();
+    }
+
+    let earth = find_body_position(get_bodies_snapshot(&app), "Earth");
+    for i in 0..app.particles.len() {
+        let r = app.particles.position(i).abs_diff(earth);
+        assert!(r > 9950. && r < 12050., "particle {} at {} km", i, r);
+    }
+
+    let saturn = load_preset(7);
+    assert_eq!(saturn.particles.len(), 5000);
+}
+
+/// Test that a close prograde encounter between two disk galaxies pulls out tidal tails,
+/// with the disks as test particles so only the two cores are bodies
+#[test]
+fn test_galaxy_collision() {
+    let mut app = Orbital::new();
+    app.load_galaxy_collision(7.5e7, 1.1);
+    let cores = get_bodies_snapshot(&app)
+        .iter()
+        .filter(|b| !b.is_barycenter)
+        .count();
+    assert_eq!(cores, 2);
+    assert_eq!(app.particles.len(), 6000);
+
+    app.start();
+    app.num_ticks = 500;
+    for _ in 0..8 {
+        app.run();
+    }
+
+    let bodies = get_bodies_snapshot(&app);
+    let cores = [
+        find_body_position(bodies.clone(), "Galaxy 1"),
+        find_body_position(bodies, "Galaxy 2"),
+    ];
+    let disk_radius = GalaxySettings::default().ring.outer_radius;
+    let in_tails = (0..app.particles.len())
+        .filter(|&i| {
+            let pos = app.particles.position(i);
+            cores
+                .iter()
+                .all(|&core| pos.abs_diff(core) > 2. * disk_radius)
+        })
+        .count();
+    assert!(in_tails > 100, "{} particles in tidal tails", in_tails);
+}
+
+/// Test that the Plummer cluster starts in virial equilibrium and stays near it,
+/// with every star a sibling of the others and softening keeping the energy error small
+#[test]
+fn test_plummer_cluster() {
+    let mut app = load_preset(9);
+    let bodies = get_bodies_snapshot(&app);
+    let barycenters = bodies.iter().filter(|b| b.is_barycenter).count();
+    assert_eq!(barycenters, 1);
+    assert_eq!(bodies.len(), 101);
+    assert!(app.softening > 0.);
+
+    app.start();
+    let initial = *app.cluster.latest().unwrap();
+    assert!((initial.virial_ratio - 0.5).abs() < 0.01);
+
+    for _ in 0..4 {
+        app.run();
+    }
+
+    let drift = (app.analysis.total_e / app.analysis.initial_e - 1.).abs();
+    assert!(drift < 0.01, "energy drift {}", drift);
+    assert!(app.cluster.samples.len() > 40);
+    for sample in app.cluster.samples.iter() {
+        assert!(
+            sample.virial_ratio > 0.35 && sample.virial_ratio < 0.7,
+            "virial ratio {} at t = {}",
+            sample.virial_ratio,
+            sample.t
+        );
+    }
+}
+
+/// Test that the body flung out of the three body preset is logged as an escape,
+/// is removed once it's far enough away, and comes back after a reset with the conditions on it
+#[test]
+fn test_escaper_removed() {
+    let mut app = load_preset(3);
+    let initial = find_body_position(get_bodies_snapshot(&app), "1");
+    app.escapes.enabled = true;
+    app.escapes.remove_escapers = true;
+    app.escapes.removal_distance = 5e4;
+    let conditions = vec![
+        EventCondition::Apsis { body: 0 },
+        EventCondition::ClosestApproach { body1: 1, body2: 2 },
+        EventCondition::ClosestApproach { body1: 0, body2: 2 },
+    ];
+    for &condition in conditions.iter() {
+        app.events.add_condition(condition);
+    }
+    app.poincare.enabled = true;
+    app.poincare.body = 0;
+
+    app.start();
+    app.num_ticks = 10000;
+    for _ in 0..40 {
+        app.run();
+    }
+
+    let escape = app
+        .events
+        .log
+        .iter()
+        .find(|e| e.kind == EventKind::Escape { system: true } && e.bodies[0].name == "1");
+    assert!(escape.is_some(), "no escape logged");
+
+    let names: Vec<String> = get_bodies_snapshot(&app)
+        .iter()
+        .filter(|b| !b.is_barycenter)
+        .map(|b| b.name.clone())
+        .collect();
+    assert_eq!(names, vec!["2", "3"]);
+    assert_eq!(
+        app.events.conditions,
+        vec![EventCondition::ClosestApproach { body1: 0, body2: 1 }]
+    );
+    assert!(!app.poincare.enabled);
+    // the remaining pair is a bound binary
+    for classification in app.escapes.classifications.iter().flatten() {
+        assert_eq!(classification.system, Binding::Bound);
+    }
+
+    app.reset();
+    let bodies = get_bodies_snapshot(&app);
+    assert_eq!(bodies[0].name, "1");
+    assert_eq!(find_body_position(bodies, "1"), initial);
+
+    let mut restored = app.events.conditions.clone();
+    restored.sort_by_key(|c| format!("{:?}", c));
+    let mut expected = conditions;
+    expected.sort_by_key(|c| format!("{:?}", c));
+    assert_eq!(restored, expected);
+    assert!(app.poincare.enabled);
+    assert_eq!(app.poincare.body, 0);
+}
+
+#[test]
+fn test_timeline_rewind_and_resume() {
+    // particles are replayed with the integrator they were run with
+    for integrator in [Integrator::SymplecticEuler, Integrator::VelocityVerlet] {
+        let mut app = load_preset(2);
+        app.integrator = integrator;
+        let settings = RingSettings {
+            count: 50,
+            dispersion: 0.05,
+            ..RingSettings::default()
+        };
+        app.add_ring(0, &settings);
+        app.start();
+        app.num_ticks = 1000;
+
+        let snapshot = |app: &Orbital| {
+            let positions: Vec<Position> = get_bodies_snapshot(app)
+                .iter()
+                .map(|b| b.absolute_pos)
+                .collect();
+            let particles: Vec<Position> = (0..app.particles.len())
+                .map(|i| app.particles.position(i))
+                .collect();
+            (app.t, positions, particles)
+        };
+
+        app.run();
+        let middle = snapshot(&app);
+        app.run();
+        let end = snapshot(&app);
+        assert_eq!(app.timeline.last_tick(), 2000);
+
+        // scrub back to the middle and forward to the end again
+        app.seek(1000);
+        assert!(app.timeline.is_rewound());
+        assert_eq!(snapshot(&app), middle);
+        app.seek(500);
+        app.seek(2000);
+        assert!(!app.timeline.is_rewound());
+        assert_eq!(snapshot(&app), end);
+
+        // running from the middle discards the rest and ends up in the same place
+        app.seek(1000);
+        app.run();
+        assert_eq!(app.timeline.last_tick(), 2000);
+        assert_eq!(snapshot(&app), end);
+        for body in get_bodies_snapshot(&app)
+            .iter()
+            .filter(|b| !b.is_barycenter)
+        {
+            assert_eq!(body.trajectory.len(), 2001);
+        }
+    }
+}
+
+#[test]
+fn test_branch_divergence() {
+    let mut app = load_preset(1);
+    app.start();
+    app.num_ticks = 1000;
+    app.run();
+
+    app.fork("same");
+    app.fork("nudged");
+    let moon = get_bodies_snapshot(&app)
+        .iter()
+        .position(|b| b.name == "Moon")
+        .unwrap();
+    app.branches[1].sim.nudge(moon, Velocity::new(0.01, 0.));
+    for _ in 0..5 {
+        app.run();
+    }
+
+    // an unchanged branch runs in lockstep and stays exactly the same
+    assert_eq!(app.branches[0].sim.t, app.t);
+    for d in app.branch_divergence(0) {
+        assert_eq!(d.distance, 0., "{}", d.name);
+        assert_eq!(d.velocity, 0., "{}", d.name);
+    }
+
+    let nudged = app.branch_divergence(1);
+    let moon = nudged.iter().find(|d| d.name == "Moon").unwrap();
+    assert!(
+        moon.distance > 10.,
+        "moon only diverged {} km",
+        moon.distance
+    );
+    assert!(moon.velocity > 0.);
+    assert_eq!(app.branches[1].sim.timeline.last_tick(), 6000);
+}
+
+#[test]
+fn test_checkpoint_resumes_identically() {
+    let mut app = load_preset(3);
+    app.start();
+    app.num_ticks = 2000;
+    app.run();
+
+    let with_trajectories = Checkpoint::from_json(&app.checkpoint(true).to_json()).unwrap();
+    let without_trajectories = Checkpoint::from_json(&app.checkpoint(false).to_json()).unwrap();
+
+    let mut loaded = Orbital::new();
+    loaded.load_checkpoint(with_trajectories);
+    assert_eq!(loaded.t, app.t);
+    assert_eq!(loaded.analysis.total_e, app.analysis.total_e);
+    assert_eq!(loaded.timeline.last_tick(), 2000);
+
+    let mut loaded_short = Orbital::new();
+    loaded_short.load_checkpoint(without_trajectories);
+    assert_eq!(loaded_short.timeline.last_tick(), 0);
+
+    app.run();
+    loaded.run();
+    loaded_short.run();
+    let positions = |app: &Orbital| -> Vec<Position> {
+        get_bodies_snapshot(app)
+            .iter()
+            .map(|b| b.absolute_pos)
+            .collect()
+    };
+    assert_eq!(positions(&loaded), positions(&app));
+    assert_eq!(positions(&loaded_short), positions(&app));
+    assert_eq!(loaded.analysis.initial_e, app.analysis.initial_e);
+
+    // the saved trajectories can still be rewound
+    loaded.seek(1000);
+    app.seek(1000);
+    assert_eq!(positions(&loaded), positions(&app));
+}
+
+/// Test importing a Horizons export: Earth and Moon in km/s and Mars in AU/day around the Sun.
+/// The Moon should stay with the Earth, as a check that the velocities are in the right units.
+#[test]
+fn test_horizons_import() {
+    let tables = std::fs::read_to_string("tests/data/horizons_sample.txt").unwrap();
+    let mut app = Orbital::new();
+    assert_eq!(app.import_horizons(&tables), Ok(4));
+
+    let bodies: Vec<&Body> = get_bodies_snapshot(&app)
+        .into_iter()
+        .filter(|b| !b.is_barycenter)
+        .collect();
+    let names: Vec<&str> = bodies.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, ["Sun", "Earth", "Moon", "Mars"]);
+    assert_eq!(bodies[0].absolute_pos, Position::new(0., 0.));
+    assert_eq!(
+        bodies[1].absolute_pos,
+        // the km values in the file, rounded to f32
+        Position::new(
+            -2.481648843478595e7_f64 as f32,
+            1.449956966502923e8_f64 as f32
+        )
+    );
+    assert_eq!(bodies[2].radius, 1737.4);
+
+    let mars = bodies[3];
+    assert!(
+        (mars.absolute_pos.x + 4.393e7).abs() < 1e4,
+        "{:?}",
+        mars.absolute_pos
+    );
+    assert!(
+        (mars.absolute_pos.y + 2.1708e8).abs() < 1e4,
+        "{:?}",
+        mars.absolute_pos
+    );
+    assert!(
+        (mars.absolute_vel.mag() - 24.81).abs() < 0.01,
+        "{:?}",
+        mars.absolute_vel
+    );
+
+    // about two months
+    app.start();
+    app.num_ticks = 5000;
+    for _ in 0..5 {
+        app.run();
+    }
+    assert!(app.t > 40. * 86400., "t = {}", app.t);
+    let bodies: Vec<&Body> = get_bodies_snapshot(&app)
+        .into_iter()
+        .filter(|b| !b.is_barycenter)
+        .collect();
+    let earth_moon = bodies[2].absolute_pos.minus(bodies[1].absolute_pos).mag();
+    assert!(
+        (3.5e5..4.2e5).contains(&earth_moon),
+        "Earth-Moon distance {}",
+        earth_moon
+    );
+}
+
+/// Test importing the ISS, a GPS satellite and a geostationary satellite from TLEs.
+/// Each should start at the distance its mean motion implies, and the ISS should stay in a low orbit.
+#[test]
+fn test_tle_import() {
+    let text = std::fs::read_to_string("tests/data/satellites.tle").unwrap();
+    let mut app = Orbital::new();
+    assert_eq!(app.import_tle(&text), Ok(3));
+
+    let bodies: Vec<&Body> = get_bodies_snapshot(&app)
+        .into_iter()
+        .filter(|b| !b.is_barycenter)
+        .collect();
+    let names: Vec<&str> = bodies.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Earth",
+            "ISS (ZARYA)",
+            "GPS BIIR-2  (PRN 13)",
+            "INTELSAT 10-02"
+        ]
+    );
+    let distances: Vec<f32> = bodies.iter().map(|b| b.absolute_pos.mag()).collect();
+    // semi-major axes from the mean motions, and the eccentricities are small
+    assert!((distances[1] - 6720.).abs() < 10., "ISS {}", distances[1]);
+    assert!((distances[2] - 26560.).abs() < 20., "GPS {}", distances[2]);
+    assert!((distances[3] - 42164.).abs() < 20., "GEO {}", distances[3]);
+
+    // about two ISS orbits
+    app.start();
+    app.num_ticks = 2 * (5500. / app.dt) as i32;
+    app.run();
+    let bodies: Vec<&Body> = get_bodies_snapshot(&app)
+        .into_iter()
+        .filter(|b| !b.is_barycenter)
+        .collect();
+    let iss_distance = bodies[1].absolute_pos.minus(bodies[0].absolute_pos).mag();
+    assert!((iss_distance - 6720.).abs() < 20., "ISS {}", iss_distance);
+}
+
+/// Test exporting samples every 100 s: the last sample matches the current state and energy,
+/// and both formats have a row per sample.
+#[test]
+fn test_export_samples() {
+    let mut app = load_preset(3);
+    app.start();
+    app.num_ticks = 2000;
+    app.run();
+
+    let samples = app.body_samples(100., false);
+    let metrics = app.metrics_samples(100.);
+    // ticks 0, 100, ..., 2000
+    assert_eq!(metrics.len(), 21);
+    assert_eq!(samples.len(), 21 * 3);
+    assert_eq!(metrics[0].energy_error, 0.);
+    assert_eq!(metrics[20].t, app.t);
+    assert_eq!(metrics[20].total_e, app.analysis.total_e);
+
+    let bodies: Vec<&Body> = get_bodies_snapshot(&app)
+        .into_iter()
+        .filter(|b| !b.is_barycenter)
+        .collect();
+    for (sample, body) in samples[60..].iter().zip(bodies) {
+        assert_eq!(sample.body, body.name);
+        assert_eq!(
+            (sample.x, sample.y),
+            (body.absolute_pos.x, body.absolute_pos.y)
+        );
+        assert_eq!((sample.rel_vx, sample.rel_vy), (body.v.x, body.v.y));
+    }
+
+    let csv = app.export(&ExportSettings {
+        interval: 100.,
+        ..ExportSettings::default()
+    });
+    assert_eq!(csv.bodies.lines().count(), 1 + 21 * 3);
+    assert_eq!(csv.metrics.lines().count(), 1 + 21);
+
+    let json = app.export(&ExportSettings {
+        format: ExportFormat::JsonLines,
+        interval: 100.,
+        include_barycenters: true,
+    });
+    assert_eq!(json.metrics.lines().count(), 21);
+    let first: serde_json::Value =
+        serde_json::from_str(json.bodies.lines().next().unwrap()).unwrap();
+    assert_eq!(first["tick"], 0);
+    assert!(json.bodies.lines().count() > 21 * 3);
+}
+
 fn load_preset(preset_idx: usize) -> Orbital {
     let mut app = Orbital::new();
 
@@ -147,3 +924,208 @@ fn find_body_position(bodies: Vec<&Body>, name: &str) -> Position {
 
     body_opt.unwrap().absolute_pos
 }
+
+#[test]
+fn test_velocity_verlet() {
+    // radius of a circular orbit, which stays constant up to the integrator's error
+    let radius_range = |integrator: Integrator| {
+        let mut app = load_preset(0);
+        app.integrator = integrator;
+        app.dt = 30.;
+        app.num_ticks = 1;
+        app.start();
+        let radius = |app: &Orbital| {
+            let bodies = get_bodies_snapshot(app);
+            find_body_position(bodies.clone(), "Orbiting Object")
+                .abs_diff(find_body_position(bodies, "Earth"))
+        };
+        let (mut min, mut max) = (f32::MAX, f32::MIN);
+        // about one orbit
+        for _ in 0..200 {
+            app.run();
+            min = min.min(radius(&app));
+            max = max.max(radius(&app));
+        }
+        max - min
+    };
+
+    let euler = radius_range(Integrator::SymplecticEuler);
+    let verlet = radius_range(Integrator::VelocityVerlet);
+    println!("radius range: euler {} km, verlet {} km", euler, verlet);
+    assert!(verlet < euler / 10.);
+}
+
+#[test]
+fn test_svg_figure() {
+    let mut app = load_preset(1);
+    app.start();
+    for _ in 0..10 {
+        app.run();
+    }
+
+    let svg = app.svg(&View::default(), 800., 600.);
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    for name in ["Sun", "Earth", "Moon"] {
+        assert!(svg.contains(&format!(">{}</text>", name)));
+    }
+    // a trajectory line through many points for each body
+    let trajectories = svg
+        .lines()
+        .filter(|line| line.contains("fill=\"none\"") && line.matches('L').count() > 10)
+        .count();
+    assert!(trajectories >= 3);
+}
+
+#[cfg(feature = "raster")]
+#[test]
+fn test_png_frames() {
+    use grav::app::orbital::snapshot::FrameSettings;
+
+    let mut app = load_preset(1);
+    app.start();
+    for _ in 0..3 {
+        app.run();
+    }
+    let t = app.t;
+    let earth = find_body_position(get_bodies_snapshot(&app), "Earth");
+
+    let png = app.png(&View::default(), 320, 240).unwrap();
+    assert_eq!(&png[1..4], b"PNG");
+
+    let settings = FrameSettings {
+        width: 160,
+        height: 120,
+        interval: t / 4.,
+    };
+    let mut lit = vec![];
+    let num_frames = app
+        .frames(&View::default(), &settings, |raster| {
+            assert_eq!((raster.width(), raster.height()), (160, 120));
+            lit.push(
+                raster
+                    .rgba()
+                    .chunks(4)
+                    .filter(|p| p[..3] != [0, 0, 0])
+                    .count(),
+            );
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(num_frames, 5);
+    assert_eq!(lit.len(), 5);
+    // the trajectories get longer
+    assert!(lit[4] > lit[0]);
+
+    // back where it was
+    assert_eq!(app.t, t);
+    assert_eq!(
+        find_body_position(get_bodies_snapshot(&app), "Earth"),
+        earth
+    );
+}
+
+#[test]
+fn test_realtime_playback() {
+    let mut app = load_preset(0);
+    app.dt = 1.;
+    // nothing runs until playing
+    assert_eq!(app.play_for(0.1), 0);
+
+    app.play();
+    app.realtime.warp = 100.;
+    // 0.1 s of wall-clock time at 100x
+    assert_eq!(app.play_for(0.05), 5);
+    assert_eq!(app.play_for(0.05), 5);
+    assert_eq!(app.t, 10.);
+    // ticks per press are left alone
+    assert_eq!(app.num_ticks, load_preset(0).num_ticks);
+
+    app.realtime.pause();
+    assert_eq!(app.play_for(0.1), 0);
+    assert_eq!(app.t, 10.);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn test_background_run() {
+    use grav::app::orbital::background::{BackgroundRun, Update};
+
+    fn finish(sim: &mut Orbital, mut run: BackgroundRun) {
+        loop {
+            match run.poll() {
+                Some(Update::Snapshot(snapshot)) => sim.continue_from(snapshot),
+                Some(Update::Finished(snapshot)) => return sim.continue_from(snapshot),
+                Some(Update::Failed(e)) => panic!("{}", e),
+                None => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        }
+    }
+
+    let mut app = load_preset(1);
+    app.start();
+    app.num_ticks = 500;
+    app.run();
+    let mut expected = app.clone();
+    expected.num_ticks = 20_000;
+    expected.run();
+
+    // the history from before the run is kept, and what the run recorded comes after it
+    let mut finished = app.clone();
+    let run = BackgroundRun::start(&mut finished, 20_000).unwrap();
+    finish(&mut finished, run);
+    assert_eq!(finished.t, expected.t);
+    assert_eq!(finished.timeline.last_tick(), expected.timeline.last_tick());
+    for (body, expected) in finished.bodies_vec().iter().zip(expected.bodies_vec()) {
+        assert_eq!(body.absolute_pos, expected.absolute_pos);
+        assert_eq!(body.trajectory.len(), expected.trajectory.len());
+        let positions = |b: &Body| {
+            b.trajectory
+                .iter()
+                .map(|s| s.absolute_pos)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(positions(body), positions(expected));
+    }
+
+    // cancelling doesn't wait for the run
+    let run = BackgroundRun::start(&mut app.clone(), 1_000_000).unwrap();
+    let (done, total) = run.progress();
+    assert!(done < total);
+    run.cancel();
+}
+
+#[test]
+fn test_parallel_same_as_serial() {
+    let run = |mut app: Orbital, parallel: bool| {
+        app.parallel = parallel;
+        app.num_ticks = 200;
+        app.start();
+        // several threads even if the machine only has one
+        #[cfg(feature = "parallel")]
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap()
+            .install(|| app.run());
+        #[cfg(not(feature = "parallel"))]
+        app.run();
+        app
+    };
+
+    // a cluster is one big sibling group, the galaxies have thousands of particles
+    let mut galaxies = Orbital::new();
+    galaxies.load_galaxy_collision(7.5e7, 1.1);
+    for integrator in [Integrator::SymplecticEuler, Integrator::VelocityVerlet] {
+        for mut app in [load_preset(9), galaxies.clone()] {
+            app.integrator = integrator;
+            let (serial, parallel) = (run(app.clone(), false), run(app, true));
+            let positions = |app: &Orbital| -> Vec<Position> {
+                app.bodies_vec().iter().map(|b| b.absolute_pos).collect()
+            };
+            assert_eq!(positions(&serial), positions(&parallel));
+            assert_eq!(serial.particles.x, parallel.particles.x);
+            assert_eq!(serial.particles.vy, parallel.particles.vy);
+        }
+    }
+}
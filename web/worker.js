@@ -0,0 +1,11 @@
+// Runs the simulation off the page's thread, see src/app/orbital/background.rs.
+// main() returns straight away here since there's no window.
+import init, { run_in_worker } from "./generated/grav.js";
+
+const ready = init();
+
+self.onmessage = async (event) => {
+  await ready;
+  const [checkpoint, ticks] = event.data;
+  run_in_worker(checkpoint, ticks);
+};